@@ -52,6 +52,24 @@ class SetWatchMode {
   }
 }
 
+class AddEntry {
+  // Ephemeral entries are removed when the connection that added them is closed
+  constructor (timestamp, tag, data, ephemeral = false) {
+    this._timestamp = timestamp
+    this._tag = tag
+    this._data = Buffer.from(data)
+    this._ephemeral = ephemeral
+  }
+
+  write (socket) {
+    socket.write(UInt8(this._ephemeral ? 27 : 2))
+    socket.write(UInt64(this._timestamp))
+    socket.write(UInt64(this._tag))
+    socket.write(UInt16(this._data.length))
+    socket.write(this._data)
+  }
+}
+
 class Radium {
   constructor (host = '127.0.0.1', port = 3126) {
     this._client = new net.Socket()
@@ -105,6 +123,13 @@ radium.onConnected()
       return radium.action(new SetWatchMode(tag ? WatchMode.Tagged : WatchMode.All, tag))
     }
   })
+  .then(() => {
+    if (!enableWatchMode) {
+      const timestamp = Math.floor(Date.now() / 1000) + 60
+
+      return radium.action(new AddEntry(timestamp, 1, 'lock', true))
+    }
+  })
   .then(() => {
     if (!enableWatchMode) {
       radium.close()
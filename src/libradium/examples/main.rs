@@ -4,6 +4,7 @@ use std::sync::mpsc;
 use std::io::prelude::*;
 use std::io;
 use std::sync::mpsc::{Sender, Receiver};
+use std::time::Duration;
 
 use libradium::{Entry, Timestamp, Core};
 
@@ -15,7 +16,7 @@ struct Listener {
 }
 
 impl libradium::Listener<Data> for Listener {
-    fn on_expired(&self, entries: Entries<Data>) {
+    fn on_expired(&self, entries: Entries<Data>, _: Timestamp) {
         self.tx.send(entries).unwrap();
     }
 }
@@ -29,10 +30,10 @@ fn main() {
     let now = Timestamp::now();
 
     for i in 0..100 {
-        core.add_entry(Entry::gen(now + (i * 10), ())).unwrap();
+        core.add_entry(Entry::gen(now + Duration::from_secs(i * 10), ())).unwrap();
 
         core
-            .add_entry(Entry::gen(now + (i * 10) + 1, ()))
+            .add_entry(Entry::gen(now + Duration::from_millis(i * 10_000 + 250), ()))
             .unwrap();
     }
 
@@ -41,7 +42,7 @@ fn main() {
         let len = entries.len();
 
         for entry in entries {
-            print!("( {:?} )", entry.id().timestamp().sec);
+            print!("( {:?} )", entry.id().timestamp().millis());
         }
 
         if len > 0 {
@@ -0,0 +1,171 @@
+use std::fmt;
+
+/// The `OverflowPolicy` determines what happens to a new entry when a [`Core`] is at its [`Capacity`].
+///
+/// [`Core`]: struct.Core.html
+/// [`Capacity`]: struct.Capacity.html
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OverflowPolicy {
+    /// The new entry is rejected with `CommandError::StorageFull`
+    Reject,
+    /// The entries that expire next are expired early until the new entry fits.
+    /// Recurring entries that are expired early don't re-arm.
+    ExpireNext,
+}
+
+/// A `Capacity` limits the number of entries and the total size of their data a [`Core`] keeps.
+///
+/// The size of the data is determined by a function, since only the user of the [`Core`]
+/// knows which part of its data is worth accounting for.
+///
+/// [`Core`]: struct.Core.html
+pub struct Capacity<T> {
+    max_entries: Option<usize>,
+    max_bytes: Option<usize>,
+    policy: OverflowPolicy,
+    weigh: fn(&T) -> usize,
+}
+
+impl<T> Capacity<T> {
+    /// Creates an unlimited `Capacity` that uses `weigh` to determine the size of an entry's data
+    pub fn new(weigh: fn(&T) -> usize) -> Self {
+        Capacity {
+            max_entries: None,
+            max_bytes: None,
+            policy: OverflowPolicy::Reject,
+            weigh,
+        }
+    }
+
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn policy(mut self, policy: OverflowPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.policy
+    }
+
+    pub fn weigh(&self, data: &T) -> usize {
+        (self.weigh)(data)
+    }
+
+    /// Determines if an entry of `bytes` bytes fits next to `entries` entries of `used_bytes` bytes
+    pub fn fits(&self, entries: usize, used_bytes: usize, bytes: usize) -> bool {
+        self.max_entries.map_or(true, |max| entries < max)
+            && self.max_bytes.map_or(true, |max| used_bytes.saturating_add(bytes) <= max)
+    }
+
+    /// Divides the `Capacity` between `shards` shards, so that the shares add up to the whole `Capacity`.
+    /// The remainder goes to the first shards.
+    pub fn split(&self, shards: usize) -> Vec<Self> {
+        let shards = shards.max(1);
+
+        (0..shards)
+            .map(|shard| {
+                let share = |max: usize| max / shards + if shard < max % shards { 1 } else { 0 };
+
+                Capacity {
+                    max_entries: self.max_entries.map(&share),
+                    max_bytes: self.max_bytes.map(&share),
+                    policy: self.policy,
+                    weigh: self.weigh,
+                }
+            })
+            .collect()
+    }
+
+    /// Determines if an entry of `bytes` bytes would fit into an empty storage
+    pub fn can_fit(&self, bytes: usize) -> bool {
+        self.fits(0, 0, bytes)
+    }
+}
+
+impl<T> Clone for Capacity<T> {
+    fn clone(&self) -> Self {
+        Capacity {
+            max_entries: self.max_entries,
+            max_bytes: self.max_bytes,
+            policy: self.policy,
+            weigh: self.weigh,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Capacity<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Capacity")
+            .field("max_entries", &self.max_entries)
+            .field("max_bytes", &self.max_bytes)
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn len(data: &Vec<u8>) -> usize {
+        data.len()
+    }
+
+    #[test]
+    fn test_unlimited() {
+        let capacity = Capacity::new(len);
+
+        assert!(capacity.fits(usize::max_value() - 1, usize::max_value(), 10));
+    }
+
+    #[test]
+    fn test_max_entries() {
+        let capacity = Capacity::new(len).max_entries(2);
+
+        assert!(capacity.fits(1, 0, 0));
+        assert!(!capacity.fits(2, 0, 0));
+    }
+
+    #[test]
+    fn test_max_bytes() {
+        let capacity = Capacity::new(len).max_bytes(10);
+
+        assert_eq!(3, capacity.weigh(&vec![1, 2, 3]));
+        assert!(capacity.fits(5, 7, 3));
+        assert!(!capacity.fits(5, 8, 3));
+        assert!(!capacity.can_fit(11));
+    }
+
+    #[test]
+    fn test_split() {
+        let shares = Capacity::new(len).max_entries(10).max_bytes(9).split(3);
+
+        assert_eq!(3, shares.len());
+        assert!(shares[0].fits(3, 0, 0));
+        assert!(!shares[0].fits(4, 0, 0));
+        assert!(shares[1].fits(2, 0, 0));
+        assert!(!shares[1].fits(3, 0, 0));
+        assert!(shares[2].fits(2, 0, 0));
+        assert!(!shares[2].fits(3, 0, 0));
+        assert!(shares.iter().all(|share| share.fits(0, 0, 3)));
+        assert!(shares.iter().all(|share| !share.fits(0, 0, 4)));
+    }
+
+    #[test]
+    fn test_split_small() {
+        let shares = Capacity::new(len).max_entries(1).split(4);
+
+        assert!(shares[0].fits(0, 0, 0));
+        assert!(!shares[0].fits(1, 0, 0));
+        assert!(shares[1..].iter().all(|share| !share.fits(0, 0, 0)));
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use super::entry::Timestamp;
+
+/// A function that is called whenever a [`Clock`] is moved by hand.
+/// It returns `false` once it is no longer interested in changes.
+///
+/// [`Clock`]: trait.Clock.html
+pub type Waker = Box<Fn() -> bool + Send>;
+
+/// A `Clock` tells the worker of a [`Core`] what time it is and how long it may sleep
+/// until the next entry expires.
+///
+/// [`Core`]: struct.Core.html
+pub trait Clock: Send {
+    fn now(&self) -> Timestamp;
+
+    /// Returns how long to wait until `deadline` has passed,
+    /// or `None` if the clock doesn't advance on its own.
+    fn timeout(&self, deadline: Timestamp) -> Option<Duration>;
+
+    /// Registers a `waker` that is called when the time changes other than by itself passing.
+    /// Clocks that only follow the real time never call it.
+    fn on_change(&self, _waker: Waker) {}
+}
+
+/// A shared `Clock`, e.g. one that is used by all shards of a [`Core`]
+///
+/// [`Core`]: struct.Core.html
+impl<C: Clock + Sync + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> Timestamp {
+        (**self).now()
+    }
+
+    fn timeout(&self, deadline: Timestamp) -> Option<Duration> {
+        (**self).timeout(deadline)
+    }
+
+    fn on_change(&self, waker: Waker) {
+        (**self).on_change(waker)
+    }
+}
+
+/// A `SystemClock` follows the time of the operating system.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::now()
+    }
+
+    fn timeout(&self, deadline: Timestamp) -> Option<Duration> {
+        let millis = deadline.millis() - Timestamp::now().millis();
+
+        Some(Duration::from_millis(millis.max(0) as u64))
+    }
+}
+
+/// A `ManualClock` only moves when it is told to, so that expiration can be tested
+/// without waiting in real time.
+///
+/// Clones of a `ManualClock` share their time, so a clone can be moved
+/// after the `ManualClock` has been handed to a [`Core`]. Moving the clock wakes up the worker,
+/// which expires all entries that are due at the new time.
+///
+/// [`Core`]: struct.Core.html
+#[derive(Clone)]
+pub struct ManualClock {
+    inner: Arc<Mutex<ManualClockInner>>,
+}
+
+struct ManualClockInner {
+    now: Timestamp,
+    wakers: Vec<Waker>,
+}
+
+impl ManualClock {
+    pub fn new(now: Timestamp) -> Self {
+        ManualClock {
+            inner: Arc::new(Mutex::new(ManualClockInner {
+                now,
+                wakers: Vec::new(),
+            })),
+        }
+    }
+
+    pub fn set(&self, now: Timestamp) {
+        let mut inner = self.inner.lock().unwrap();
+
+        inner.now = now;
+        inner.wakers.retain(|waker| waker());
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        let now = self.now();
+
+        self.set(now + duration);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Timestamp {
+        self.inner.lock().unwrap().now
+    }
+
+    fn timeout(&self, deadline: Timestamp) -> Option<Duration> {
+        if deadline <= self.now() {
+            Some(Duration::from_millis(0))
+        } else {
+            None
+        }
+    }
+
+    fn on_change(&self, waker: Waker) {
+        self.inner.lock().unwrap().wakers.push(waker);
+    }
+}
+
+impl fmt::Debug for ManualClock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ManualClock")
+            .field("now", &self.now())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_advance() {
+        let clock = ManualClock::new(Timestamp::from_millis(1000));
+
+        clock.clone().advance(Duration::from_millis(500));
+
+        assert_eq!(Timestamp::from_millis(1500), clock.now());
+    }
+
+    #[test]
+    fn test_timeout() {
+        let clock = ManualClock::new(Timestamp::from_millis(1000));
+
+        assert_eq!(Some(Duration::from_millis(0)), clock.timeout(Timestamp::from_millis(1000)));
+        assert_eq!(None, clock.timeout(Timestamp::from_millis(1001)));
+    }
+
+    #[test]
+    fn test_wakers() {
+        let clock = ManualClock::new(Timestamp::from_millis(0));
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        {
+            let calls = calls.clone();
+            clock.on_change(Box::new(move || calls.fetch_add(1, Ordering::SeqCst) == 0));
+        }
+
+        clock.set(Timestamp::from_millis(10));
+        clock.set(Timestamp::from_millis(20));
+        clock.set(Timestamp::from_millis(30));
+
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+    }
+}
@@ -1,7 +1,67 @@
-use super::entry::{Entry, EntryId};
+use std::fmt;
+use std::io;
+use std::sync::mpsc;
+use super::entry::{Entry, EntryId, EntryKey, Timestamp};
+use super::core::{CommandResult, KeyConflict, Upserted, ShutdownPolicy};
+use super::storage::Storage;
+use super::capacity::Capacity;
+use super::subscription::Subscriber;
+
+/// The sending half of the channel a [`Command`]'s result is sent back on
+///
+/// [`Command`]: enum.Command.html
+pub type Reply<R> = mpsc::Sender<R>;
 
 #[derive(Debug)]
 pub enum Command<T: Send + 'static> {
-    AddEntry(Entry<T>),
-    RemoveEntry(EntryId),
+    AddEntry(Entry<T>, Reply<CommandResult>),
+    RemoveEntry(EntryId, Reply<Option<Entry<T>>>),
+    /// Adds all entries in one go, replying with the result for each of them
+    AddEntries(Vec<Entry<T>>, Reply<Vec<CommandResult>>),
+    /// Removes all entries in one go, replying with the removed entries
+    RemoveEntries(Vec<EntryId>, Reply<Vec<Option<Entry<T>>>>),
+    /// Adds an entry, resolving a conflict with an entry with the same key by the given policy.
+    /// Replies whether the entry has been added or the existing entry has been kept.
+    Upsert(Entry<T>, KeyConflict, Reply<CommandResult<Upserted>>),
+    RemoveByKey(EntryKey, Reply<Option<Entry<T>>>),
+    /// Moves an entry to a new timestamp, replying with its new id
+    Reschedule(EntryId, Timestamp, Reply<Option<EntryId>>),
+    Snapshot(Reply<io::Result<()>>),
+    SetCapacity(Capacity<T>, Reply<()>),
+    Query(Query<T>),
+    Subscribe(Subscriber<T>, Reply<()>),
+    /// Removes the subscriber with the given id
+    Unsubscribe(u64),
+    /// Wakes up the worker, so that it checks for expired entries, e.g. after its clock has been moved
+    Tick,
+    /// Stops the worker, replying with the entries that are handed back to the caller
+    Shutdown(ShutdownPolicy, Reply<io::Result<Vec<Entry<T>>>>),
+}
+
+/// A `Query` reads from the [`Storage`] on the worker thread and sends its result back on its own.
+///
+/// Queries are closures, so that results can be copied out of the storage
+/// without requiring the data of every [`Core`] to be `Clone`.
+///
+/// [`Storage`]: trait.Storage.html
+/// [`Core`]: struct.Core.html
+pub struct Query<T: Send + 'static>(Box<FnOnce(&Storage<T>) + Send>);
+
+impl<T: Send + 'static> Query<T> {
+    pub fn new<F>(query: F) -> Self
+    where
+        F: FnOnce(&Storage<T>) + Send + 'static,
+    {
+        Query(Box::new(query))
+    }
+
+    pub fn run(self, storage: &Storage<T>) {
+        (self.0)(storage)
+    }
+}
+
+impl<T: Send + 'static> fmt::Debug for Query<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Query")
+    }
 }
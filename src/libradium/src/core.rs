@@ -1,19 +1,35 @@
 use std::thread;
 use std::error;
+use std::io;
 use std::fmt;
-use std::sync::Arc;
+use std::sync::{mpsc, Arc, Mutex};
 
-use super::storage::Storage;
-use super::entry::{Entry, EntryId};
-use super::command::Command;
+use super::storage::{Storage, BTreeStorage};
+use super::entry::{Entry, EntryId, EntryKey, Timestamp};
+use super::command::{Command, Query, Reply};
+use super::capacity::Capacity;
+use super::clock::{Clock, SystemClock};
+use super::subscription::{Subscription, Subscriber};
+#[cfg(feature = "stream")]
+use super::stream::EntryStream;
 use super::worker::{Listener, spawn_worker};
-use super::sync::{channel, Sender, Receiver, SendError};
+use super::sync::{channel, sync_channel, Sender, Receiver, SendError, TrySendError};
 
-pub type CommandResult = Result<(), CommandError>;
+pub type CommandResult<R = ()> = Result<R, CommandError>;
 
 #[derive(Debug)]
 pub enum CommandError {
     SendError,
+    RecvError,
+    StorageError(io::Error),
+    /// An entry with the same id or key already exists
+    DuplicateEntry,
+    /// The entry doesn't fit into the [`Capacity`] of the `Core`
+    ///
+    /// [`Capacity`]: struct.Capacity.html
+    StorageFull,
+    /// The command queue of a bounded `Core` is full. The command can be retried later.
+    QueueFull,
     #[doc(hidden)]
     __NonExhaustive,
 }
@@ -24,10 +40,30 @@ impl<T> From<SendError<T>> for CommandError {
     }
 }
 
+impl<T> From<TrySendError<T>> for CommandError {
+    fn from(err: TrySendError<T>) -> Self {
+        match err {
+            TrySendError::Full(_) => CommandError::QueueFull,
+            TrySendError::Disconnected(_) => CommandError::SendError,
+        }
+    }
+}
+
+impl From<mpsc::RecvError> for CommandError {
+    fn from(_: mpsc::RecvError) -> Self {
+        CommandError::RecvError
+    }
+}
+
 impl fmt::Display for CommandError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             &CommandError::SendError => write!(f, "{}", "error sending command"),
+            &CommandError::RecvError => write!(f, "{}", "error receiving command result"),
+            &CommandError::StorageError(ref err) => write!(f, "storage error: {}", err),
+            &CommandError::DuplicateEntry => write!(f, "{}", "entry already exists"),
+            &CommandError::StorageFull => write!(f, "{}", "storage is full"),
+            &CommandError::QueueFull => write!(f, "{}", "command queue is full"),
             &CommandError::__NonExhaustive => unreachable!(),
         }
     }
@@ -37,17 +73,221 @@ impl error::Error for CommandError {
     fn description(&self) -> &str {
         match self {
             &CommandError::SendError => "error sending command",
+            &CommandError::RecvError => "error receiving command result",
+            &CommandError::StorageError(_) => "storage error",
+            &CommandError::DuplicateEntry => "entry already exists",
+            &CommandError::StorageFull => "storage is full",
+            &CommandError::QueueFull => "command queue is full",
             &CommandError::__NonExhaustive => unreachable!(),
         }
     }
 }
 
+/// The `ShutdownPolicy` determines what happens to the pending entries when a [`Core`] shuts down.
+///
+/// [`Core`]: struct.Core.html
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ShutdownPolicy {
+    /// All pending entries expire immediately and are handed to the listener
+    ExpireAll,
+    /// All pending entries are removed and returned to the caller of `shutdown`
+    Return,
+    /// All pending entries are written to disk, so that they are restored on the next start.
+    /// Fails if the [`Storage`] is not persistent.
+    ///
+    /// [`Storage`]: trait.Storage.html
+    Persist,
+}
+
+/// The `KeyConflict` policy determines what [`upsert_entry`] does if an entry with the same [`EntryKey`] exists already.
+///
+/// [`upsert_entry`]: struct.Core.html#method.upsert_entry
+/// [`EntryKey`]: struct.EntryKey.html
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum KeyConflict {
+    /// The existing entry is replaced, e.g. to push back its deadline each time an event occurs
+    Replace,
+    /// The existing entry is kept, so that retrying the addition has no effect
+    KeepExisting,
+    /// The entry that expires first is kept
+    KeepEarliest,
+}
+
+/// The outcome of [`upsert_entry`]
+///
+/// [`upsert_entry`]: struct.Core.html#method.upsert_entry
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Upserted {
+    /// The new entry has been stored with the given id, replacing the existing entry if there was one
+    Added(EntryId),
+    /// The existing entry with the given id has been kept and the new entry was dropped
+    Kept(EntryId),
+}
+
+impl Upserted {
+    /// Returns the id of the entry that is stored afterwards
+    pub fn id(&self) -> EntryId {
+        match self {
+            &Upserted::Added(id) => id,
+            &Upserted::Kept(id) => id,
+        }
+    }
+}
+
+/// A `Builder` configures a [`Core`] before its workers are spawned.
+///
+/// By default, a [`Core`] keeps its entries in a single [`BTreeStorage`], follows the [`SystemClock`]
+/// and queues an unlimited number of commands.
+///
+/// [`Core`]: struct.Core.html
+/// [`BTreeStorage`]: struct.BTreeStorage.html
+/// [`SystemClock`]: struct.SystemClock.html
+pub struct Builder<T: Send + 'static> {
+    storages: Vec<Box<Storage<T>>>,
+    clock: Arc<Clock + Sync>,
+    queue_len: Option<usize>,
+}
+
+impl<T: Send + 'static> Builder<T> {
+    pub fn new() -> Self {
+        Builder {
+            storages: vec![Box::new(BTreeStorage::new())],
+            clock: Arc::new(SystemClock),
+            queue_len: None,
+        }
+    }
+
+    pub fn storage<S: Storage<T> + 'static>(mut self, storage: S) -> Self {
+        self.storages = vec![Box::new(storage)];
+        self
+    }
+
+    /// Partitions the entries across `shards` worker threads by their id.
+    /// Each shard keeps its entries in the storage that `storage` returns for the index of the shard.
+    pub fn shards<S, F>(mut self, shards: usize, mut storage: F) -> Self
+    where
+        S: Storage<T> + 'static,
+        F: FnMut(usize) -> S,
+    {
+        self.storages = (0..shards.max(1)).map(|shard| Box::new(storage(shard)) as Box<Storage<T>>).collect();
+        self
+    }
+
+    /// Sets the clock, which is shared by all shards
+    pub fn clock<C: Clock + Sync + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Limits the number of commands waiting for the worker of each shard.
+    /// Commands sent while the queue is full fail with `CommandError::QueueFull`.
+    pub fn queue_len(mut self, queue_len: usize) -> Self {
+        self.queue_len = Some(queue_len);
+        self
+    }
+
+    pub fn spawn<L: Listener<T> + 'static>(self, listener: L) -> Core<T> {
+        let listener = Arc::new(Mutex::new(listener));
+        let clock = self.clock;
+        let queue_len = self.queue_len;
+
+        let shards = self
+            .storages
+            .into_iter()
+            .map(|storage| Shard::spawn(storage, clock.clone(), queue_len, Box::new(listener.clone())))
+            .collect();
+
+        Core { shards }
+    }
+}
+
+impl<T: Send + 'static> Default for Builder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A worker thread with its own storage, which keeps the entries whose ids belong to the shard
+struct Shard<T: Send + 'static> {
+    tx: Sender<Command<T>>,
+    /// Taken by the first call to `shutdown`
+    join_handle: Arc<Mutex<Option<thread::JoinHandle<()>>>>,
+    /// Used by the clock to wake up the worker, as long as any clone of the `Core` is alive
+    waker: Arc<Mutex<Sender<Command<T>>>>,
+}
+
+impl<T: Send + 'static> Shard<T> {
+    fn spawn(storage: Box<Storage<T>>, clock: Arc<Clock + Sync>, queue_len: Option<usize>, listener: Box<Listener<T>>) -> Self {
+        let (tx, rx): (Sender<Command<T>>, Receiver<Command<T>>) = match queue_len {
+            Some(queue_len) => sync_channel(queue_len),
+            None => channel(),
+        };
+
+        let waker = Arc::new(Mutex::new(tx.clone()));
+        let weak_waker = Arc::downgrade(&waker);
+
+        // A full queue wakes up the worker anyway
+        clock.on_change(Box::new(move || match weak_waker.upgrade() {
+            Some(waker) => !matches!(waker.lock().unwrap().try_send(Command::Tick), Err(TrySendError::Disconnected(_))),
+            None => false,
+        }));
+
+        let join_handle = spawn_worker(storage, Box::new(clock), rx, listener);
+
+        Shard {
+            tx,
+            join_handle: Arc::new(Mutex::new(Some(join_handle))),
+            waker,
+        }
+    }
+
+    /// Sends a command without waiting for the reply.
+    /// Fails with `CommandError::QueueFull` instead of waiting for room in a bounded queue.
+    fn send<R, F>(&self, command: F) -> CommandResult<mpsc::Receiver<R>>
+    where
+        F: FnOnce(Reply<R>) -> Command<T>,
+    {
+        let (reply, response) = mpsc::channel();
+
+        self.tx.try_send(command(reply))?;
+
+        Ok(response)
+    }
+
+    /// Waits for the worker thread to finish after it has replied to a shutdown
+    fn join(&self) {
+        let join_handle = self.join_handle.lock().unwrap().take();
+
+        if let Some(join_handle) = join_handle {
+            // The worker has already replied, so it can't panic anymore
+            let _ = join_handle.join();
+        }
+    }
+}
+
+impl<T: Send + 'static> Clone for Shard<T> {
+    fn clone(&self) -> Self {
+        Shard {
+            tx: self.tx.clone(),
+            join_handle: self.join_handle.clone(),
+            waker: self.waker.clone(),
+        }
+    }
+}
+
+/// A `Core` stores entries on one or more worker threads and expires them when they are due.
+///
+/// A sharded `Core` assigns each entry to a shard by the sequence part of its [`EntryId`],
+/// so that an entry stays on its shard when it is rescheduled or re-armed.
+/// Entries with an [`EntryKey`] receive an id that belongs to the shard of their key.
+///
+/// [`EntryId`]: struct.EntryId.html
+/// [`EntryKey`]: struct.EntryKey.html
 pub struct Core<T>
 where
     T: Send + 'static,
 {
-    tx: Sender<Command<T>>,
-    join_handle: Arc<thread::JoinHandle<()>>,
+    shards: Vec<Shard<T>>,
 }
 
 impl<T> Clone for Core<T>
@@ -56,8 +296,7 @@ where
 {
     fn clone(&self) -> Self {
         Core {
-            tx: self.tx.clone(),
-            join_handle: self.join_handle.clone(),
+            shards: self.shards.clone(),
         }
     }
 }
@@ -66,29 +305,879 @@ impl<T> Core<T>
 where
     T: Send + 'static,
 {
+    /// Spawns a `Core` that keeps its entries in a [`BTreeStorage`]
+    ///
+    /// [`BTreeStorage`]: struct.BTreeStorage.html
     pub fn spawn<L>(listener: L) -> Self
     where
         L: Listener<T> + 'static,
     {
-        let (tx, rx): (Sender<Command<T>>, Receiver<Command<T>>) = channel();
-        let storage = Storage::new();
-        let join_handle = spawn_worker(storage, rx, Box::new(listener));
+        Self::spawn_with_storage(BTreeStorage::new(), listener)
+    }
 
-        Core {
-            tx,
-            join_handle: Arc::new(join_handle),
-        }
+    /// Spawns a `Core` that keeps its entries in the given [`Storage`]
+    ///
+    /// [`Storage`]: trait.Storage.html
+    pub fn spawn_with_storage<S, L>(storage: S, listener: L) -> Self
+    where
+        S: Storage<T> + 'static,
+        L: Listener<T> + 'static,
+    {
+        Self::spawn_with_clock(storage, SystemClock, listener)
     }
 
+    /// Spawns a `Core` that keeps its entries in the given [`Storage`]
+    /// and expires them according to the given [`Clock`]
+    ///
+    /// [`Storage`]: trait.Storage.html
+    /// [`Clock`]: trait.Clock.html
+    pub fn spawn_with_clock<S, C, L>(storage: S, clock: C, listener: L) -> Self
+    where
+        S: Storage<T> + 'static,
+        C: Clock + Sync + 'static,
+        L: Listener<T> + 'static,
+    {
+        Builder::new().storage(storage).clock(clock).spawn(listener)
+    }
+
+    /// Returns a [`Builder`] to configure a `Core` before spawning it
+    ///
+    /// [`Builder`]: struct.Builder.html
+    pub fn builder() -> Builder<T> {
+        Builder::new()
+    }
+
+    /// Returns the number of shards the entries are partitioned across
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Adds an entry, returning once it has been stored.
+    ///
+    /// Fails with `CommandError::DuplicateEntry` if an entry with the same id or key already exists
+    /// and with `CommandError::StorageFull` if the entry doesn't fit into the [`Capacity`].
+    ///
+    /// [`Capacity`]: struct.Capacity.html
     pub fn add_entry(&self, entry: Entry<T>) -> CommandResult {
-        self.command(Command::AddEntry(entry))
+        let id = entry.id();
+
+        self.request(id, |reply| Command::AddEntry(entry, reply))?
     }
 
-    pub fn remove_entry(&self, id: EntryId) -> CommandResult {
-        self.command(Command::RemoveEntry(id))
+    /// Removes an entry, returning the removed entry
+    /// or `None` if there was no entry with the given id
+    pub fn remove_entry(&self, id: EntryId) -> CommandResult<Option<Entry<T>>> {
+        self.request(id, |reply| Command::RemoveEntry(id, reply))
+    }
+
+    /// Adds an entry, or resolves the conflict with an existing entry with the same [`EntryKey`] by `policy`.
+    /// Returns whether the entry has been added or the existing one has been kept.
+    ///
+    /// A keyed entry is stored on the shard of its key, so it receives a new id from [`EntryId::gen`]
+    /// if its id belongs to another shard. The id it has been added with is returned in [`Upserted::Added`]. Keyed entries should always be added this way,
+    /// because [`add_entry`] and [`add_entries`] can only detect a key that is taken on the shard of the id.
+    ///
+    /// [`EntryKey`]: struct.EntryKey.html
+    /// [`EntryId::gen`]: struct.EntryId.html#method.gen
+    /// [`add_entry`]: #method.add_entry
+    /// [`add_entries`]: #method.add_entries
+    /// [`Upserted::Added`]: enum.Upserted.html#variant.Added
+    pub fn upsert_entry(&self, entry: Entry<T>, policy: KeyConflict) -> CommandResult<Upserted> {
+        let index = match entry.key() {
+            Some(key) => self.key_shard_index(key),
+            None => self.shard_index(entry.id()),
+        };
+
+        let mut id = entry.id();
+
+        while self.shard_index(id) != index {
+            id = EntryId::gen(id.timestamp());
+        }
+
+        let entry = entry.with_id(id);
+
+        self.request_shard(index, |reply| Command::Upsert(entry, policy, reply))?
+    }
+
+    /// Removes the entry with the given key, returning the removed entry
+    /// or `None` if there was no entry with that key
+    pub fn remove_entry_by_key(&self, key: EntryKey) -> CommandResult<Option<Entry<T>>> {
+        let index = self.key_shard_index(&key);
+
+        self.request_shard(index, |reply| Command::RemoveByKey(key, reply))
+    }
+
+    /// Adds several entries at once, returning the result for each of them in the order of `entries`.
+    ///
+    /// Each shard adds its part of the batch in one go, without expiring entries or handling
+    /// other commands in between. If the queue of a shard is full, the parts of the other shards
+    /// may have been added nonetheless.
+    pub fn add_entries(&self, entries: Vec<Entry<T>>) -> CommandResult<Vec<CommandResult>> {
+        self.request_batch(entries, |entry| entry.id(), Command::AddEntries)
+    }
+
+    /// Removes several entries at once, returning the removed entries in the order of `ids`.
+    /// Batches are applied like in [`add_entries`].
+    ///
+    /// [`add_entries`]: #method.add_entries
+    pub fn remove_entries(&self, ids: Vec<EntryId>) -> CommandResult<Vec<Option<Entry<T>>>> {
+        self.request_batch(ids, |id| *id, Command::RemoveEntries)
+    }
+
+    /// Moves an entry to expire at `timestamp` instead, keeping its data.
+    ///
+    /// Returns the new id of the entry or `None` if there was no entry with the given id,
+    /// e.g. because it has already expired.
+    pub fn reschedule(&self, id: EntryId, timestamp: Timestamp) -> CommandResult<Option<EntryId>> {
+        self.request(id, |reply| Command::Reschedule(id, timestamp, reply))
+    }
+
+    /// Writes a snapshot of all entries if the [`Storage`] is persistent
+    ///
+    /// [`Storage`]: trait.Storage.html
+    pub fn snapshot(&self) -> CommandResult {
+        for result in self.request_all(Command::Snapshot)? {
+            result.map_err(CommandError::StorageError)?;
+        }
+
+        Ok(())
+    }
+
+    /// Limits the number and size of the entries that can be added from now on.
+    /// Entries that are already pending are kept, even if they exceed the capacity.
+    ///
+    /// The capacity is divided between the shards, so a sharded `Core` may reject entries
+    /// before the whole capacity is used if the entries are unevenly distributed.
+    pub fn set_capacity(&self, capacity: Capacity<T>) -> CommandResult {
+        let responses = self
+            .shards
+            .iter()
+            .zip(capacity.split(self.shards.len()))
+            .map(|(shard, share)| shard.send(|reply| Command::SetCapacity(share, reply)))
+            .collect::<CommandResult<Vec<_>>>()?;
+
+        for response in responses {
+            response.recv()?;
+        }
+
+        Ok(())
+    }
+
+    /// Stops the worker threads, handling the pending entries according to `policy`,
+    /// and returns once they have finished. Entries are only returned for `ShutdownPolicy::Return`,
+    /// ordered by their id.
+    ///
+    /// All clones of the `Core` fail with `CommandError::SendError` afterwards.
+    /// If the policy can't be applied, the workers of the shards that failed keep running.
+    pub fn shutdown(&self, policy: ShutdownPolicy) -> CommandResult<Vec<Entry<T>>> {
+        let mut responses = Vec::with_capacity(self.shards.len());
+
+        for shard in &self.shards {
+            let (reply, response) = mpsc::channel();
+
+            // Unlike other commands, shutting down waits for room in a bounded queue
+            shard.tx.send(Command::Shutdown(policy, reply))?;
+            responses.push(response);
+        }
+
+        let mut entries = Vec::new();
+        let mut result = Ok(());
+
+        for (shard, response) in self.shards.iter().zip(responses) {
+            match response.recv()? {
+                Ok(shard_entries) => {
+                    entries.extend(shard_entries);
+                    shard.join();
+                }
+                Err(err) => {
+                    if result.is_ok() {
+                        result = Err(CommandError::StorageError(err));
+                    }
+                }
+            }
+        }
+
+        entries.sort_by_key(|entry| entry.id());
+
+        result.map(|_| entries)
+    }
+
+    /// Returns the number of pending entries
+    pub fn count(&self) -> CommandResult<usize> {
+        Ok(self.query_all(|storage| storage.len())?.into_iter().sum())
+    }
+
+    /// Returns the timestamp of the entry that expires next
+    pub fn next_deadline(&self) -> CommandResult<Option<Timestamp>> {
+        Ok(self.query_all(|storage| storage.next_deadline())?.into_iter().flatten().min())
+    }
+
+    /// Returns the senders of all shards, e.g. to unsubscribe from them
+    fn senders(&self) -> Vec<Sender<Command<T>>> {
+        self.shards.iter().map(|shard| shard.tx.clone()).collect()
+    }
+
+    /// Returns the index of the shard the entry with the given id belongs to
+    fn shard_index(&self, id: EntryId) -> usize {
+        (id.id() % self.shards.len() as u64) as usize
+    }
+
+    /// Returns the index of the shard the entry with the given key belongs to,
+    /// using the FNV-1a hash of the key, so that the shard stays the same across restarts
+    fn key_shard_index(&self, key: &EntryKey) -> usize {
+        let hash = key.as_bytes().iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+
+        (hash % self.shards.len() as u64) as usize
+    }
+
+    /// Runs `query` against the storage of the shard of `id` on its worker thread and blocks until it returns
+    fn query<R, F>(&self, id: EntryId, query: F) -> CommandResult<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&Storage<T>) -> R + Send + 'static,
+    {
+        self.query_shard(self.shard_index(id), query)
+    }
+
+    /// Runs `query` against the storage of the shard with the given index and blocks until it returns
+    fn query_shard<R, F>(&self, index: usize, query: F) -> CommandResult<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&Storage<T>) -> R + Send + 'static,
+    {
+        self.request_shard(index, |reply| {
+            Command::Query(Query::new(move |storage| {
+                let _ = reply.send(query(storage));
+            }))
+        })
+    }
+
+    /// Runs `query` against the storages of all shards and returns their results in the order of the shards
+    fn query_all<R, F>(&self, query: F) -> CommandResult<Vec<R>>
+    where
+        R: Send + 'static,
+        F: Fn(&Storage<T>) -> R + Clone + Send + 'static,
+    {
+        self.request_all(|reply| {
+            let query = query.clone();
+
+            Command::Query(Query::new(move |storage| {
+                let _ = reply.send(query(storage));
+            }))
+        })
+    }
+
+    /// Sends a command to the shard of `id` and blocks until its worker has replied.
+    /// Fails with `CommandError::QueueFull` instead of waiting for room in a bounded queue.
+    fn request<R, F>(&self, id: EntryId, command: F) -> CommandResult<R>
+    where
+        F: FnOnce(Reply<R>) -> Command<T>,
+    {
+        self.request_shard(self.shard_index(id), command)
+    }
+
+    fn request_shard<R, F>(&self, index: usize, command: F) -> CommandResult<R>
+    where
+        F: FnOnce(Reply<R>) -> Command<T>,
+    {
+        Ok(self.shards[index].send(command)?.recv()?)
+    }
+
+    /// Sends a command to all shards at once and blocks until all workers have replied
+    fn request_all<R, F>(&self, command: F) -> CommandResult<Vec<R>>
+    where
+        F: Fn(Reply<R>) -> Command<T>,
+    {
+        let responses = self.shards.iter().map(|shard| shard.send(&command)).collect::<CommandResult<Vec<_>>>()?;
+
+        responses.into_iter().map(|response| Ok(response.recv()?)).collect()
+    }
+
+    /// Splits a batch by shard, sends each part to its shard at once
+    /// and blocks until all results have been received, which are returned in the order of the batch
+    fn request_batch<I, R, F>(&self, items: Vec<I>, id: fn(&I) -> EntryId, command: F) -> CommandResult<Vec<R>>
+    where
+        F: Fn(Vec<I>, Reply<Vec<R>>) -> Command<T>,
+    {
+        let len = items.len();
+        let mut parts: Vec<(Vec<usize>, Vec<I>)> = self.shards.iter().map(|_| (Vec::new(), Vec::new())).collect();
+
+        for (index, item) in items.into_iter().enumerate() {
+            let part = &mut parts[self.shard_index(id(&item))];
+
+            part.0.push(index);
+            part.1.push(item);
+        }
+
+        let mut responses = Vec::new();
+
+        for (shard, (indices, items)) in self.shards.iter().zip(parts) {
+            if !items.is_empty() {
+                responses.push((indices, shard.send(|reply| command(items, reply))?));
+            }
+        }
+
+        let mut results: Vec<Option<R>> = (0..len).map(|_| None).collect();
+
+        for (indices, response) in responses {
+            for (index, result) in indices.into_iter().zip(response.recv()?) {
+                results[index] = Some(result);
+            }
+        }
+
+        // A worker that replied with fewer results than it was sent items is as good as gone
+        results.into_iter().collect::<Option<Vec<R>>>().ok_or(CommandError::RecvError)
+    }
+
+    /// Hands one of the subscribers to the worker of each shard
+    fn add_subscribers(&self, subscribers: Vec<Subscriber<T>>) -> CommandResult {
+        let responses = self
+            .shards
+            .iter()
+            .zip(subscribers)
+            .map(|(shard, subscriber)| shard.send(|reply| Command::Subscribe(subscriber, reply)))
+            .collect::<CommandResult<Vec<_>>>()?;
+
+        for response in responses {
+            response.recv()?;
+        }
+
+        Ok(())
     }
+}
+
+impl<T> Core<T>
+where
+    T: Clone + Send + 'static,
+{
+    /// Returns a copy of the entry with the given id, or `None` if it doesn't exist (anymore)
+    pub fn get_entry(&self, id: EntryId) -> CommandResult<Option<Entry<T>>> {
+        self.query(id, move |storage| storage.get_entry(id).cloned())
+    }
+
+    /// Returns a copy of the entry with the given key, or `None` if it doesn't exist (anymore)
+    pub fn get_entry_by_key(&self, key: EntryKey) -> CommandResult<Option<Entry<T>>> {
+        let index = self.key_shard_index(&key);
+
+        self.query_shard(index, move |storage| storage.find_key(&key).and_then(|id| storage.get_entry(id)).cloned())
+    }
+
+    /// Subscribes to the expired entries for which `filter` returns `true`.
+    ///
+    /// Each [`Subscription`] receives its own copy of the matching entries,
+    /// in addition to the listener the `Core` has been spawned with.
+    ///
+    /// [`Subscription`]: struct.Subscription.html
+    pub fn subscribe<F>(&self, filter: F) -> CommandResult<Subscription<T>>
+    where
+        F: Fn(&Entry<T>) -> bool + Send + 'static,
+    {
+        let (subscription, subscribers) = Subscription::new(filter, Entry::clone, self.senders());
+
+        self.add_subscribers(subscribers)?;
+
+        Ok(subscription)
+    }
+
+    /// Returns a [`EntryStream`] of the expired entries for which `filter` returns `true`,
+    /// which pauses the `Core` once it buffers `capacity` entries. The limit is soft,
+    /// as the entries that are due at once are always buffered as a whole.
+    ///
+    /// [`EntryStream`]: struct.EntryStream.html
+    #[cfg(feature = "stream")]
+    pub fn stream<F>(&self, filter: F, capacity: usize) -> CommandResult<EntryStream<T>>
+    where
+        F: Fn(&Entry<T>) -> bool + Send + 'static,
+    {
+        let (stream, subscribers) = EntryStream::new(filter, capacity, Entry::clone, self.senders());
+
+        self.add_subscribers(subscribers)?;
+
+        Ok(stream)
+    }
+
+    /// Returns copies of up to `limit` entries that expire at or after `from` and before `to`, ordered by their id
+    pub fn entries_between(&self, from: Timestamp, to: Timestamp, limit: usize) -> CommandResult<Vec<Entry<T>>> {
+        let mut entries: Vec<Entry<T>> = self
+            .query_all(move |storage| storage.entries_between(from, to, limit).into_iter().cloned().collect::<Vec<_>>())?
+            .into_iter()
+            .flatten()
+            .collect();
+
+        entries.sort_by_key(|entry| entry.id());
+        entries.truncate(limit);
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::time::Duration;
+    use std::sync::mpsc::TryRecvError;
+    use super::*;
+    use super::super::storage::PersistentStorage;
+    use super::super::journal::{SnapshotPolicy, SyncPolicy};
+    use super::super::clock::ManualClock;
+    use super::super::capacity::OverflowPolicy;
+    use super::super::recurrence::{Recurrence, Schedule};
+
+    struct ChannelListener(mpsc::Sender<Vec<Entry<u64>>>);
+
+    impl Listener<u64> for ChannelListener {
+        fn on_expired(&self, entries: Vec<Entry<u64>>, _: Timestamp) {
+            let _ = self.0.send(entries);
+        }
+    }
+
+    #[derive(Debug, Eq, PartialEq)]
+    enum Event {
+        Expired(Vec<EntryId>, Timestamp),
+        Added(EntryId),
+        Removed(EntryId),
+        Rescheduled(EntryId, EntryId),
+        Rejected(EntryId),
+    }
+
+    struct EventListener(mpsc::Sender<Event>);
+
+    impl Listener<u64> for EventListener {
+        fn on_expired(&self, entries: Vec<Entry<u64>>, fired_at: Timestamp) {
+            let _ = self.0.send(Event::Expired(ids(entries), fired_at));
+        }
+
+        fn on_added(&self, entry: &Entry<u64>) {
+            let _ = self.0.send(Event::Added(entry.id()));
+        }
+
+        fn on_removed(&self, entry: &Entry<u64>) {
+            let _ = self.0.send(Event::Removed(entry.id()));
+        }
+
+        fn on_rescheduled(&self, from: EntryId, to: EntryId) {
+            let _ = self.0.send(Event::Rescheduled(from, to));
+        }
+
+        fn on_rejected(&self, entry: &Entry<u64>, _: &CommandError) {
+            let _ = self.0.send(Event::Rejected(entry.id()));
+        }
+    }
+
+    /// Blocks the worker in `on_added` until it is told to go on
+    struct BlockingListener {
+        entered: mpsc::Sender<()>,
+        resume: Mutex<mpsc::Receiver<()>>,
+    }
+
+    impl Listener<u64> for BlockingListener {
+        fn on_expired(&self, _: Vec<Entry<u64>>, _: Timestamp) {}
+
+        fn on_added(&self, _: &Entry<u64>) {
+            let _ = self.entered.send(());
+            let _ = self.resume.lock().unwrap().recv();
+        }
+    }
+
+    fn spawn() -> (Core<u64>, ManualClock, mpsc::Receiver<Vec<Entry<u64>>>) {
+        let clock = ManualClock::new(Timestamp::from_millis(0));
+        let (tx, rx) = mpsc::channel();
+        let core = Core::spawn_with_clock(BTreeStorage::new(), clock.clone(), ChannelListener(tx));
+
+        (core, clock, rx)
+    }
+
+    fn at(millis: i64) -> EntryId {
+        EntryId::new(Timestamp::from_millis(millis), millis as u64)
+    }
+
+    fn ids(entries: Vec<Entry<u64>>) -> Vec<EntryId> {
+        entries.iter().map(|entry| entry.id()).collect()
+    }
+
+    #[test]
+    fn test_expire_when_clock_advances() {
+        let (core, clock, rx) = spawn();
+
+        core.add_entry(Entry::new(at(1000), 1)).unwrap();
+        core.add_entry(Entry::new(at(2000), 2)).unwrap();
+
+        clock.advance(Duration::from_millis(999));
+
+        // Commands are handled in order, so the worker has checked the clock once this returns
+        assert_eq!(2, core.count().unwrap());
+        assert_eq!(Err(TryRecvError::Empty), rx.try_recv().map(ids));
+
+        clock.advance(Duration::from_millis(1));
+
+        assert_eq!(vec![at(1000)], ids(rx.recv().unwrap()));
+        assert_eq!(1, core.count().unwrap());
+    }
+
+    #[test]
+    fn test_past_entry() {
+        let (core, clock, rx) = spawn();
+
+        clock.set(Timestamp::from_millis(5000));
+        core.add_entry(Entry::new(at(1000), 1)).unwrap();
+
+        assert_eq!(vec![at(1000)], ids(rx.recv().unwrap()));
+    }
+
+    #[test]
+    fn test_recurring() {
+        let (core, clock, rx) = spawn();
+        let recurrence = Recurrence::new(Schedule::Interval(Duration::from_millis(100))).times(2);
+
+        core.add_entry(Entry::recurring(at(100), 1, recurrence)).unwrap();
+
+        clock.set(Timestamp::from_millis(100));
+        assert_eq!(1, rx.recv().unwrap()[0].occurrence());
+
+        clock.set(Timestamp::from_millis(200));
+        assert_eq!(2, rx.recv().unwrap()[0].occurrence());
+
+        assert_eq!(0, core.count().unwrap());
+    }
+
+    #[test]
+    fn test_reschedule_recurring() {
+        let (core, clock, rx) = spawn();
+        let recurrence = Recurrence::new(Schedule::Interval(Duration::from_millis(100))).times(3);
+
+        core.add_entry(Entry::recurring(at(100), 1, recurrence)).unwrap();
+
+        clock.set(Timestamp::from_millis(100));
+        assert_eq!(1, rx.recv().unwrap()[0].occurrence());
+
+        let moved = core.reschedule(EntryId::new(Timestamp::from_millis(200), 100), Timestamp::from_millis(500)).unwrap().unwrap();
+
+        assert!(core.get_entry(moved).unwrap().unwrap().recurrence().is_some());
+
+        clock.set(Timestamp::from_millis(500));
+        assert_eq!(2, rx.recv().unwrap()[0].occurrence());
+
+        clock.set(Timestamp::from_millis(600));
+        assert_eq!(3, rx.recv().unwrap()[0].occurrence());
+
+        assert_eq!(0, core.count().unwrap());
+    }
+
+    #[test]
+    fn test_capacity() {
+        let (core, _, rx) = spawn();
+
+        core.set_capacity(Capacity::new(|_| 1).max_entries(1)).unwrap();
+        core.add_entry(Entry::new(at(1000), 1)).unwrap();
+
+        match core.add_entry(Entry::new(at(2000), 2)) {
+            Err(CommandError::StorageFull) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        core.set_capacity(Capacity::new(|_| 1).max_entries(1).policy(OverflowPolicy::ExpireNext)).unwrap();
+        core.add_entry(Entry::new(at(2000), 2)).unwrap();
+
+        assert_eq!(vec![at(1000)], ids(rx.recv().unwrap()));
+        assert_eq!(1, core.count().unwrap());
+    }
+
+    #[test]
+    fn test_capacity_shards() {
+        let core = Core::builder()
+            .shards(4, |_| BTreeStorage::new())
+            .clock(ManualClock::new(Timestamp::from_millis(0)))
+            .spawn(ChannelListener(mpsc::channel().0));
+
+        core.set_capacity(Capacity::new(|_| 1).max_entries(5)).unwrap();
+
+        let added = (1..21).filter(|&i| core.add_entry(Entry::new(at(1000 + i), i as u64)).is_ok()).count();
+
+        assert_eq!(5, added);
+        assert_eq!(5, core.count().unwrap());
+    }
+
+    #[test]
+    fn test_queue_full() {
+        let (entered_tx, entered) = mpsc::channel();
+        let (resume, resume_rx) = mpsc::channel();
+        let listener = BlockingListener { entered: entered_tx, resume: Mutex::new(resume_rx) };
+        let core = Core::builder()
+            .clock(ManualClock::new(Timestamp::from_millis(0)))
+            .queue_len(1)
+            .spawn(listener);
+
+        let adding = {
+            let core = core.clone();
+            ::std::thread::spawn(move || core.add_entry(Entry::new(at(1000), 1)))
+        };
+
+        // The worker is busy with the first entry, so a single command fills the queue
+        entered.recv().unwrap();
+        core.shards[0].tx.try_send(Command::Tick).unwrap();
+
+        match core.count() {
+            Err(CommandError::QueueFull) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        resume.send(()).unwrap();
+        adding.join().unwrap().unwrap();
+
+        // The tick may still be queued, which shutting down waits for, unlike other commands
+        assert_eq!(vec![at(1000)], ids(core.shutdown(ShutdownPolicy::Return).unwrap()));
+    }
+
+    #[test]
+    fn test_listener_hooks() {
+        let clock = ManualClock::new(Timestamp::from_millis(0));
+        let (tx, rx) = mpsc::channel();
+        let core = Core::spawn_with_clock(BTreeStorage::new(), clock.clone(), EventListener(tx));
+        let recurrence = Recurrence::new(Schedule::Interval(Duration::from_millis(100))).times(2);
+
+        core.add_entry(Entry::new(at(1000), 1)).unwrap();
+        assert!(core.add_entry(Entry::new(at(1000), 1)).is_err());
+        core.add_entry(Entry::new(at(2000), 2)).unwrap();
+        core.reschedule(at(2000), Timestamp::from_millis(3000)).unwrap();
+        core.remove_entry(at(1000)).unwrap();
+        core.add_entry(Entry::recurring(at(100), 3, recurrence)).unwrap();
+
+        // The entry fires late, since the clock jumps past its timestamp
+        clock.set(Timestamp::from_millis(150));
+        core.count().unwrap();
+
+        let moved = EntryId::new(Timestamp::from_millis(3000), 2000);
+        let rearmed = EntryId::new(Timestamp::from_millis(200), 100);
+
+        assert_eq!(
+            vec![
+                Event::Added(at(1000)),
+                Event::Rejected(at(1000)),
+                Event::Added(at(2000)),
+                Event::Rescheduled(at(2000), moved),
+                Event::Removed(at(1000)),
+                Event::Added(at(100)),
+                Event::Expired(vec![at(100)], Timestamp::from_millis(150)),
+                Event::Added(rearmed),
+            ],
+            rx.try_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let (core, clock, rx) = spawn();
+        let even = core.subscribe(|entry| entry.data() % 2 == 0).unwrap();
+        let all = core.subscribe(|_| true).unwrap();
+
+        for i in 1..5 {
+            core.add_entry(Entry::new(at(i * 100), i as u64)).unwrap();
+        }
+
+        clock.set(Timestamp::from_millis(400));
+
+        assert_eq!(4, rx.recv().unwrap().len());
+        assert_eq!(vec![2, 4], even.try_iter().map(|entry| *entry.data()).collect::<Vec<_>>());
+        assert_eq!(vec![1, 2, 3, 4], all.try_iter().map(|entry| *entry.data()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_shards() {
+        let clock = ManualClock::new(Timestamp::from_millis(0));
+        let (tx, rx) = mpsc::channel();
+        let core = Core::builder()
+            .shards(3, |_| BTreeStorage::new())
+            .clock(clock.clone())
+            .spawn(ChannelListener(tx));
+        let all = core.subscribe(|_| true).unwrap();
+
+        assert_eq!(3, core.shards());
+
+        for i in 1..7 {
+            core.add_entry(Entry::new(at(i * 100), i as u64)).unwrap();
+        }
+
+        assert!(core.add_entry(Entry::new(at(100), 1)).is_err());
+        assert_eq!(6, core.count().unwrap());
+        assert_eq!(Some(Timestamp::from_millis(100)), core.next_deadline().unwrap());
+        assert_eq!(vec![at(100), at(200), at(300)], ids(core.entries_between(Timestamp::from_millis(0), Timestamp::from_millis(1000), 3).unwrap()));
+
+        let moved = core.reschedule(at(200), Timestamp::from_millis(5000)).unwrap().unwrap();
+
+        assert_eq!(Some(2), core.get_entry(moved).unwrap().map(|entry| *entry.data()));
+        assert!(core.remove_entry(at(300)).unwrap().is_some());
+
+        clock.set(Timestamp::from_millis(600));
+        assert_eq!(1, core.count().unwrap());
+
+        let mut expired: Vec<EntryId> = rx.try_iter().flat_map(ids).collect();
+        let mut received: Vec<u64> = all.try_iter().map(|entry| *entry.data()).collect();
+
+        expired.sort();
+        received.sort();
+
+        assert_eq!(vec![at(100), at(400), at(500), at(600)], expired);
+        assert_eq!(vec![1, 4, 5, 6], received);
+        assert_eq!(vec![moved], ids(core.shutdown(ShutdownPolicy::Return).unwrap()));
+    }
+
+    #[test]
+    fn test_batches() {
+        let (tx, rx) = mpsc::channel();
+        let core = Core::builder()
+            .shards(2, |_| BTreeStorage::new())
+            .clock(ManualClock::new(Timestamp::from_millis(0)))
+            .spawn(EventListener(tx));
+
+        let results = core.add_entries(vec![Entry::new(at(3), 3), Entry::new(at(2), 2), Entry::new(at(3), 3)]).unwrap();
+
+        match results.as_slice() {
+            &[Ok(()), Ok(()), Err(CommandError::DuplicateEntry)] => {}
+            results => panic!("unexpected results {:?}", results),
+        }
+
+        let removed = core.remove_entries(vec![at(2), at(1), at(3)]).unwrap();
+
+        assert_eq!(vec![Some(2), None, Some(3)], removed.iter().map(|entry| entry.as_ref().map(|entry| *entry.data())).collect::<Vec<_>>());
+        assert_eq!(0, core.count().unwrap());
+        assert!(core.add_entries(Vec::new()).unwrap().is_empty());
+
+        // The shards report to the listener independently of each other
+        let events: Vec<Event> = rx.try_iter().collect();
+
+        assert_eq!(5, events.len());
+
+        for event in &[Event::Added(at(2)), Event::Added(at(3)), Event::Rejected(at(3)), Event::Removed(at(2)), Event::Removed(at(3))] {
+            assert!(events.contains(event), "missing {:?}", event);
+        }
+    }
+
+    #[test]
+    fn test_upsert() {
+        let (core, clock, rx) = spawn();
+        let key = EntryKey::new("debounce");
+
+        let first = core.upsert_entry(Entry::new(at(1000), 1).with_key(key.clone()), KeyConflict::Replace).unwrap();
+        let second = core.upsert_entry(Entry::new(at(2000), 2).with_key(key.clone()), KeyConflict::Replace).unwrap();
+
+        assert_eq!(Upserted::Added(at(1000)), first);
+        assert_eq!(Upserted::Added(at(2000)), second);
+        assert_eq!(1, core.count().unwrap());
+
+        assert_eq!(Upserted::Kept(at(2000)), core.upsert_entry(Entry::new(at(500), 3).with_key(key.clone()), KeyConflict::KeepExisting).unwrap());
+        assert_eq!(Upserted::Kept(at(2000)), core.upsert_entry(Entry::new(at(3000), 4).with_key(key.clone()), KeyConflict::KeepEarliest).unwrap());
+        assert_eq!(Upserted::Added(at(1500)), core.upsert_entry(Entry::new(at(1500), 5).with_key(key.clone()), KeyConflict::KeepEarliest).unwrap());
+
+        assert!(core.add_entry(Entry::new(at(4000), 6).with_key(key.clone())).is_err());
+        assert_eq!(Some(5), core.get_entry_by_key(key.clone()).unwrap().map(|entry| *entry.data()));
+
+        clock.set(Timestamp::from_millis(1500));
+        assert_eq!(vec![at(1500)], ids(rx.recv().unwrap()));
+        assert!(core.get_entry_by_key(key.clone()).unwrap().is_none());
+
+        core.upsert_entry(Entry::new(at(5000), 7).with_key(key.clone()), KeyConflict::KeepExisting).unwrap();
+
+        assert_eq!(Some(7), core.remove_entry_by_key(key.clone()).unwrap().map(|entry| *entry.data()));
+        assert!(core.remove_entry_by_key(key).unwrap().is_none());
+        assert_eq!(0, core.count().unwrap());
+    }
+
+    #[test]
+    fn test_upsert_shards() {
+        let core = Core::builder()
+            .shards(3, |_| BTreeStorage::new())
+            .clock(ManualClock::new(Timestamp::from_millis(0)))
+            .spawn(ChannelListener(mpsc::channel().0));
+
+        for i in 0..6 {
+            let entry = Entry::gen(Timestamp::from_millis(1000), i).with_key(EntryKey::new(vec![i as u8]));
+            let id = match core.upsert_entry(entry, KeyConflict::Replace).unwrap() {
+                Upserted::Added(id) => id,
+                upserted => panic!("unexpected result {:?}", upserted),
+            };
+
+            // The entry can be found both by its key and by its possibly regenerated id
+            assert_eq!(Some(id), core.get_entry_by_key(EntryKey::new(vec![i as u8])).unwrap().map(|entry| entry.id()));
+            assert_eq!(Some(i), core.get_entry(id).unwrap().map(|entry| *entry.data()));
+
+            let entry = Entry::gen(Timestamp::from_millis(2000), i).with_key(EntryKey::new(vec![i as u8]));
+            core.upsert_entry(entry, KeyConflict::Replace).unwrap();
+        }
+
+        assert_eq!(6, core.count().unwrap());
+    }
+
+    #[test]
+    fn test_subscription_ends_on_shutdown() {
+        let (core, _, _) = spawn();
+        let subscription = core.subscribe(|_| true).unwrap();
+
+        core.shutdown(ShutdownPolicy::Return).unwrap();
+
+        assert!(subscription.recv().is_err());
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let (core, _, rx) = spawn();
+
+        core.add_entry(Entry::new(at(2000), 2)).unwrap();
+        core.add_entry(Entry::new(at(1000), 1)).unwrap();
+
+        assert_eq!(vec![at(1000), at(2000)], ids(core.shutdown(ShutdownPolicy::Return).unwrap()));
+        assert!(rx.try_recv().is_err());
+
+        match core.count() {
+            Err(CommandError::SendError) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_shutdown_expire_all() {
+        let (core, _, rx) = spawn();
+
+        core.add_entry(Entry::new(at(1000), 1)).unwrap();
+
+        assert!(core.shutdown(ShutdownPolicy::ExpireAll).unwrap().is_empty());
+        assert_eq!(vec![at(1000)], ids(rx.recv().unwrap()));
+    }
+
+    #[test]
+    fn test_periodic_snapshot() {
+        let dir = env::temp_dir().join(format!("libradium-core-test-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut storage = PersistentStorage::open(&dir, SyncPolicy::Never, BTreeStorage::new()).unwrap();
+
+        storage.set_snapshot_policy(SnapshotPolicy { interval: Some(Duration::from_millis(50)), max_journal_len: None });
+
+        let (tx, _rx) = mpsc::channel();
+        let core = Core::spawn_with_clock(storage, ManualClock::new(Timestamp::from_millis(0)), ChannelListener(tx));
+
+        core.add_entry(Entry::new(at(1000), 1)).unwrap();
+
+        // The snapshot is written without any further commands
+        thread::sleep(Duration::from_millis(300));
+
+        assert_eq!(0, fs::metadata(dir.join("journal")).unwrap().len());
+        assert!(fs::metadata(dir.join("snapshot")).unwrap().len() > 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_shutdown_persist() {
+        let (core, _, _) = spawn();
+
+        match core.shutdown(ShutdownPolicy::Persist) {
+            Err(CommandError::StorageError(_)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
 
-    fn command(&self, command: Command<T>) -> CommandResult {
-        Ok(self.tx.send(command)?)
+        assert_eq!(0, core.count().unwrap());
     }
 }
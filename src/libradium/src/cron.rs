@@ -0,0 +1,334 @@
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+use super::entry::Timestamp;
+
+const MILLIS_PER_MINUTE: i64 = 60 * 1_000;
+const MINUTES_PER_DAY: i64 = 24 * 60;
+
+/// Number of years after which a schedule that hasn't fired yet is considered to never fire.
+/// Every combination of a date and a weekday repeats within this period.
+const MAX_YEARS: i64 = 28;
+
+const MONTH_NAMES: &[&str] = &["JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC"];
+const WEEKDAY_NAMES: &[&str] = &["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CronError {
+    /// The expression doesn't consist of exactly five fields
+    FieldCount,
+    /// A field contains a value that is not a number or name
+    InvalidValue,
+    /// A value is out of range for its field
+    OutOfRange,
+    /// A step is zero, larger than the range of its field or not a number
+    InvalidStep,
+}
+
+impl fmt::Display for CronError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", error::Error::description(self))
+    }
+}
+
+impl error::Error for CronError {
+    fn description(&self) -> &str {
+        match self {
+            &CronError::FieldCount => "cron expression must have five fields",
+            &CronError::InvalidValue => "invalid value in cron expression",
+            &CronError::OutOfRange => "value out of range in cron expression",
+            &CronError::InvalidStep => "invalid step in cron expression",
+        }
+    }
+}
+
+/// A `CronExpression` is a schedule in the five field format of cron:
+/// `minute hour day-of-month month day-of-week`.
+///
+/// Each field is either `*`, a value, a range `a-b` or a list of those separated by commas,
+/// optionally followed by a step `/n`. Months and weekdays can also be given by their
+/// three letter english names; Sunday is both 0 and 7.
+/// Like in cron, an entry fires if either the day of month or the weekday matches,
+/// unless one of them is `*`.
+///
+/// All times are in UTC.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CronExpression {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronExpression {
+    pub fn parse(source: &str) -> Result<Self, CronError> {
+        let fields: Vec<&str> = source.split_whitespace().collect();
+
+        if fields.len() != 5 {
+            return Err(CronError::FieldCount);
+        }
+
+        let mut weekdays = parse_field(fields[4], 0, 7, WEEKDAY_NAMES, 0)?;
+
+        // Sunday can be written as 7
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(CronExpression {
+            source: fields.join(" "),
+            minutes: parse_field(fields[0], 0, 59, &[], 0)?,
+            hours: parse_field(fields[1], 0, 23, &[], 0)?,
+            days: parse_field(fields[2], 1, 31, &[], 0)?,
+            months: parse_field(fields[3], 1, 12, MONTH_NAMES, 1)?,
+            weekdays,
+            any_day: fields[2].starts_with('*'),
+            any_weekday: fields[4].starts_with('*'),
+        })
+    }
+
+    /// Returns the expression in its normalized textual form
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the first timestamp strictly after `after` at which the expression fires,
+    /// or `None` if it never fires, e.g. for the 30th of February, or only after the largest timestamp.
+    pub fn next_after(&self, after: Timestamp) -> Option<Timestamp> {
+        let mut minute = after.millis().div_euclid(MILLIS_PER_MINUTE) + 1;
+        let last_day = minute.div_euclid(MINUTES_PER_DAY) + MAX_YEARS * 366;
+
+        loop {
+            let day = minute.div_euclid(MINUTES_PER_DAY);
+
+            if day > last_day {
+                return None;
+            }
+
+            let (_, month, day_of_month) = civil_from_days(day);
+
+            if self.months & (1 << month) == 0 {
+                minute = (day - day_of_month as i64 + days_in_month(day, month) + 1) * MINUTES_PER_DAY;
+                continue;
+            }
+
+            if !self.matches_day(day, day_of_month) {
+                minute = (day + 1) * MINUTES_PER_DAY;
+                continue;
+            }
+
+            let minute_of_day = minute.rem_euclid(MINUTES_PER_DAY);
+
+            if self.hours & (1 << (minute_of_day / 60)) == 0 {
+                minute += 60 - minute_of_day % 60;
+                continue;
+            }
+
+            if self.minutes & (1 << (minute_of_day % 60)) == 0 {
+                minute += 1;
+                continue;
+            }
+
+            return minute.checked_mul(MILLIS_PER_MINUTE).map(Timestamp::from_millis);
+        }
+    }
+
+    fn matches_day(&self, day: i64, day_of_month: u32) -> bool {
+        // 1970-01-01 was a Thursday
+        let weekday = (day + 4).rem_euclid(7);
+        let matches_day = self.days & (1 << day_of_month) != 0;
+        let matches_weekday = self.weekdays & (1 << weekday) != 0;
+
+        if self.any_day || self.any_weekday {
+            matches_day && matches_weekday
+        } else {
+            matches_day || matches_weekday
+        }
+    }
+}
+
+impl FromStr for CronExpression {
+    type Err = CronError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for CronExpression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// Parses a field into a bit set in which bit `n` is set if the field matches the value `n`
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], first_name: u32) -> Result<u64, CronError> {
+    let mut set = 0;
+
+    for part in field.split(',') {
+        let (range, step) = match part.find('/') {
+            Some(pos) => match part[pos + 1..].parse::<u32>() {
+                Ok(step) if step > 0 && step <= max => (&part[..pos], step),
+                _ => return Err(CronError::InvalidStep),
+            },
+            None => (part, 1),
+        };
+
+        let (start, end) = match range.find('-') {
+            _ if range == "*" => (min, max),
+            Some(pos) => (
+                parse_value(&range[..pos], names, first_name)?,
+                parse_value(&range[pos + 1..], names, first_name)?,
+            ),
+            // A single value with a step runs until the end of the range, like in `5/15`
+            None if step > 1 => (parse_value(range, names, first_name)?, max),
+            None => {
+                let value = parse_value(range, names, first_name)?;
+                (value, value)
+            }
+        };
+
+        if start < min || end > max || start > end {
+            return Err(CronError::OutOfRange);
+        }
+
+        let mut value = start;
+
+        while value <= end {
+            set |= 1 << value;
+            value += step;
+        }
+    }
+
+    Ok(set)
+}
+
+fn parse_value(value: &str, names: &[&str], first_name: u32) -> Result<u32, CronError> {
+    if let Ok(value) = value.parse() {
+        return Ok(value);
+    }
+
+    let upper = value.to_uppercase();
+
+    names
+        .iter()
+        .position(|name| *name == upper)
+        .map(|pos| pos as u32 + first_name)
+        .ok_or(CronError::InvalidValue)
+}
+
+/// Converts the number of days since 1970-01-01 into year, month and day of month
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+/// Returns the number of days in the month that contains the given day
+fn days_in_month(day: i64, month: u32) -> i64 {
+    match month {
+        2 => {
+            let (year, _, _) = civil_from_days(day);
+
+            if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) { 29 } else { 28 }
+        }
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// 2017-07-14 02:40:00 UTC, a Friday
+    const START: i64 = 1_500_000_000_000;
+
+    fn next(expression: &str, after: i64) -> Option<i64> {
+        CronExpression::parse(expression).unwrap().next_after(Timestamp::from_millis(after)).map(|ts| ts.millis())
+    }
+
+    fn minutes(n: i64) -> i64 {
+        START + n * MILLIS_PER_MINUTE
+    }
+
+    #[test]
+    fn test_every_minute() {
+        assert_eq!(Some(minutes(1)), next("* * * * *", START));
+        assert_eq!(Some(minutes(1)), next("* * * * *", START + 59_999));
+    }
+
+    #[test]
+    fn test_step() {
+        assert_eq!(Some(minutes(5)), next("*/5 * * * *", START));
+        assert_eq!(Some(minutes(5)), next("*/5 * * * *", minutes(4)));
+        assert_eq!(Some(minutes(10)), next("*/5 * * * *", minutes(5)));
+        assert_eq!(Some(minutes(2)), next("2/5 * * * *", START));
+    }
+
+    #[test]
+    fn test_list_and_range() {
+        assert_eq!(Some(minutes(20)), next("0,30 3-4 * * *", START));
+        assert_eq!(Some(minutes(20 + 120)), next("0 4-5 * * *", minutes(20 + 60)));
+    }
+
+    #[test]
+    fn test_days() {
+        // 2017-08-01 00:00:00
+        assert_eq!(Some(1_501_545_600_000), next("0 0 1 * *", START));
+        // 2017-07-16 00:00:00, a Sunday
+        assert_eq!(Some(1_500_163_200_000), next("0 0 * * 0", START));
+        assert_eq!(Some(1_500_163_200_000), next("0 0 * * sun", START));
+        assert_eq!(Some(1_500_163_200_000), next("0 0 * * 7", START));
+        // Either the 20th or the next Sunday
+        assert_eq!(Some(1_500_163_200_000), next("0 0 20 * 0", START));
+    }
+
+    #[test]
+    fn test_months() {
+        // 2018-02-01 00:00:00
+        assert_eq!(Some(1_517_443_200_000), next("0 0 1 feb *", START));
+        // 2020-02-29 00:00:00
+        assert_eq!(Some(1_582_934_400_000), next("0 0 29 2 *", START));
+        assert_eq!(None, next("0 0 30 2 *", START));
+    }
+
+    #[test]
+    fn test_before_epoch() {
+        assert_eq!(Some(0), next("0 0 1 1 *", -1));
+    }
+
+    #[test]
+    fn test_after_max() {
+        assert_eq!(None, next("* * * * *", i64::max_value()));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(Err(CronError::FieldCount), CronExpression::parse("* * * *"));
+        assert_eq!(Err(CronError::InvalidValue), CronExpression::parse("a * * * *"));
+        assert_eq!(Err(CronError::OutOfRange), CronExpression::parse("60 * * * *"));
+        assert_eq!(Err(CronError::OutOfRange), CronExpression::parse("* * 0 * *"));
+        assert_eq!(Err(CronError::OutOfRange), CronExpression::parse("5-1 * * * *"));
+        assert_eq!(Err(CronError::InvalidStep), CronExpression::parse("*/0 * * * *"));
+        assert_eq!(Err(CronError::InvalidStep), CronExpression::parse("*/60 * * * *"));
+        assert_eq!(Err(CronError::InvalidStep), CronExpression::parse("5/4294967295 * * * *"));
+    }
+
+    #[test]
+    fn test_source() {
+        assert_eq!("*/5 * * * *", CronExpression::parse("  */5 *  * * * ").unwrap().source());
+    }
+}
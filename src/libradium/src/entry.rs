@@ -1,66 +1,114 @@
 use std::ops::Add;
-use rand::{Rng, thread_rng};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 use time::{Timespec, get_time};
+use super::recurrence::Recurrence;
 
-/// A `Timestamp` holds a unix timestamp in seconds.
+const MILLIS_PER_SEC: i64 = 1_000;
+const NANOS_PER_MILLI: i64 = 1_000_000;
+
+/// The last id handed out by `EntryId::gen`
+static LAST_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A `Timestamp` holds a unix timestamp with millisecond precision.
 /// It is used to mark the expiration date of an [`Entry`].
 ///
 /// [`Entry`]: struct.Entry.html
 #[derive(Eq, Ord, PartialEq, PartialOrd, Debug, Copy, Clone, Hash)]
 pub struct Timestamp {
-    pub sec: i64,
+    millis: i64,
 }
 
 impl Timestamp {
-    pub fn new(sec: i64) -> Self {
-        Timestamp { sec }
+    /// Creates a `Timestamp` from a unix timestamp in seconds.
+    /// Returns `None` if the timestamp is out of range in milliseconds.
+    pub fn from_secs(sec: i64) -> Option<Self> {
+        sec.checked_mul(MILLIS_PER_SEC).map(Self::from_millis)
+    }
+
+    /// Creates a `Timestamp` from a unix timestamp in milliseconds
+    pub fn from_millis(millis: i64) -> Self {
+        Timestamp { millis }
     }
 
     /// Returns a `Timestamp` at the current time
     pub fn now() -> Self {
-        Self::new(get_time().sec)
+        Self::from(get_time())
     }
-}
 
-impl From<Timespec> for Timestamp {
-    fn from(value: Timespec) -> Self {
-        Self::new(value.sec)
+    /// Returns the whole seconds of this `Timestamp`, rounded towards negative infinity
+    pub fn secs(&self) -> i64 {
+        self.millis.div_euclid(MILLIS_PER_SEC)
     }
-}
 
-impl From<i64> for Timestamp {
-    fn from(value: i64) -> Self {
-        Self::new(value)
+    pub fn millis(&self) -> i64 {
+        self.millis
+    }
+
+    /// Adds a `Duration`, returning `None` if the result is out of range
+    pub fn checked_add(&self, duration: Duration) -> Option<Self> {
+        let secs = duration.as_secs();
+
+        if secs > i64::max_value() as u64 {
+            return None;
+        }
+
+        (secs as i64)
+            .checked_mul(MILLIS_PER_SEC)
+            .and_then(|millis| millis.checked_add(duration.subsec_millis() as i64))
+            .and_then(|millis| self.millis.checked_add(millis))
+            .map(Self::from_millis)
     }
 }
 
-impl Into<i64> for Timestamp {
-    fn into(self) -> i64 {
-        self.sec
+impl From<Timespec> for Timestamp {
+    fn from(value: Timespec) -> Self {
+        // The clock is not supplied by clients, so a time out of range is clamped instead of rejected
+        let millis = value.sec.saturating_mul(MILLIS_PER_SEC).saturating_add((value.nsec as i64) / NANOS_PER_MILLI);
+
+        Self::from_millis(millis)
     }
 }
 
-impl Add<i64> for Timestamp {
+impl Add<Duration> for Timestamp {
     type Output = Timestamp;
 
-    fn add(self, rhs: i64) -> Self::Output {
-        Timestamp::new(self.sec + rhs)
+    /// # Panics
+    ///
+    /// Panics if the result is out of range, use [`checked_add`] for durations that come from clients.
+    ///
+    /// [`checked_add`]: #method.checked_add
+    fn add(self, rhs: Duration) -> Self::Output {
+        self.checked_add(rhs).expect("overflow when adding duration to timestamp")
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 /// An `EntryId` consists of a `timestamp` at which the entry expires
 /// and an `id`, which ensures the `EntryId` is unique.
 ///
+/// Ids created by [`gen`] are taken from a process wide sequence,
+/// so they are unique regardless of their timestamp.
+///
 /// The `EntryId` is sorted by timestamp and then by id, allowing the [`Storage`] to iterate
 /// only through the first few entries when checking for expiration.
 ///
 /// [`Storage`]: struct.Storage.html
+/// [`gen`]: #method.gen
 pub struct EntryId {
     timestamp: Timestamp,
-    id: u16,
+    id: u64,
 }
 
+/// An `EntryKey` is a key chosen by the client that identifies an [`Entry`]
+/// independently of its [`EntryId`]. A [`Core`] holds at most one entry per key.
+///
+/// [`Entry`]: struct.Entry.html
+/// [`EntryId`]: struct.EntryId.html
+/// [`Core`]: struct.Core.html
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct EntryKey(Vec<u8>);
+
 #[derive(Debug)]
 /// An `Entry` represents a single entry stored in [`Storage`].
 /// It can hold arbitrary data in its `data` field.
@@ -69,36 +117,88 @@ pub struct EntryId {
 pub struct Entry<T: Send + 'static> {
     id: EntryId,
     data: T,
+    recurrence: Option<Recurrence<T>>,
+    key: Option<EntryKey>,
 }
 
 impl EntryId {
-    pub fn new<TS: Into<Timestamp>>(timestamp: TS, id: u16) -> Self {
+    pub fn new<TS: Into<Timestamp>>(timestamp: TS, id: u64) -> Self {
         EntryId {
             timestamp: timestamp.into(),
             id,
         }
     }
 
-    /// Creates a new `EntryId` with a random value for `id`
+    /// Creates a new `EntryId` with the next value of the sequence for `id`
     pub fn gen<TS: Into<Timestamp>>(timestamp: TS) -> Self {
-        let mut rng = thread_rng();
-        let id = rng.gen::<u16>();
+        let id = LAST_ID.fetch_add(1, Ordering::SeqCst) + 1;
 
         Self::new(timestamp, id)
     }
 
+    /// Advances the sequence used by [`gen`] past the id of this `EntryId`,
+    /// e.g. after the entry has been loaded from disk, so that no other entry receives the same id.
+    ///
+    /// [`gen`]: #method.gen
+    pub fn reserve(&self) {
+        LAST_ID.fetch_max(self.id, Ordering::SeqCst);
+    }
+
     pub fn timestamp(&self) -> Timestamp {
         self.timestamp
     }
 
-    pub fn id(&self) -> u16 {
+    pub fn id(&self) -> u64 {
         self.id
     }
 }
 
+impl EntryKey {
+    pub fn new<K: Into<Vec<u8>>>(key: K) -> Self {
+        EntryKey(key.into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
 impl<T: Send + 'static> Entry<T> {
     pub fn new(id: EntryId, data: T) -> Self {
-        Entry { id, data }
+        Entry {
+            id,
+            data,
+            recurrence: None,
+            key: None,
+        }
+    }
+
+    /// Creates an `Entry` that re-arms itself according to the given [`Recurrence`] each time it expires
+    ///
+    /// [`Recurrence`]: struct.Recurrence.html
+    pub fn recurring(id: EntryId, data: T, recurrence: Recurrence<T>) -> Self {
+        Entry {
+            id,
+            data,
+            recurrence: Some(recurrence),
+            key: None,
+        }
+    }
+
+    /// Sets the [`EntryKey`] of this `Entry`, or removes it if `None` is given
+    ///
+    /// [`EntryKey`]: struct.EntryKey.html
+    pub fn with_key<K: Into<Option<EntryKey>>>(mut self, key: K) -> Self {
+        self.key = key.into();
+        self
+    }
+
+    /// Replaces the [`EntryId`] of this `Entry`, keeping its data, recurrence and key
+    ///
+    /// [`EntryId`]: struct.EntryId.html
+    pub fn with_id(mut self, id: EntryId) -> Self {
+        self.id = id;
+        self
     }
 
     /// Convenience method that generates an [`EntryId`].
@@ -120,6 +220,25 @@ impl<T: Send + 'static> Entry<T> {
         &self.data
     }
 
+    /// Gives a mutable reference to the data that is stored in this `Entry`
+    pub fn data_mut(&mut self) -> &mut T {
+        &mut self.data
+    }
+
+    pub fn recurrence(&self) -> Option<&Recurrence<T>> {
+        self.recurrence.as_ref()
+    }
+
+    pub fn key(&self) -> Option<&EntryKey> {
+        self.key.as_ref()
+    }
+
+    /// Returns which occurrence of a recurring `Entry` this is, starting at 1.
+    /// Entries that don't recur always return 1.
+    pub fn occurrence(&self) -> u32 {
+        self.recurrence.as_ref().map_or(1, |recurrence| recurrence.occurrence())
+    }
+
     /// Returns the data, consuming the `Entry`
     pub fn consume_data(self) -> T {
         self.data
@@ -134,6 +253,28 @@ where
         Entry {
             id: self.id,
             data: self.data.clone(),
+            recurrence: self.recurrence.clone(),
+            key: self.key.clone(),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_secs() {
+        assert_eq!(Some(Timestamp::from_millis(12_000)), Timestamp::from_secs(12));
+        assert_eq!(None, Timestamp::from_secs(i64::max_value() / 1_000 + 1));
+    }
+
+    #[test]
+    fn test_checked_add() {
+        let timestamp = Timestamp::from_millis(1_000);
+
+        assert_eq!(Some(Timestamp::from_millis(2_500)), timestamp.checked_add(Duration::from_millis(1_500)));
+        assert_eq!(None, timestamp.checked_add(Duration::from_millis(i64::max_value() as u64)));
+        assert_eq!(None, timestamp.checked_add(Duration::from_secs(u64::max_value())));
+    }
+}
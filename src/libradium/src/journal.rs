@@ -0,0 +1,441 @@
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
+use super::entry::{Entry, EntryId, EntryKey, Timestamp};
+use super::recurrence::{Recurrence, Schedule};
+use super::cron::CronExpression;
+
+const OP_ADD: u8 = 0;
+const OP_REMOVE: u8 = 1;
+const OP_ADD_RECURRING: u8 = 2;
+
+/// Set on the op of an add record if the entry has a key
+const OP_KEYED: u8 = 0x80;
+
+const SCHEDULE_INTERVAL: u8 = 0;
+const SCHEDULE_CRON: u8 = 1;
+
+/// Size of the record header (op: u8 | ts: i64 | id: u64)
+const HEADER_LEN: u64 = 1 + 8 + 8;
+
+/// Size of a recurrence without its schedule (remaining: u32 | end: i64 | occurrence: u32)
+const RECURRENCE_LEN: u64 = 4 + 8 + 4;
+
+/// A `Payload` is the data of an [`Entry`] that can be written to disk
+/// by a [`PersistentStorage`].
+///
+/// [`Entry`]: struct.Entry.html
+/// [`PersistentStorage`]: struct.PersistentStorage.html
+pub trait Payload: Sized {
+    fn write_payload<W: io::Write>(&self, target: &mut W) -> io::Result<()>;
+
+    /// Reads the payload from `source`, which contains exactly the bytes written by `write_payload`
+    fn read_payload<R: io::Read>(source: &mut R) -> io::Result<Self>;
+}
+
+/// The `SyncPolicy` determines how often the journal of a [`PersistentStorage`]
+/// is flushed to disk using `fsync`.
+///
+/// [`PersistentStorage`]: struct.PersistentStorage.html
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SyncPolicy {
+    /// Every record is synced before the command is applied
+    Always,
+    /// Records are synced if the last sync is older than the given interval
+    Interval(Duration),
+    /// Syncing is left to the operating system
+    Never,
+}
+
+/// The `SnapshotPolicy` determines when a [`PersistentStorage`] writes a snapshot
+/// of all entries and truncates its journal. Snapshots can always be triggered by hand.
+///
+/// [`PersistentStorage`]: struct.PersistentStorage.html
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub struct SnapshotPolicy {
+    /// A snapshot is written when the last one is older than this interval
+    pub interval: Option<Duration>,
+    /// A snapshot is written when the journal has grown beyond this size in bytes
+    pub max_journal_len: Option<u64>,
+}
+
+#[derive(Debug)]
+pub enum Record<T: Send + 'static> {
+    Add(Entry<T>),
+    Remove(EntryId),
+}
+
+/// A `Journal` is an append-only log of all changes made to a storage.
+///
+/// op: u8 | ts: i64 | id: u64 | (key_len: u16 | key: key_len bytes, only if keyed) | (recurrence, only if op = add recurring) |
+/// (len: u32 | payload: len bytes, only if op = add)
+///
+/// The op of an add record has its highest bit set if the entry has a key.
+/// A recurrence is stored as schedule | remaining: u32 | end: i64 | occurrence: u32,
+/// where a `remaining` of 0 means unlimited and an `end` of `i64::MIN` means no end.
+/// The schedule is either 0: u8 | interval: u64 or 1: u8 | len: u16 | cron expression: len bytes.
+#[derive(Debug)]
+pub struct Journal {
+    file: BufWriter<File>,
+    len: u64,
+    policy: SyncPolicy,
+    last_synced: Instant,
+}
+
+impl Journal {
+    /// Opens the journal at `path` and replays all of its records using `replay`.
+    ///
+    /// An incomplete record at the end of the journal, left behind by a crash
+    /// in the middle of a write, is discarded. A corrupt record fails with `InvalidData`
+    /// and leaves the journal untouched.
+    pub fn open<T, P, F>(path: P, policy: SyncPolicy, replay: F) -> io::Result<Self>
+    where
+        T: Payload + Send + 'static,
+        P: AsRef<Path>,
+        F: FnMut(Record<T>),
+    {
+        let file = OpenOptions::new().read(true).append(true).create(true).open(path)?;
+        let len = replay_records(&file, replay)?;
+
+        if len < file.metadata()?.len() {
+            file.set_len(len)?;
+        }
+
+        Ok(Journal {
+            file: BufWriter::new(file),
+            len,
+            policy,
+            last_synced: Instant::now(),
+        })
+    }
+
+    /// Returns the size of the journal in bytes
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn append_add<T: Payload + Send + 'static>(&mut self, entry: &Entry<T>) -> io::Result<()> {
+        self.len += write_add(&mut self.file, entry)?;
+
+        Ok(())
+    }
+
+    pub fn append_remove(&mut self, id: EntryId) -> io::Result<()> {
+        self.len += write_remove(&mut self.file, id)?;
+
+        Ok(())
+    }
+
+    /// Writes all appended records to the file, syncing it according to the [`SyncPolicy`]
+    ///
+    /// [`SyncPolicy`]: enum.SyncPolicy.html
+    pub fn commit(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let needs_sync = match self.policy {
+            SyncPolicy::Always => true,
+            SyncPolicy::Interval(interval) => self.last_synced.elapsed() >= interval,
+            SyncPolicy::Never => false,
+        };
+
+        if needs_sync {
+            self.file.get_ref().sync_data()?;
+            self.last_synced = Instant::now();
+        }
+
+        Ok(())
+    }
+
+    /// Removes all records from the journal
+    pub fn truncate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.file.get_ref().set_len(0)?;
+        self.file.get_ref().sync_data()?;
+        self.len = 0;
+        self.last_synced = Instant::now();
+
+        Ok(())
+    }
+}
+
+/// Replays the snapshot at `path` using `replay`. A missing snapshot is treated as empty.
+pub fn read_snapshot<T, P, F>(path: P, replay: F) -> io::Result<()>
+where
+    T: Payload + Send + 'static,
+    P: AsRef<Path>,
+    F: FnMut(Record<T>),
+{
+    match File::open(path) {
+        Ok(file) => replay_records(&file, replay).map(|_| ()),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Atomically replaces the snapshot at `path` with the given entries.
+///
+/// The snapshot uses the same format as the journal, containing only additions.
+pub fn write_snapshot<'a, T, P, I>(path: P, entries: I) -> io::Result<()>
+where
+    T: Payload + Send + 'static,
+    P: AsRef<Path>,
+    I: Iterator<Item = &'a Entry<T>>,
+{
+    let path = path.as_ref();
+    let tmp_path = path.with_extension("tmp");
+
+    {
+        let mut file = BufWriter::new(File::create(&tmp_path)?);
+
+        for entry in entries {
+            write_add(&mut file, entry)?;
+        }
+
+        file.flush()?;
+        file.get_ref().sync_all()?;
+    }
+
+    fs::rename(&tmp_path, path)?;
+
+    // The rename itself is only durable once the directory has been synced
+    if let Some(dir) = path.parent() {
+        File::open(dir)?.sync_all()?;
+    }
+
+    Ok(())
+}
+
+/// Replays all complete records of `file`, returning the number of bytes they take up
+fn replay_records<T, F>(file: &File, mut replay: F) -> io::Result<u64>
+where
+    T: Payload + Send + 'static,
+    F: FnMut(Record<T>),
+{
+    let mut reader = BufReader::new(file);
+    let mut len = 0;
+
+    while let Some((record, record_len)) = read_record(&mut reader)? {
+        replay(record);
+        len += record_len;
+    }
+
+    Ok(len)
+}
+
+fn write_header<W: io::Write>(target: &mut W, op: u8, id: EntryId) -> io::Result<()> {
+    target.write_u8(op)?;
+    target.write_i64::<NetworkEndian>(id.timestamp().millis())?;
+    target.write_u64::<NetworkEndian>(id.id())?;
+
+    Ok(())
+}
+
+/// Writes an add record, returning its length in bytes
+fn write_add<T, W>(target: &mut W, entry: &Entry<T>) -> io::Result<u64>
+where
+    T: Payload + Send + 'static,
+    W: io::Write,
+{
+    let mut payload = Vec::new();
+    entry.data().write_payload(&mut payload)?;
+
+    if payload.len() > u32::max_value() as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "payload is too large"));
+    }
+
+    if entry.key().map_or(false, |key| key.as_bytes().len() > u16::max_value() as usize) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "key is too large"));
+    }
+
+    // Checked before anything is written, so that the journal doesn't end up with a partial record
+    if let Some(&Schedule::Cron(ref expression)) = entry.recurrence().map(Recurrence::schedule) {
+        if expression.source().len() > u16::max_value() as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "cron expression is too long"));
+        }
+    }
+
+    let op = match entry.recurrence() {
+        Some(_) => OP_ADD_RECURRING,
+        None => OP_ADD,
+    };
+
+    let key_len = match entry.key() {
+        Some(key) => {
+            write_header(target, op | OP_KEYED, entry.id())?;
+            target.write_u16::<NetworkEndian>(key.as_bytes().len() as u16)?;
+            target.write_all(key.as_bytes())?;
+            2 + key.as_bytes().len() as u64
+        }
+        None => {
+            write_header(target, op, entry.id())?;
+            0
+        }
+    };
+
+    let recurrence_len = match entry.recurrence() {
+        Some(recurrence) => write_recurrence(target, recurrence)?,
+        None => 0,
+    };
+
+    target.write_u32::<NetworkEndian>(payload.len() as u32)?;
+    target.write_all(&payload)?;
+
+    Ok(HEADER_LEN + key_len + recurrence_len + 4 + payload.len() as u64)
+}
+
+/// Writes a recurrence, returning its length in bytes
+fn write_recurrence<T, W: io::Write>(target: &mut W, recurrence: &Recurrence<T>) -> io::Result<u64> {
+    let schedule_len = match recurrence.schedule() {
+        &Schedule::Interval(interval) => {
+            target.write_u8(SCHEDULE_INTERVAL)?;
+            target.write_u64::<NetworkEndian>(interval.as_secs() * 1_000 + interval.subsec_millis() as u64)?;
+            1 + 8
+        }
+        &Schedule::Cron(ref expression) => {
+            let source = expression.source().as_bytes();
+
+            target.write_u8(SCHEDULE_CRON)?;
+            target.write_u16::<NetworkEndian>(source.len() as u16)?;
+            target.write_all(source)?;
+            1 + 2 + source.len() as u64
+        }
+    };
+
+    target.write_u32::<NetworkEndian>(recurrence.remaining().unwrap_or(0))?;
+    target.write_i64::<NetworkEndian>(recurrence.end().map_or(i64::min_value(), |end| end.millis()))?;
+    target.write_u32::<NetworkEndian>(recurrence.occurrence())?;
+
+    Ok(schedule_len + RECURRENCE_LEN)
+}
+
+/// Reads a recurrence along with its length in bytes
+fn read_recurrence<T, R>(source: &mut R) -> io::Result<(Recurrence<T>, u64)>
+where
+    T: Payload + Send + 'static,
+    R: io::Read,
+{
+    let (schedule, schedule_len) = match source.read_u8()? {
+        SCHEDULE_INTERVAL => (Schedule::Interval(Duration::from_millis(source.read_u64::<NetworkEndian>()?)), 1 + 8),
+        SCHEDULE_CRON => {
+            let len = source.read_u16::<NetworkEndian>()? as u64;
+            let mut expression = String::new();
+
+            if source.take(len).read_to_string(&mut expression)? < len as usize {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            match CronExpression::parse(&expression) {
+                Ok(expression) => (Schedule::Cron(expression), 1 + 2 + len),
+                Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+            }
+        }
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid schedule")),
+    };
+
+    let mut recurrence = Recurrence::with_clone(schedule, clone_payload);
+
+    match source.read_u32::<NetworkEndian>()? {
+        0 => {}
+        remaining => recurrence = recurrence.times(remaining),
+    }
+
+    match source.read_i64::<NetworkEndian>()? {
+        end if end == i64::min_value() => {}
+        end => recurrence = recurrence.until(Timestamp::from_millis(end)),
+    }
+
+    let recurrence = recurrence.with_occurrence(source.read_u32::<NetworkEndian>()?);
+
+    Ok((recurrence, schedule_len + RECURRENCE_LEN))
+}
+
+/// Copies the data of a recurring entry that has been read back from disk.
+/// The storage can't require the data to be `Clone`, so it is copied through its payload instead.
+fn clone_payload<T: Payload>(data: &T) -> T {
+    let mut payload = Vec::new();
+
+    data.write_payload(&mut payload).expect("unable to write payload");
+    T::read_payload(&mut io::Cursor::new(payload)).expect("unable to read payload")
+}
+
+/// Writes a remove record, returning its length in bytes
+fn write_remove<W: io::Write>(target: &mut W, id: EntryId) -> io::Result<u64> {
+    write_header(target, OP_REMOVE, id)?;
+
+    Ok(HEADER_LEN)
+}
+
+/// Reads the next record along with its length in bytes.
+/// Returns `None` if the end of the journal or an incomplete record has been reached.
+///
+/// A record is only incomplete if it is cut short by the end of the file, which happens when a crash interrupts a write.
+/// Records that can't be decoded are reported as `InvalidData`, so that the records after them aren't discarded.
+fn read_record<T, R>(source: &mut R) -> io::Result<Option<(Record<T>, u64)>>
+where
+    T: Payload + Send + 'static,
+    R: io::Read,
+{
+    match read_complete_record(source) {
+        Ok(record) => Ok(Some(record)),
+        Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+fn read_complete_record<T, R>(source: &mut R) -> io::Result<(Record<T>, u64)>
+where
+    T: Payload + Send + 'static,
+    R: io::Read,
+{
+    let op = source.read_u8()?;
+    let timestamp = source.read_i64::<NetworkEndian>()?;
+    let id = EntryId::new(Timestamp::from_millis(timestamp), source.read_u64::<NetworkEndian>()?);
+
+    match op & !OP_KEYED {
+        OP_ADD | OP_ADD_RECURRING => {
+            let (key, key_len) = match op & OP_KEYED {
+                OP_KEYED => {
+                    let len = source.read_u16::<NetworkEndian>()? as u64;
+                    let mut key = Vec::new();
+
+                    if source.take(len).read_to_end(&mut key)? < len as usize {
+                        return Err(io::ErrorKind::UnexpectedEof.into());
+                    }
+
+                    (Some(EntryKey::new(key)), 2 + len)
+                }
+                _ => (None, 0),
+            };
+
+            let recurrence = match op & !OP_KEYED {
+                OP_ADD_RECURRING => Some(read_recurrence(source)?),
+                _ => None,
+            };
+
+            let len = source.read_u32::<NetworkEndian>()? as u64;
+            let mut payload = Vec::new();
+
+            if source.take(len).read_to_end(&mut payload)? < len as usize {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            // The payload has been read completely, so it is corrupt if it can't be decoded,
+            // even if the decoder runs out of bytes
+            let data = T::read_payload(&mut io::Cursor::new(payload))
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+            let (entry, recurrence_len) = match recurrence {
+                Some((recurrence, recurrence_len)) => (Entry::recurring(id, data, recurrence), recurrence_len),
+                None => (Entry::new(id, data), 0),
+            };
+
+            Ok((Record::Add(entry.with_key(key)), HEADER_LEN + key_len + recurrence_len + 4 + len))
+        }
+        OP_REMOVE => Ok((Record::Remove(id), HEADER_LEN)),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid journal record")),
+    }
+}
@@ -1,5 +1,9 @@
-extern crate rand;
+extern crate byteorder;
 pub extern crate time;
+#[cfg(feature = "stream")]
+extern crate futures_core;
+#[cfg(all(test, feature = "stream"))]
+extern crate futures_executor;
 
 mod entry;
 mod core;
@@ -7,7 +11,24 @@ mod storage;
 mod worker;
 mod sync;
 mod command;
+mod journal;
+mod recurrence;
+mod cron;
+mod capacity;
+mod clock;
+mod subscription;
+#[cfg(feature = "stream")]
+mod stream;
 
 pub use entry::*;
 pub use core::*;
 pub use worker::Listener;
+pub use subscription::Subscription;
+#[cfg(feature = "stream")]
+pub use stream::EntryStream;
+pub use storage::{Storage, BTreeStorage, TimingWheel, PersistentStorage};
+pub use journal::{Payload, SyncPolicy, SnapshotPolicy};
+pub use recurrence::{Recurrence, Schedule};
+pub use cron::{CronExpression, CronError};
+pub use capacity::{Capacity, OverflowPolicy};
+pub use clock::{Clock, SystemClock, ManualClock, Waker};
\ No newline at end of file
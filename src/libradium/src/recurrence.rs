@@ -0,0 +1,220 @@
+use std::fmt;
+use std::time::Duration;
+use super::entry::Timestamp;
+use super::cron::CronExpression;
+
+/// A `Schedule` determines at which timestamps a recurring [`Entry`] expires.
+///
+/// [`Entry`]: struct.Entry.html
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Schedule {
+    /// The entry expires repeatedly with a fixed interval in between
+    Interval(Duration),
+    /// The entry expires whenever the cron expression matches
+    Cron(CronExpression),
+}
+
+impl Schedule {
+    /// Returns the first timestamp after `now` at which an entry that last expired at `last` expires again.
+    ///
+    /// Occurrences that have been missed, e.g. because the worker was busy or not running, are skipped.
+    pub fn next(&self, last: Timestamp, now: Timestamp) -> Option<Timestamp> {
+        match self {
+            &Schedule::Interval(interval) => {
+                // An empty interval would make the entry expire again and again within the same millisecond.
+                // If the interval or the next occurrence is out of range, the entry doesn't expire again.
+                let interval = Timestamp::from_millis(0).checked_add(interval)?.millis().max(1);
+                let missed = now.millis().saturating_sub(last.millis()).max(0) / interval;
+                let offset = (missed + 1).checked_mul(interval)?;
+
+                last.millis().checked_add(offset).map(Timestamp::from_millis)
+            }
+            &Schedule::Cron(ref expression) => expression.next_after(last.max(now)),
+        }
+    }
+}
+
+/// A `Recurrence` makes an [`Entry`] re-arm itself according to its [`Schedule`] each time it expires,
+/// until it has expired a given number of times or the schedule has passed its end.
+///
+/// The worker hands a copy of the data to the [`Listener`] on every expiration,
+/// so the `Recurrence` knows how to clone the data of the entry.
+///
+/// [`Entry`]: struct.Entry.html
+/// [`Schedule`]: enum.Schedule.html
+/// [`Listener`]: trait.Listener.html
+pub struct Recurrence<T> {
+    schedule: Schedule,
+    remaining: Option<u32>,
+    end: Option<Timestamp>,
+    occurrence: u32,
+    clone_data: fn(&T) -> T,
+}
+
+impl<T: Clone> Recurrence<T> {
+    pub fn new(schedule: Schedule) -> Self {
+        Self::with_clone(schedule, T::clone)
+    }
+}
+
+impl<T> Recurrence<T> {
+    /// Creates a `Recurrence` for data that doesn't implement `Clone`,
+    /// using `clone_data` to copy it instead.
+    pub fn with_clone(schedule: Schedule, clone_data: fn(&T) -> T) -> Self {
+        Recurrence {
+            schedule,
+            remaining: None,
+            end: None,
+            occurrence: 1,
+            clone_data,
+        }
+    }
+
+    /// Limits the number of times the entry expires, including the next expiration
+    pub fn times(mut self, count: u32) -> Self {
+        self.remaining = Some(count);
+        self
+    }
+
+    /// Stops the entry from re-arming itself after the given timestamp
+    pub fn until(mut self, end: Timestamp) -> Self {
+        self.end = Some(end);
+        self
+    }
+
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    /// Returns how many times the entry is going to expire or `None` if there is no limit
+    pub fn remaining(&self) -> Option<u32> {
+        self.remaining
+    }
+
+    pub fn end(&self) -> Option<Timestamp> {
+        self.end
+    }
+
+    /// Returns which occurrence of the entry this is, starting at 1 for the first expiration
+    pub fn occurrence(&self) -> u32 {
+        self.occurrence
+    }
+
+    /// Sets the occurrence, e.g. when restoring an entry that has already expired before
+    pub fn with_occurrence(mut self, occurrence: u32) -> Self {
+        self.occurrence = occurrence;
+        self
+    }
+
+    /// Returns the timestamp of the next occurrence after the entry expired at `last`
+    /// along with the `Recurrence` for that occurrence, or `None` if the entry is not going to expire again.
+    pub fn next(&self, last: Timestamp, now: Timestamp) -> Option<(Timestamp, Recurrence<T>)> {
+        let remaining = match self.remaining {
+            Some(remaining) if remaining <= 1 => return None,
+            remaining => remaining.map(|remaining| remaining - 1),
+        };
+
+        let timestamp = self.schedule.next(last, now)?;
+
+        if self.end.map_or(false, |end| timestamp > end) {
+            return None;
+        }
+
+        let recurrence = Recurrence {
+            remaining,
+            occurrence: self.occurrence.saturating_add(1),
+            ..self.clone()
+        };
+
+        Some((timestamp, recurrence))
+    }
+
+    pub fn clone_data(&self, data: &T) -> T {
+        (self.clone_data)(data)
+    }
+}
+
+impl<T> Clone for Recurrence<T> {
+    fn clone(&self) -> Self {
+        Recurrence {
+            schedule: self.schedule.clone(),
+            remaining: self.remaining,
+            end: self.end,
+            occurrence: self.occurrence,
+            clone_data: self.clone_data,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Recurrence<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Recurrence")
+            .field("schedule", &self.schedule)
+            .field("remaining", &self.remaining)
+            .field("end", &self.end)
+            .field("occurrence", &self.occurrence)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(millis: i64) -> Timestamp {
+        Timestamp::from_millis(millis)
+    }
+
+    #[test]
+    fn test_interval() {
+        let schedule = Schedule::Interval(Duration::from_millis(100));
+
+        assert_eq!(Some(at(1100)), schedule.next(at(1000), at(1000)));
+        assert_eq!(Some(at(1100)), schedule.next(at(1000), at(1099)));
+        assert_eq!(Some(at(1200)), schedule.next(at(1000), at(1100)));
+        assert_eq!(Some(at(1600)), schedule.next(at(1000), at(1550)));
+    }
+
+    #[test]
+    fn test_empty_interval() {
+        let schedule = Schedule::Interval(Duration::from_millis(0));
+
+        assert_eq!(Some(at(1001)), schedule.next(at(1000), at(1000)));
+    }
+
+    #[test]
+    fn test_interval_out_of_range() {
+        let schedule = Schedule::Interval(Duration::from_millis(i64::max_value() as u64));
+
+        assert_eq!(None, schedule.next(at(1000), at(1000)));
+        assert_eq!(None, Schedule::Interval(Duration::from_secs(u64::max_value())).next(at(0), at(0)));
+    }
+
+    #[test]
+    fn test_times() {
+        let recurrence = Recurrence::<u8>::new(Schedule::Interval(Duration::from_millis(10))).times(2);
+
+        let (timestamp, recurrence) = recurrence.next(at(0), at(0)).unwrap();
+
+        assert_eq!(at(10), timestamp);
+        assert_eq!(Some(1), recurrence.remaining());
+        assert_eq!(2, recurrence.occurrence());
+        assert!(recurrence.next(at(10), at(10)).is_none());
+    }
+
+    #[test]
+    fn test_until() {
+        let recurrence = Recurrence::<u8>::new(Schedule::Interval(Duration::from_millis(10))).until(at(20));
+
+        assert_eq!(at(20), recurrence.next(at(10), at(10)).unwrap().0);
+        assert!(recurrence.next(at(20), at(20)).is_none());
+    }
+
+    #[test]
+    fn test_cron() {
+        let schedule = Schedule::Cron(CronExpression::parse("*/5 * * * *").unwrap());
+
+        assert_eq!(Some(at(300_000)), schedule.next(at(0), at(0)));
+        assert_eq!(Some(at(600_000)), schedule.next(at(0), at(300_000)));
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+use std::collections::Bound::{Included, Excluded};
+use super::{Storage, KeyIndex};
+use super::super::entry::{Entry, EntryId, EntryKey, Timestamp};
+
+/// A [`Storage`] that keeps all entries in a `BTreeMap` ordered by their [`EntryId`].
+///
+/// Adding and removing entries takes `O(log n)`.
+///
+/// [`Storage`]: trait.Storage.html
+/// [`EntryId`]: struct.EntryId.html
+#[derive(Debug)]
+pub struct BTreeStorage<T: Send + 'static> {
+    entries: BTreeMap<EntryId, Entry<T>>,
+    keys: KeyIndex,
+}
+
+impl<T: Send + 'static> BTreeStorage<T> {
+    pub fn new() -> Self {
+        BTreeStorage {
+            entries: BTreeMap::new(),
+            keys: KeyIndex::default(),
+        }
+    }
+}
+
+impl<T: Send + 'static> Default for BTreeStorage<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send + 'static> Storage<T> for BTreeStorage<T> {
+    fn add_entry(&mut self, entry: Entry<T>) {
+        if let Some(replaced) = self.entries.remove(&entry.id()) {
+            self.keys.remove(&replaced);
+        }
+
+        self.keys.insert(&entry);
+        self.entries.insert(entry.id(), entry);
+    }
+
+    fn remove_entry(&mut self, id: EntryId) -> Option<Entry<T>> {
+        let entry = self.entries.remove(&id);
+
+        if let Some(ref entry) = entry {
+            self.keys.remove(entry);
+        }
+
+        entry
+    }
+
+    fn has_entry(&self, id: EntryId) -> bool {
+        self.entries.contains_key(&id)
+    }
+
+    fn get_entry(&self, id: EntryId) -> Option<&Entry<T>> {
+        self.entries.get(&id)
+    }
+
+    fn find_key(&self, key: &EntryKey) -> Option<EntryId> {
+        self.keys.get(key)
+    }
+
+    fn entries_between(&self, from: Timestamp, to: Timestamp, limit: usize) -> Vec<&Entry<T>> {
+        if from >= to {
+            return Vec::new();
+        }
+
+        // No id sorts before 0, so these bounds cover all ids of the first and the last timestamp
+        let range = (Included(EntryId::new(from, 0)), Excluded(EntryId::new(to, 0)));
+
+        self.entries.range(range).take(limit).map(|(_, entry)| entry).collect()
+    }
+
+    // TODO: add max expired entries per turn (-> use environment variable)
+    fn expire_entries(&mut self, now: Timestamp) -> Vec<Entry<T>> {
+        // Every entry expiring after `now` is moved out of the map,
+        // so the expired entries are what remains.
+        let pending = match self.entries.keys().find(|id| id.timestamp() > now) {
+            Some(&id) => self.entries.split_off(&id),
+            None => BTreeMap::new(),
+        };
+
+        let expired = ::std::mem::replace(&mut self.entries, pending);
+
+        for entry in expired.values() {
+            self.keys.remove(entry);
+        }
+
+        expired.into_values().collect()
+    }
+
+    fn next_deadline(&self) -> Option<Timestamp> {
+        self.entries.keys().next().map(|id| id.timestamp())
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn entries<'a>(&'a self) -> Box<Iterator<Item = &'a Entry<T>> + 'a> {
+        Box::new(self.entries.values())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    storage_tests!(BTreeStorage::new());
+}
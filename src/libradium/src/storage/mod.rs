@@ -0,0 +1,176 @@
+use std::io;
+use std::collections::HashMap;
+use std::time::Duration;
+use super::entry::{Entry, EntryId, EntryKey, Timestamp};
+
+#[cfg(test)]
+#[macro_use]
+mod test_suite;
+
+mod btree;
+mod wheel;
+mod persistent;
+
+pub use self::btree::BTreeStorage;
+pub use self::wheel::TimingWheel;
+pub use self::persistent::PersistentStorage;
+
+/// A `Storage` holds all pending entries of a [`Core`] and decides when they expire.
+///
+/// The storage is owned by the worker thread of the [`Core`],
+/// so implementations don't need any synchronization.
+///
+/// [`Core`]: struct.Core.html
+pub trait Storage<T: Send + 'static>: Send {
+    /// Stores the entry, replacing an existing entry with the same id
+    fn add_entry(&mut self, entry: Entry<T>);
+
+    /// Removes the entry with the given id, returning it if it existed
+    fn remove_entry(&mut self, id: EntryId) -> Option<Entry<T>>;
+
+    fn has_entry(&self, id: EntryId) -> bool;
+
+    fn get_entry(&self, id: EntryId) -> Option<&Entry<T>>;
+
+    /// Returns the id of the entry with the given [`EntryKey`]
+    ///
+    /// [`EntryKey`]: struct.EntryKey.html
+    fn find_key(&self, key: &EntryKey) -> Option<EntryId>;
+
+    /// Returns up to `limit` entries that expire at or after `from` and before `to`, ordered by their id
+    fn entries_between(&self, from: Timestamp, to: Timestamp, limit: usize) -> Vec<&Entry<T>> {
+        let mut entries: Vec<&Entry<T>> = self.entries()
+            .filter(|entry| entry.id().timestamp() >= from && entry.id().timestamp() < to)
+            .collect();
+
+        entries.sort_by_key(|entry| entry.id());
+        entries.truncate(limit);
+
+        entries
+    }
+
+    /// Moves the entry with the id `from` to the id `to`, replacing an existing entry with that id.
+    /// The entry keeps everything else, e.g. its recurrence. Returns `false` if there was no entry to move.
+    fn move_entry(&mut self, from: EntryId, to: EntryId) -> bool {
+        match self.remove_entry(from) {
+            Some(entry) => {
+                self.add_entry(entry.with_id(to));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes and returns all entries that expire at or before `now`, ordered by their id
+    fn expire_entries(&mut self, now: Timestamp) -> Vec<Entry<T>>;
+
+    /// Returns the timestamp of the entry that expires next
+    fn next_deadline(&self) -> Option<Timestamp>;
+
+    /// Returns the number of stored entries
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns all stored entries in no particular order
+    fn entries<'a>(&'a self) -> Box<Iterator<Item = &'a Entry<T>> + 'a>;
+
+    /// Writes a snapshot of all entries to disk, compacting any log kept alongside.
+    ///
+    /// Storages that don't persist their entries return an error.
+    fn snapshot(&mut self) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Other, "storage is not persistent"))
+    }
+
+    /// Returns the time until the storage is due to write a snapshot on a timer,
+    /// or `None` if it doesn't write snapshots periodically.
+    /// The worker calls [`snapshot`] once it is due, also while no commands arrive.
+    ///
+    /// [`snapshot`]: #method.snapshot
+    fn next_snapshot(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl<T: Send + 'static> Storage<T> for Box<Storage<T>> {
+    fn add_entry(&mut self, entry: Entry<T>) {
+        (**self).add_entry(entry)
+    }
+
+    fn remove_entry(&mut self, id: EntryId) -> Option<Entry<T>> {
+        (**self).remove_entry(id)
+    }
+
+    fn has_entry(&self, id: EntryId) -> bool {
+        (**self).has_entry(id)
+    }
+
+    fn get_entry(&self, id: EntryId) -> Option<&Entry<T>> {
+        (**self).get_entry(id)
+    }
+
+    fn find_key(&self, key: &EntryKey) -> Option<EntryId> {
+        (**self).find_key(key)
+    }
+
+    fn entries_between(&self, from: Timestamp, to: Timestamp, limit: usize) -> Vec<&Entry<T>> {
+        (**self).entries_between(from, to, limit)
+    }
+
+    fn move_entry(&mut self, from: EntryId, to: EntryId) -> bool {
+        (**self).move_entry(from, to)
+    }
+
+    fn expire_entries(&mut self, now: Timestamp) -> Vec<Entry<T>> {
+        (**self).expire_entries(now)
+    }
+
+    fn next_deadline(&self) -> Option<Timestamp> {
+        (**self).next_deadline()
+    }
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+
+    fn entries<'a>(&'a self) -> Box<Iterator<Item = &'a Entry<T>> + 'a> {
+        (**self).entries()
+    }
+
+    fn snapshot(&mut self) -> io::Result<()> {
+        (**self).snapshot()
+    }
+
+    fn next_snapshot(&self) -> Option<Duration> {
+        (**self).next_snapshot()
+    }
+}
+
+/// Maps the keys of the stored entries to their ids, for storages that keep entries by id.
+#[derive(Debug, Default)]
+struct KeyIndex {
+    ids: HashMap<EntryKey, EntryId>,
+}
+
+impl KeyIndex {
+    fn insert<T: Send + 'static>(&mut self, entry: &Entry<T>) {
+        if let Some(key) = entry.key() {
+            self.ids.insert(key.clone(), entry.id());
+        }
+    }
+
+    /// Removes the key of the entry, unless it has since been taken by another entry
+    fn remove<T: Send + 'static>(&mut self, entry: &Entry<T>) {
+        if let Some(key) = entry.key() {
+            if self.ids.get(key) == Some(&entry.id()) {
+                self.ids.remove(key);
+            }
+        }
+    }
+
+    fn get(&self, key: &EntryKey) -> Option<EntryId> {
+        self.ids.get(key).cloned()
+    }
+}
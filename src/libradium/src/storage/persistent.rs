@@ -0,0 +1,582 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use super::Storage;
+use super::super::entry::{Entry, EntryId, EntryKey, Timestamp};
+use super::super::journal::{self, Journal, Payload, Record, SnapshotPolicy, SyncPolicy};
+
+/// File name of the journal inside the data directory
+const JOURNAL_FILE: &str = "journal";
+
+/// File name of the snapshot inside the data directory
+const SNAPSHOT_FILE: &str = "snapshot";
+
+/// A `PersistentStorage` wraps another [`Storage`] and records every change
+/// in an append-only journal before applying it, so that the entries survive a restart.
+///
+/// Expired entries are recorded as removals, so they don't fire again after a restart.
+///
+/// To keep the journal from growing forever, all entries are periodically written
+/// to a snapshot according to the [`SnapshotPolicy`], after which the journal is truncated.
+/// On startup the snapshot is loaded first and the journal is replayed on top of it.
+///
+/// # Panics
+/// The worker thread panics if the journal or an automatic snapshot can't be written,
+/// as continuing would silently lose entries on the next restart.
+///
+/// [`Storage`]: trait.Storage.html
+/// [`SnapshotPolicy`]: struct.SnapshotPolicy.html
+#[derive(Debug)]
+pub struct PersistentStorage<S> {
+    inner: S,
+    dir: PathBuf,
+    journal: Journal,
+    snapshot_policy: SnapshotPolicy,
+    last_snapshot: Instant,
+}
+
+impl<S> PersistentStorage<S> {
+    /// Loads the snapshot and replays the journal inside `dir` into `inner`.
+    /// The directory is created if it doesn't exist yet.
+    pub fn open<T, P>(dir: P, policy: SyncPolicy, mut inner: S) -> io::Result<Self>
+    where
+        T: Payload + Send + 'static,
+        S: Storage<T>,
+        P: AsRef<Path>,
+    {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        // A crash between writing a snapshot and truncating the journal leaves records
+        // in the journal that are already part of the snapshot. Replaying them is harmless,
+        // because adding an entry replaces an existing one.
+        journal::read_snapshot(dir.join(SNAPSHOT_FILE), |record| replay(&mut inner, record))?;
+        let journal = Journal::open(dir.join(JOURNAL_FILE), policy, |record| replay(&mut inner, record))?;
+
+        Ok(PersistentStorage {
+            inner,
+            dir,
+            journal,
+            snapshot_policy: SnapshotPolicy::default(),
+            last_snapshot: Instant::now(),
+        })
+    }
+
+    pub fn set_snapshot_policy(&mut self, policy: SnapshotPolicy) {
+        self.snapshot_policy = policy;
+    }
+
+    pub fn snapshot_policy(&self) -> SnapshotPolicy {
+        self.snapshot_policy
+    }
+
+    /// Returns the size of the journal in bytes
+    pub fn journal_len(&self) -> u64 {
+        self.journal.len()
+    }
+
+    fn commit(&mut self) {
+        self.journal.commit().expect("unable to write journal");
+    }
+
+    /// Returns the time until the interval of the snapshot policy has elapsed,
+    /// or `None` if there is no interval or nothing has changed since the last snapshot
+    fn snapshot_timeout(&self) -> Option<Duration> {
+        if self.journal.len() == 0 {
+            return None;
+        }
+
+        let interval = self.snapshot_policy.interval?;
+
+        Some(interval.checked_sub(self.last_snapshot.elapsed()).unwrap_or(Duration::from_secs(0)))
+    }
+
+    fn snapshot_due(&self) -> bool {
+        if self.journal.len() == 0 {
+            return false;
+        }
+
+        let interval_elapsed = self.snapshot_timeout() == Some(Duration::from_secs(0));
+
+        let journal_exceeded = match self.snapshot_policy.max_journal_len {
+            Some(max_len) => self.journal.len() > max_len,
+            None => false,
+        };
+
+        interval_elapsed || journal_exceeded
+    }
+}
+
+fn replay<T, S>(storage: &mut S, record: Record<T>)
+where
+    T: Send + 'static,
+    S: Storage<T>,
+{
+    match record {
+        Record::Add(entry) => {
+            entry.id().reserve();
+            storage.add_entry(entry);
+        }
+        Record::Remove(id) => {
+            storage.remove_entry(id);
+        }
+    }
+}
+
+impl<T, S> Storage<T> for PersistentStorage<S>
+where
+    T: Payload + Send + 'static,
+    S: Storage<T>,
+{
+    fn add_entry(&mut self, entry: Entry<T>) {
+        self.journal.append_add(&entry).expect("unable to write journal");
+        self.commit();
+        self.inner.add_entry(entry);
+
+        if self.snapshot_due() {
+            self.snapshot().expect("unable to write snapshot");
+        }
+    }
+
+    fn remove_entry(&mut self, id: EntryId) -> Option<Entry<T>> {
+        if !self.inner.has_entry(id) {
+            return None;
+        }
+
+        self.journal.append_remove(id).expect("unable to write journal");
+        self.commit();
+
+        let entry = self.inner.remove_entry(id);
+
+        if self.snapshot_due() {
+            self.snapshot().expect("unable to write snapshot");
+        }
+
+        entry
+    }
+
+    fn has_entry(&self, id: EntryId) -> bool {
+        self.inner.has_entry(id)
+    }
+
+    fn get_entry(&self, id: EntryId) -> Option<&Entry<T>> {
+        self.inner.get_entry(id)
+    }
+
+    fn find_key(&self, key: &EntryKey) -> Option<EntryId> {
+        self.inner.find_key(key)
+    }
+
+    fn entries_between(&self, from: Timestamp, to: Timestamp, limit: usize) -> Vec<&Entry<T>> {
+        self.inner.entries_between(from, to, limit)
+    }
+
+    fn move_entry(&mut self, from: EntryId, to: EntryId) -> bool {
+        let entry = match self.inner.remove_entry(from) {
+            Some(entry) => entry.with_id(to),
+            None => return false,
+        };
+
+        // Both records are committed together. The addition is written first,
+        // so an interrupted write can't lose the entry.
+        self.journal.append_add(&entry).expect("unable to write journal");
+        self.journal.append_remove(from).expect("unable to write journal");
+        self.commit();
+        self.inner.add_entry(entry);
+
+        if self.snapshot_due() {
+            self.snapshot().expect("unable to write snapshot");
+        }
+
+        true
+    }
+
+    fn expire_entries(&mut self, now: Timestamp) -> Vec<Entry<T>> {
+        let expired = self.inner.expire_entries(now);
+
+        if !expired.is_empty() {
+            for entry in &expired {
+                self.journal.append_remove(entry.id()).expect("unable to write journal");
+            }
+
+            self.commit();
+        }
+
+        if self.snapshot_due() {
+            self.snapshot().expect("unable to write snapshot");
+        }
+
+        expired
+    }
+
+    fn next_deadline(&self) -> Option<Timestamp> {
+        self.inner.next_deadline()
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn entries<'a>(&'a self) -> Box<Iterator<Item = &'a Entry<T>> + 'a> {
+        self.inner.entries()
+    }
+
+    fn snapshot(&mut self) -> io::Result<()> {
+        journal::write_snapshot(self.dir.join(SNAPSHOT_FILE), self.inner.entries())?;
+        self.journal.truncate()?;
+        self.last_snapshot = Instant::now();
+
+        Ok(())
+    }
+
+    fn next_snapshot(&self) -> Option<Duration> {
+        self.snapshot_timeout()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::OpenOptions;
+    use std::path::PathBuf;
+    use std::process;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
+    use super::*;
+    use super::super::BTreeStorage;
+    use super::super::super::recurrence::{Recurrence, Schedule};
+    use super::super::super::cron::CronExpression;
+
+    static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+    impl Payload for u64 {
+        fn write_payload<W: io::Write>(&self, target: &mut W) -> io::Result<()> {
+            target.write_u64::<NetworkEndian>(*self)
+        }
+
+        fn read_payload<R: io::Read>(source: &mut R) -> io::Result<Self> {
+            source.read_u64::<NetworkEndian>()
+        }
+    }
+
+    fn temp_dir() -> PathBuf {
+        let n = NEXT_DIR.fetch_add(1, Ordering::SeqCst);
+        let dir = env::temp_dir().join(format!("libradium-test-{}-{}", process::id(), n));
+        let _ = fs::remove_dir_all(&dir);
+
+        dir
+    }
+
+    fn open(dir: &PathBuf) -> PersistentStorage<BTreeStorage<u64>> {
+        PersistentStorage::open(dir, SyncPolicy::Never, BTreeStorage::new()).unwrap()
+    }
+
+    fn open_compacting(dir: &PathBuf) -> PersistentStorage<BTreeStorage<u64>> {
+        let mut storage = open(dir);
+
+        storage.set_snapshot_policy(SnapshotPolicy {
+            interval: None,
+            max_journal_len: Some(0),
+        });
+
+        storage
+    }
+
+    mod compacting {
+        use super::{open_compacting, temp_dir};
+        use entry::Timestamp;
+        use storage::Storage;
+
+        storage_tests!(open_compacting(&temp_dir()));
+    }
+
+    storage_tests!(open(&temp_dir()));
+
+    #[test]
+    fn test_replay() {
+        let dir = temp_dir();
+
+        {
+            let mut storage = open(&dir);
+
+            storage.add_entry(entry(10, 1));
+            storage.add_entry(entry(20, 2));
+            storage.add_entry(entry(30, 3));
+            storage.remove_entry(EntryId::new(at(20), 2));
+            assert_eq!(vec![1], ids(storage.expire_entries(at(10))));
+        }
+
+        let mut storage = open(&dir);
+
+        assert_eq!(1, storage.len());
+        assert_eq!(vec![3], ids(storage.expire_entries(at(30))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_discards_incomplete_record() {
+        let dir = temp_dir();
+
+        {
+            let mut storage = open(&dir);
+
+            storage.add_entry(entry(10, 1));
+            storage.add_entry(entry(20, 2));
+        }
+
+        let file = OpenOptions::new().write(true).open(dir.join(JOURNAL_FILE)).unwrap();
+        let len = file.metadata().unwrap().len();
+        file.set_len(len - 1).unwrap();
+
+        {
+            let mut storage = open(&dir);
+
+            assert_eq!(1, storage.len());
+            storage.add_entry(entry(30, 3));
+        }
+
+        let mut storage = open(&dir);
+
+        assert_eq!(vec![1, 3], ids(storage.expire_entries(at(30))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_corrupt_record() {
+        let dir = temp_dir();
+
+        {
+            let mut storage = open(&dir);
+
+            storage.add_entry(entry(10, 1));
+            storage.add_entry(entry(20, 2));
+            storage.add_entry(entry(30, 3));
+        }
+
+        // Shortens the payload of the second record, which can't be decoded as a u64 anymore
+        let mut journal = fs::read(dir.join(JOURNAL_FILE)).unwrap();
+        let len = journal.len();
+
+        journal[46..50].copy_from_slice(&[0, 0, 0, 4]);
+        fs::write(dir.join(JOURNAL_FILE), &journal).unwrap();
+
+        match PersistentStorage::open(&dir, SyncPolicy::Never, BTreeStorage::<u64>::new()) {
+            Err(ref err) if err.kind() == io::ErrorKind::InvalidData => {}
+            result => panic!("unexpected result {:?}", result.map(|storage| storage.len())),
+        }
+
+        assert_eq!(len as u64, fs::metadata(dir.join(JOURNAL_FILE)).unwrap().len());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_long_cron_expression() {
+        let dir = temp_dir();
+        let mut storage = open(&dir);
+        let expression = CronExpression::parse(&format!("{} * * * *", vec!["0"; 40_000].join(","))).unwrap();
+        let entry = Entry::recurring(EntryId::new(at(10), 1), 1, Recurrence::new(Schedule::Cron(expression)));
+
+        assert!(storage.journal.append_add(&entry).is_err());
+        assert_eq!(0, storage.journal_len());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replays_moved_entry() {
+        let dir = temp_dir();
+
+        {
+            let mut storage = open(&dir);
+
+            storage.add_entry(entry(10, 1));
+            assert!(storage.move_entry(EntryId::new(at(10), 1), EntryId::new(at(50), 1)));
+        }
+
+        let mut storage = open(&dir);
+
+        assert!(storage.expire_entries(at(10)).is_empty());
+        assert_eq!(vec![1], ids(storage.expire_entries(at(50))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replays_recurring_entry() {
+        let dir = temp_dir();
+        let recurrence = Recurrence::new(Schedule::Interval(Duration::from_millis(50))).times(3).until(at(1000));
+
+        {
+            let mut storage = open(&dir);
+
+            storage.add_entry(Entry::recurring(EntryId::new(at(10), 1), 1, recurrence));
+            storage.snapshot().unwrap();
+            let recurrence = Recurrence::new(Schedule::Cron(CronExpression::parse("*/5 * * * *").unwrap())).with_occurrence(4);
+            storage.add_entry(Entry::recurring(EntryId::new(at(20), 2), 2, recurrence));
+        }
+
+        let mut storage = open(&dir);
+        let expired = storage.expire_entries(at(20));
+        let first = expired[0].recurrence().unwrap();
+        let second = expired[1].recurrence().unwrap();
+
+        assert_eq!(&Schedule::Interval(Duration::from_millis(50)), first.schedule());
+        assert_eq!(Some(3), first.remaining());
+        assert_eq!(Some(at(1000)), first.end());
+        assert_eq!(2, first.clone_data(expired[1].data()));
+
+        assert_eq!(1, first.occurrence());
+
+        assert_eq!(&Schedule::Cron(CronExpression::parse("*/5 * * * *").unwrap()), second.schedule());
+        assert_eq!(None, second.remaining());
+        assert_eq!(None, second.end());
+        assert_eq!(4, second.occurrence());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replays_keys() {
+        let dir = temp_dir();
+        let recurrence = Recurrence::new(Schedule::Interval(Duration::from_millis(50)));
+
+        {
+            let mut storage = open(&dir);
+
+            storage.add_entry(entry(10, 1).with_key(EntryKey::new("a")));
+            storage.snapshot().unwrap();
+            storage.add_entry(Entry::recurring(EntryId::new(at(20), 2), 2, recurrence).with_key(EntryKey::new("b")));
+            assert!(storage.move_entry(EntryId::new(at(10), 1), EntryId::new(at(30), 1)));
+        }
+
+        let storage = open(&dir);
+
+        assert_eq!(Some(EntryId::new(at(30), 1)), storage.find_key(&EntryKey::new("a")));
+        assert_eq!(Some(EntryId::new(at(20), 2)), storage.find_key(&EntryKey::new("b")));
+        assert!(storage.get_entry(EntryId::new(at(20), 2)).unwrap().recurrence().is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replay_reserves_ids() {
+        let dir = temp_dir();
+        let id = EntryId::gen(at(10));
+
+        {
+            let mut storage = open(&dir);
+            storage.add_entry(Entry::new(EntryId::new(at(10), id.id() + 1000), 1));
+        }
+
+        open(&dir);
+
+        assert!(EntryId::gen(at(10)).id() > id.id() + 1000);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let dir = temp_dir();
+
+        {
+            let mut storage = open(&dir);
+
+            storage.add_entry(entry(10, 1));
+            storage.add_entry(entry(20, 2));
+            storage.snapshot().unwrap();
+
+            assert_eq!(0, storage.journal_len());
+
+            storage.add_entry(entry(30, 3));
+            storage.remove_entry(EntryId::new(at(10), 1));
+        }
+
+        let mut storage = open(&dir);
+
+        assert_eq!(2, storage.len());
+        assert_eq!(vec![2, 3], ids(storage.expire_entries(at(30))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_compacts_journal() {
+        let dir = temp_dir();
+
+        {
+            let mut storage = open(&dir);
+
+            storage.set_snapshot_policy(SnapshotPolicy {
+                interval: None,
+                max_journal_len: Some(100),
+            });
+
+            for id in 0..100 {
+                storage.add_entry(entry(10, id));
+                assert!(storage.journal_len() <= 100);
+            }
+
+            assert_eq!(100, storage.expire_entries(at(10)).len());
+        }
+
+        assert!(open(&dir).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_next_snapshot() {
+        let dir = temp_dir();
+        let mut storage = open(&dir);
+
+        storage.set_snapshot_policy(SnapshotPolicy {
+            interval: Some(Duration::from_secs(60)),
+            max_journal_len: None,
+        });
+
+        // Nothing to write yet
+        assert_eq!(None, storage.next_snapshot());
+
+        storage.add_entry(entry(10, 1));
+
+        assert!(storage.next_snapshot().unwrap() > Duration::from_secs(50));
+
+        storage.set_snapshot_policy(SnapshotPolicy {
+            interval: Some(Duration::from_secs(0)),
+            max_journal_len: None,
+        });
+
+        assert_eq!(Some(Duration::from_secs(0)), storage.next_snapshot());
+
+        storage.snapshot().unwrap();
+
+        assert_eq!(None, storage.next_snapshot());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replays_journal_already_in_snapshot() {
+        let dir = temp_dir();
+
+        {
+            let mut storage = open(&dir);
+
+            storage.add_entry(entry(10, 1));
+            storage.add_entry(entry(20, 2));
+            journal::write_snapshot(dir.join(SNAPSHOT_FILE), storage.entries()).unwrap();
+        }
+
+        let mut storage = open(&dir);
+
+        assert_eq!(2, storage.len());
+        assert_eq!(vec![1, 2], ids(storage.expire_entries(at(20))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,282 @@
+/// Generates the test suite that every [`Storage`] implementation has to pass.
+///
+/// [`Storage`]: trait.Storage.html
+macro_rules! storage_tests {
+    ($storage: expr) => {
+        use std::time::Duration;
+        use $crate::{Entry, EntryId};
+
+        /// Arbitrary point in time at which all tests start
+        const START: i64 = 1_500_000_000_000;
+
+        fn at(millis: i64) -> Timestamp {
+            Timestamp::from_millis(START + millis)
+        }
+
+        fn entry(millis: i64, id: u64) -> Entry<u64> {
+            Entry::new(EntryId::new(at(millis), id), id)
+        }
+
+        fn ids(entries: Vec<Entry<u64>>) -> Vec<u64> {
+            entries.into_iter().map(|entry| entry.consume_data()).collect()
+        }
+
+        #[test]
+        fn test_add_and_remove() {
+            let mut storage = $storage;
+
+            storage.add_entry(entry(10, 1));
+
+            assert_eq!(1, storage.len());
+            assert!(storage.has_entry(EntryId::new(at(10), 1)));
+            assert!(!storage.has_entry(EntryId::new(at(10), 2)));
+
+            assert!(storage.remove_entry(EntryId::new(at(10), 2)).is_none());
+            assert_eq!(Some(1), storage.remove_entry(EntryId::new(at(10), 1)).map(|e| e.consume_data()));
+            assert!(storage.is_empty());
+            assert!(storage.remove_entry(EntryId::new(at(10), 1)).is_none());
+        }
+
+        #[test]
+        fn test_add_replaces_entry() {
+            let mut storage = $storage;
+
+            storage.add_entry(Entry::new(EntryId::new(at(10), 1), 1));
+            storage.add_entry(Entry::new(EntryId::new(at(10), 1), 2));
+
+            assert_eq!(1, storage.len());
+            assert_eq!(vec![2], ids(storage.expire_entries(at(10))));
+        }
+
+        #[test]
+        fn test_move_entry() {
+            let mut storage = $storage;
+
+            storage.expire_entries(at(0));
+            storage.add_entry(entry(10, 1));
+
+            assert!(!storage.move_entry(EntryId::new(at(10), 2), EntryId::new(at(20), 2)));
+            assert!(storage.move_entry(EntryId::new(at(10), 1), EntryId::new(at(20), 1)));
+
+            assert_eq!(1, storage.len());
+            assert!(!storage.has_entry(EntryId::new(at(10), 1)));
+            assert!(storage.expire_entries(at(10)).is_empty());
+            assert_eq!(Some(at(20)), storage.next_deadline());
+            assert_eq!(vec![1], ids(storage.expire_entries(at(20))));
+        }
+
+        #[test]
+        fn test_move_recurring_entry() {
+            use $crate::{Recurrence, Schedule};
+
+            let mut storage = $storage;
+            let recurrence = Recurrence::new(Schedule::Interval(Duration::from_millis(50))).with_occurrence(2);
+
+            storage.add_entry(Entry::recurring(EntryId::new(at(10), 1), 1, recurrence));
+
+            assert!(storage.move_entry(EntryId::new(at(10), 1), EntryId::new(at(20), 1)));
+
+            let expired = storage.expire_entries(at(20));
+
+            assert_eq!(1, expired.len());
+            assert_eq!(2, expired[0].occurrence());
+            assert!(expired[0].recurrence().is_some());
+        }
+
+        #[test]
+        fn test_expire_entries() {
+            let mut storage = $storage;
+
+            storage.expire_entries(at(0));
+
+            storage.add_entry(entry(20, 3));
+            storage.add_entry(entry(10, 2));
+            storage.add_entry(entry(10, 1));
+            storage.add_entry(entry(30, 4));
+
+            assert!(storage.expire_entries(at(9)).is_empty());
+            assert_eq!(vec![1, 2], ids(storage.expire_entries(at(10))));
+            assert_eq!(vec![3], ids(storage.expire_entries(at(25))));
+            assert_eq!(1, storage.len());
+            assert_eq!(vec![4], ids(storage.expire_entries(at(1000))));
+            assert!(storage.is_empty());
+        }
+
+        #[test]
+        fn test_expire_entries_in_the_past() {
+            let mut storage = $storage;
+
+            storage.expire_entries(at(1000));
+            storage.add_entry(entry(-5000, 1));
+            storage.add_entry(entry(1000, 2));
+
+            assert_eq!(vec![1, 2], ids(storage.expire_entries(at(1000))));
+        }
+
+        #[test]
+        fn test_expire_entries_added_before_first_check() {
+            let mut storage = $storage;
+
+            storage.add_entry(entry(100, 1));
+            storage.add_entry(entry(0, 2));
+
+            assert_eq!(vec![2], ids(storage.expire_entries(at(50))));
+            assert_eq!(vec![1], ids(storage.expire_entries(at(100))));
+        }
+
+        #[test]
+        fn test_next_deadline() {
+            let mut storage = $storage;
+
+            assert_eq!(None, storage.next_deadline());
+
+            storage.expire_entries(at(0));
+            storage.add_entry(entry(5000, 1));
+            storage.add_entry(entry(70, 2));
+            storage.add_entry(entry(4_000_000, 3));
+
+            assert_eq!(Some(at(70)), storage.next_deadline());
+            storage.expire_entries(at(70));
+            assert_eq!(Some(at(5000)), storage.next_deadline());
+            storage.remove_entry(EntryId::new(at(5000), 1));
+            assert_eq!(Some(at(4_000_000)), storage.next_deadline());
+        }
+
+        #[test]
+        fn test_expire_distant_entries() {
+            let mut storage = $storage;
+            let day = 24 * 60 * 60 * 1000;
+            let offsets = [1, 63, 64, 65, 4095, 4096, 262_144, 3_600_000, 30 * day, 800 * day, 3000 * day];
+
+            storage.expire_entries(at(0));
+
+            for (n, offset) in offsets.iter().enumerate() {
+                storage.add_entry(entry(*offset, n as u64));
+            }
+
+            for (n, offset) in offsets.iter().enumerate() {
+                assert!(storage.expire_entries(at(offset - 1)).is_empty());
+                assert_eq!(Some(at(*offset)), storage.next_deadline());
+                assert_eq!(vec![n as u64], ids(storage.expire_entries(at(*offset))));
+            }
+
+            assert!(storage.is_empty());
+        }
+
+        #[test]
+        fn test_entries() {
+            let mut storage = $storage;
+
+            storage.add_entry(entry(10, 1));
+            storage.expire_entries(at(0));
+            storage.add_entry(entry(5000, 2));
+            storage.add_entry(entry(4_000_000, 3));
+
+            let mut ids: Vec<u64> = storage.entries().map(|entry| *entry.data()).collect();
+            ids.sort();
+
+            assert_eq!(vec![1, 2, 3], ids);
+        }
+
+        #[test]
+        fn test_get_entry() {
+            let mut storage = $storage;
+
+            storage.add_entry(entry(10, 1));
+            storage.expire_entries(at(0));
+            storage.add_entry(entry(5000, 2));
+
+            assert_eq!(Some(1), storage.get_entry(EntryId::new(at(10), 1)).map(|entry| *entry.data()));
+            assert_eq!(Some(2), storage.get_entry(EntryId::new(at(5000), 2)).map(|entry| *entry.data()));
+            assert!(storage.get_entry(EntryId::new(at(10), 2)).is_none());
+        }
+
+        #[test]
+        fn test_find_key() {
+            use $crate::EntryKey;
+
+            let mut storage = $storage;
+            let key = EntryKey::new("debounce");
+
+            storage.add_entry(entry(10, 1).with_key(key.clone()));
+            storage.expire_entries(at(0));
+            storage.add_entry(entry(5000, 2).with_key(EntryKey::new("other")));
+
+            assert_eq!(Some(EntryId::new(at(10), 1)), storage.find_key(&key));
+            assert!(storage.move_entry(EntryId::new(at(10), 1), EntryId::new(at(20), 1)));
+            assert_eq!(Some(EntryId::new(at(20), 1)), storage.find_key(&key));
+
+            // Replacing an entry by id drops its key, unless the new entry has the same one
+            storage.add_entry(entry(5000, 2));
+            assert_eq!(None, storage.find_key(&EntryKey::new("other")));
+
+            assert_eq!(vec![1], ids(storage.expire_entries(at(20))));
+            assert_eq!(None, storage.find_key(&key));
+
+            storage.add_entry(entry(30, 3).with_key(key.clone()));
+            assert!(storage.remove_entry(EntryId::new(at(30), 3)).is_some());
+            assert_eq!(None, storage.find_key(&key));
+        }
+
+        #[test]
+        fn test_entries_between() {
+            let mut storage = $storage;
+
+            storage.expire_entries(at(0));
+
+            for (n, offset) in [30, 10, 20, 20, 40, 3_600_000].iter().enumerate() {
+                storage.add_entry(entry(*offset, n as u64));
+            }
+
+            let between = |storage: &Storage<u64>, from, to, limit| -> Vec<u64> {
+                storage.entries_between(at(from), at(to), limit).iter().map(|entry| *entry.data()).collect()
+            };
+
+            assert_eq!(vec![1, 2, 3, 0], between(&storage, 0, 40, 10));
+            assert_eq!(vec![1, 2], between(&storage, 0, 40, 2));
+            assert_eq!(vec![2, 3], between(&storage, 20, 30, 10));
+            assert_eq!(vec![4, 5], between(&storage, 40, 3_600_001, 10));
+            assert!(between(&storage, 40, 20, 10).is_empty());
+        }
+
+        #[test]
+        fn test_matches_reference() {
+            let mut storage = $storage;
+            let mut pending = Vec::new();
+            let mut seed: u64 = 42;
+            let mut next = move |max: u64| {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (seed >> 33) % max
+            };
+
+            let mut now = at(0);
+            storage.expire_entries(now);
+
+            for id in 0..2000 {
+                let offset = match next(3) {
+                    0 => next(100),
+                    1 => next(100_000),
+                    _ => next(100_000_000),
+                };
+
+                let entry = Entry::new(EntryId::new(now + Duration::from_millis(offset), id), id);
+                pending.push(entry.id());
+                storage.add_entry(entry);
+
+                if next(4) == 0 {
+                    now = now + Duration::from_millis(next(50_000));
+
+                    let mut expected: Vec<EntryId> = pending.iter().cloned().filter(|id| id.timestamp() <= now).collect();
+                    expected.sort();
+                    pending.retain(|id| id.timestamp() > now);
+
+                    let expired: Vec<EntryId> = storage.expire_entries(now).iter().map(|e| e.id()).collect();
+
+                    assert_eq!(expected, expired);
+                    assert_eq!(pending.len(), storage.len());
+                    assert_eq!(pending.iter().map(|id| id.timestamp()).min(), storage.next_deadline());
+                }
+            }
+        }
+    };
+}
@@ -0,0 +1,307 @@
+use std::collections::{BTreeMap, HashMap};
+use std::mem;
+use super::{Storage, KeyIndex};
+use super::super::entry::{Entry, EntryId, EntryKey, Timestamp};
+
+/// Number of bits used to address a slot within a level
+const SLOT_BITS: u32 = 6;
+
+/// Number of slots per level
+const SLOTS: usize = 1 << SLOT_BITS;
+
+/// Number of levels. Each level covers 64 times the range of the level below,
+/// starting with one millisecond per slot on the lowest level.
+const LEVELS: usize = 6;
+
+/// Number of milliseconds the wheel covers (about 795 days)
+const MAX_RANGE: u64 = 1 << (SLOT_BITS as usize * LEVELS);
+
+/// Maps a unix timestamp in milliseconds onto an unsigned tick while preserving the order
+fn to_tick(timestamp: Timestamp) -> u64 {
+    (timestamp.millis() as u64) ^ (1 << 63)
+}
+
+#[derive(Debug)]
+struct Level<T: Send + 'static> {
+    /// Bit `n` is set if slot `n` holds at least one entry
+    occupied: u64,
+    slots: Vec<HashMap<EntryId, Entry<T>>>,
+}
+
+/// A hierarchical timing wheel with millisecond resolution.
+///
+/// Entries are hashed into the slot of the level that matches how far their
+/// expiration lies in the future. As time advances, the entries of higher levels
+/// cascade down into the lower levels until they expire on the lowest level.
+/// This makes adding, removing and expiring an entry `O(1)`.
+///
+/// Entries expiring after the range of the wheel (about 795 days) as well as
+/// entries added before the wheel has been advanced for the first time are kept
+/// in an ordered overflow area and moved into the wheel once they are in range.
+///
+/// The wheel never moves backwards in time: if `now` is earlier than the last time
+/// the wheel was advanced to, entries are expired relative to the latter.
+#[derive(Debug)]
+pub struct TimingWheel<T: Send + 'static> {
+    /// The tick up to which all entries in the wheel have been expired
+    elapsed: Option<u64>,
+    levels: Vec<Level<T>>,
+    /// The level and slot of every entry that is stored in the wheel
+    index: HashMap<EntryId, (usize, usize)>,
+    overflow: BTreeMap<EntryId, Entry<T>>,
+    keys: KeyIndex,
+}
+
+impl<T: Send + 'static> Level<T> {
+    fn new() -> Self {
+        Level {
+            occupied: 0,
+            slots: (0..SLOTS).map(|_| HashMap::new()).collect(),
+        }
+    }
+
+    /// Number of ticks covered by a single slot of this level
+    fn slot_range(level: usize) -> u64 {
+        1 << (SLOT_BITS as usize * level)
+    }
+
+    /// Returns the next occupied slot after the slot `elapsed` falls into,
+    /// along with the tick at which that slot begins.
+    fn next_occupied(&self, level: usize, elapsed: u64) -> Option<(usize, u64)> {
+        let slot_range = Self::slot_range(level);
+        let level_range = slot_range << SLOT_BITS;
+        let current = ((elapsed / slot_range) % SLOTS as u64) as u32;
+
+        let candidates = match current {
+            63 => 0,
+            _ => self.occupied & (!0u64 << (current + 1)),
+        };
+
+        if candidates == 0 {
+            return None;
+        }
+
+        let slot = candidates.trailing_zeros() as usize;
+        let level_start = elapsed & !(level_range - 1);
+
+        Some((slot, level_start + slot as u64 * slot_range))
+    }
+}
+
+impl<T: Send + 'static> TimingWheel<T> {
+    pub fn new() -> Self {
+        TimingWheel {
+            elapsed: None,
+            levels: (0..LEVELS).map(|_| Level::new()).collect(),
+            index: HashMap::new(),
+            overflow: BTreeMap::new(),
+            keys: KeyIndex::default(),
+        }
+    }
+
+    fn insert(&mut self, entry: Entry<T>) {
+        let id = entry.id();
+        let tick = to_tick(id.timestamp());
+
+        let elapsed = match self.elapsed {
+            Some(elapsed) if tick > elapsed && (tick ^ elapsed) < MAX_RANGE => elapsed,
+            _ => {
+                self.overflow.insert(id, entry);
+                return;
+            }
+        };
+
+        // The level is determined by the most significant bit in which
+        // the expiration differs from the current time
+        let significant = 63 - ((tick ^ elapsed) | (SLOTS as u64 - 1)).leading_zeros() as usize;
+        let level = significant / SLOT_BITS as usize;
+        let slot = ((tick >> (level * SLOT_BITS as usize)) % SLOTS as u64) as usize;
+
+        self.levels[level].occupied |= 1 << slot;
+        self.levels[level].slots[slot].insert(id, entry);
+        self.index.insert(id, (level, slot));
+    }
+
+    /// Removes the entry from the wheel or the overflow area without updating the key index
+    fn take_entry(&mut self, id: EntryId) -> Option<Entry<T>> {
+        match self.index.remove(&id) {
+            Some((level, slot)) => {
+                let level = &mut self.levels[level];
+                let entry = level.slots[slot].remove(&id);
+
+                if level.slots[slot].is_empty() {
+                    level.occupied &= !(1 << slot);
+                }
+
+                entry
+            }
+            None => self.overflow.remove(&id),
+        }
+    }
+
+    /// Returns the level and slot that need to be processed next, along with the tick at which it begins
+    fn next_expiration(&self) -> Option<(usize, usize, u64)> {
+        let elapsed = self.elapsed?;
+
+        self.levels
+            .iter()
+            .enumerate()
+            .filter_map(|(n, level)| level.next_occupied(n, elapsed).map(|(slot, tick)| (n, slot, tick)))
+            .next()
+    }
+
+    fn take_slot(&mut self, level: usize, slot: usize) -> HashMap<EntryId, Entry<T>> {
+        self.levels[level].occupied &= !(1 << slot);
+
+        let entries = mem::replace(&mut self.levels[level].slots[slot], HashMap::new());
+
+        for id in entries.keys() {
+            self.index.remove(id);
+        }
+
+        entries
+    }
+
+    /// Moves all overflowing entries which are now in range of the wheel into the wheel
+    fn migrate_overflow(&mut self, elapsed: u64) {
+        loop {
+            let id = match self.overflow.keys().next() {
+                Some(&id) if (to_tick(id.timestamp()) ^ elapsed) < MAX_RANGE => id,
+                _ => break,
+            };
+
+            // We know that the entry exists, because we just got its id
+            let entry = self.overflow.remove(&id).unwrap();
+            self.insert(entry);
+        }
+    }
+}
+
+impl<T: Send + 'static> Default for TimingWheel<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send + 'static> Storage<T> for TimingWheel<T> {
+    fn add_entry(&mut self, entry: Entry<T>) {
+        self.remove_entry(entry.id());
+        self.keys.insert(&entry);
+        self.insert(entry);
+    }
+
+    fn remove_entry(&mut self, id: EntryId) -> Option<Entry<T>> {
+        let entry = self.take_entry(id);
+
+        if let Some(ref entry) = entry {
+            self.keys.remove(entry);
+        }
+
+        entry
+    }
+
+    fn has_entry(&self, id: EntryId) -> bool {
+        self.index.contains_key(&id) || self.overflow.contains_key(&id)
+    }
+
+    fn get_entry(&self, id: EntryId) -> Option<&Entry<T>> {
+        match self.index.get(&id) {
+            Some(&(level, slot)) => self.levels[level].slots[slot].get(&id),
+            None => self.overflow.get(&id),
+        }
+    }
+
+    fn find_key(&self, key: &EntryKey) -> Option<EntryId> {
+        self.keys.get(key)
+    }
+
+    fn expire_entries(&mut self, now: Timestamp) -> Vec<Entry<T>> {
+        let now = match self.elapsed {
+            Some(elapsed) if elapsed > to_tick(now) => elapsed,
+            _ => to_tick(now),
+        };
+
+        if self.elapsed.is_none() {
+            self.elapsed = Some(now);
+        }
+
+        let mut expired = Vec::new();
+
+        while let Some((level, slot, tick)) = self.next_expiration() {
+            if tick > now {
+                break;
+            }
+
+            self.elapsed = Some(tick);
+
+            // Entries that don't expire yet are re-inserted relative
+            // to the new time, which moves them to a lower level
+            for (_, entry) in self.take_slot(level, slot) {
+                if to_tick(entry.id().timestamp()) <= tick {
+                    expired.push(entry);
+                } else {
+                    self.insert(entry);
+                }
+            }
+        }
+
+        self.elapsed = Some(now);
+
+        let pending = match self.overflow.keys().find(|id| to_tick(id.timestamp()) > now) {
+            Some(&id) => self.overflow.split_off(&id),
+            None => BTreeMap::new(),
+        };
+
+        expired.extend(mem::replace(&mut self.overflow, pending).into_values());
+        expired.sort_by_key(|entry| entry.id());
+
+        for entry in &expired {
+            self.keys.remove(entry);
+        }
+
+        self.migrate_overflow(now);
+
+        expired
+    }
+
+    fn next_deadline(&self) -> Option<Timestamp> {
+        let overflow = self.overflow.keys().next().map(|id| id.timestamp());
+
+        // All entries in the next slot expire before those in any other slot,
+        // but within a slot of a higher level they are not ordered.
+        let wheel = self.next_expiration().and_then(|(level, slot, _)| {
+            self.levels[level].slots[slot].keys().map(|id| id.timestamp()).min()
+        });
+
+        match (overflow, wheel) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.index.len() + self.overflow.len()
+    }
+
+    fn entries<'a>(&'a self) -> Box<Iterator<Item = &'a Entry<T>> + 'a> {
+        let wheel = self.levels.iter().flat_map(|level| level.slots.iter()).flat_map(|slot| slot.values());
+
+        Box::new(wheel.chain(self.overflow.values()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    storage_tests!(TimingWheel::new());
+
+    #[test]
+    fn test_tick_preserves_order() {
+        let timestamps = [-1_000, -1, 0, 1, 1_500_000_000_000];
+
+        for pair in timestamps.windows(2) {
+            assert!(to_tick(Timestamp::from_millis(pair[0])) < to_tick(Timestamp::from_millis(pair[1])));
+        }
+    }
+}
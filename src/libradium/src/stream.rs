@@ -0,0 +1,256 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll, Waker};
+use futures_core::Stream;
+use super::entry::Entry;
+use super::command::Command;
+use super::subscription::{self, Sink, Subscriber, Filter};
+use super::sync::Sender;
+
+/// An `EntryStream` is a `futures::Stream` of the expired entries of a [`Core`] that match its filter.
+///
+/// `capacity` is a soft limit on the number of buffered entries. Once the buffer is full, the [`Core`]
+/// stops expiring entries until the stream has been polled, so that entries are kept in the [`Storage`]
+/// instead of piling up in memory. This pauses all listeners and subscriptions of the [`Core`].
+///
+/// The buffer is only checked before a worker expires the next batch of entries, so the whole batch
+/// is added to the buffer even if that exceeds `capacity`. A batch holds all entries that are due
+/// when the worker checks, and each shard of the [`Core`] adds its own batch.
+///
+/// The stream ends once the [`Core`] has shut down and is unsubscribed when it is dropped.
+///
+/// [`Core`]: struct.Core.html
+/// [`Storage`]: trait.Storage.html
+pub struct EntryStream<T: Send + 'static> {
+    id: u64,
+    buffer: Arc<Mutex<Buffer<T>>>,
+    capacity: usize,
+    txs: Vec<Sender<Command<T>>>,
+}
+
+struct Buffer<T: Send + 'static> {
+    entries: VecDeque<Entry<T>>,
+    waker: Option<Waker>,
+    /// The number of sinks that are still kept by a worker
+    sinks: usize,
+}
+
+/// The end of an `EntryStream` that is kept by the worker
+struct StreamSink<T: Send + 'static> {
+    buffer: Weak<Mutex<Buffer<T>>>,
+    capacity: usize,
+}
+
+impl<T: Send + 'static> EntryStream<T> {
+    /// Creates an `EntryStream` along with the `Subscriber`s that have to be handed to the worker
+    /// of each shard, one for each of the senders in `txs`
+    pub fn new<F>(filter: F, capacity: usize, clone_entry: fn(&Entry<T>) -> Entry<T>, txs: Vec<Sender<Command<T>>>) -> (Self, Vec<Subscriber<T>>)
+    where
+        F: Fn(&Entry<T>) -> bool + Send + 'static,
+    {
+        let capacity = capacity.max(1);
+        let id = subscription::next_id();
+        let filter: Filter<T> = Arc::new(Mutex::new(filter));
+
+        let buffer = Arc::new(Mutex::new(Buffer {
+            entries: VecDeque::with_capacity(capacity),
+            waker: None,
+            sinks: txs.len(),
+        }));
+
+        let subscribers = txs
+            .iter()
+            .map(|_| {
+                let sink = StreamSink {
+                    buffer: Arc::downgrade(&buffer),
+                    capacity,
+                };
+
+                Subscriber::new(id, filter.clone(), clone_entry, sink)
+            })
+            .collect();
+
+        (EntryStream { id, buffer, capacity, txs }, subscribers)
+    }
+}
+
+impl<T: Send + 'static> Stream for EntryStream<T> {
+    type Item = Entry<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Entry<T>>> {
+        let mut buffer = self.buffer.lock().unwrap();
+        let was_full = buffer.entries.len() >= self.capacity;
+
+        match buffer.entries.pop_front() {
+            Some(entry) => {
+                // The workers have paused expiration and wait to be woken up
+                if was_full && buffer.entries.len() < self.capacity {
+                    for tx in &self.txs {
+                        let _ = tx.try_send(Command::Tick);
+                    }
+                }
+
+                Poll::Ready(Some(entry))
+            }
+            None if buffer.sinks == 0 => Poll::Ready(None),
+            None => {
+                buffer.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.buffer.lock().unwrap().entries.len(), None)
+    }
+}
+
+impl<T: Send + 'static> Drop for EntryStream<T> {
+    fn drop(&mut self) {
+        // The worker might already be gone, in which case there is nothing to unsubscribe from.
+        // If the queue is full, the worker removes the subscriber once it fails to deliver to it.
+        for tx in &self.txs {
+            let _ = tx.try_send(Command::Unsubscribe(self.id));
+        }
+    }
+}
+
+impl<T: Send + 'static> fmt::Debug for EntryStream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EntryStream")
+            .field("id", &self.id)
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+impl<T: Send + 'static> Sink<T> for StreamSink<T> {
+    fn send(&self, entry: Entry<T>) -> bool {
+        let buffer = match self.buffer.upgrade() {
+            Some(buffer) => buffer,
+            None => return false,
+        };
+
+        let mut buffer = buffer.lock().unwrap();
+
+        buffer.entries.push_back(entry);
+
+        if let Some(waker) = buffer.waker.take() {
+            waker.wake();
+        }
+
+        true
+    }
+
+    fn is_full(&self) -> bool {
+        self.buffer.upgrade().map_or(false, |buffer| buffer.lock().unwrap().entries.len() >= self.capacity)
+    }
+}
+
+impl<T: Send + 'static> Drop for StreamSink<T> {
+    /// Ends the stream once the workers of all shards have stopped or unsubscribed it
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.upgrade() {
+            let mut buffer = buffer.lock().unwrap();
+
+            buffer.sinks -= 1;
+
+            if buffer.sinks > 0 {
+                return;
+            }
+
+            if let Some(waker) = buffer.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+    use futures_executor::block_on_stream;
+    use super::super::{Core, Listener, BTreeStorage, ManualClock, EntryId, Timestamp, ShutdownPolicy};
+    use super::*;
+
+    struct NoopListener;
+
+    impl Listener<u64> for NoopListener {
+        fn on_expired(&self, _: Vec<Entry<u64>>, _: Timestamp) {}
+    }
+
+    fn spawn() -> (Core<u64>, ManualClock) {
+        let clock = ManualClock::new(Timestamp::from_millis(0));
+        let core = Core::spawn_with_clock(BTreeStorage::new(), clock.clone(), NoopListener);
+
+        (core, clock)
+    }
+
+    fn entry(millis: i64) -> Entry<u64> {
+        Entry::new(EntryId::new(Timestamp::from_millis(millis), millis as u64), millis as u64)
+    }
+
+    #[test]
+    fn test_stream() {
+        let (core, clock) = spawn();
+        let mut stream = block_on_stream(core.stream(|entry| entry.data() % 2 == 0, 10).unwrap());
+
+        for i in 1..5 {
+            core.add_entry(entry(i)).unwrap();
+        }
+
+        clock.set(Timestamp::from_millis(4));
+
+        assert_eq!(Some(2), stream.next().map(|entry| *entry.data()));
+        assert_eq!(Some(4), stream.next().map(|entry| *entry.data()));
+
+        core.shutdown(ShutdownPolicy::Return).unwrap();
+
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn test_backpressure() {
+        let (core, clock) = spawn();
+        let mut stream = block_on_stream(core.stream(|_| true, 1).unwrap());
+
+        for i in 1..4 {
+            core.add_entry(entry(i)).unwrap();
+        }
+
+        clock.set(Timestamp::from_millis(1));
+        assert_eq!(2, core.count().unwrap());
+
+        // The buffer is full, so the remaining entries are kept
+        clock.set(Timestamp::from_millis(3));
+        assert_eq!(2, core.count().unwrap());
+
+        assert_eq!(Some(1), stream.next().map(|entry| *entry.data()));
+        assert_eq!(Some(2), stream.next().map(|entry| *entry.data()));
+        assert_eq!(Some(3), stream.next().map(|entry| *entry.data()));
+        assert_eq!(0, core.count().unwrap());
+    }
+
+    #[test]
+    fn test_wakes_up_pending_stream() {
+        let (core, clock) = spawn();
+        let stream = core.stream(|_| true, 10).unwrap();
+        let (tx, rx) = mpsc::channel();
+
+        let consumer = ::std::thread::spawn(move || {
+            for entry in block_on_stream(stream) {
+                tx.send(*entry.data()).unwrap();
+            }
+        });
+
+        core.add_entry(entry(1)).unwrap();
+        clock.set(Timestamp::from_millis(1));
+
+        assert_eq!(1, rx.recv().unwrap());
+
+        core.shutdown(ShutdownPolicy::Return).unwrap();
+        consumer.join().unwrap();
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt;
+use std::sync::{mpsc, Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use super::entry::Entry;
+use super::command::Command;
+use super::sync::Sender;
+
+/// The last id handed out to a `Subscription`
+static LAST_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The filter of a subscription, which is shared by its subscribers in all shards
+pub type Filter<T> = Arc<Mutex<Fn(&Entry<T>) -> bool + Send>>;
+
+/// Returns the id for a new subscription, which its subscribers in all shards share
+pub fn next_id() -> u64 {
+    LAST_ID.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// A `Subscription` receives copies of the expired entries of a [`Core`] that match its filter.
+///
+/// The `Subscription` is removed from the [`Core`] when it is dropped. Like a clone of the [`Core`],
+/// it keeps the worker running. Receiving fails once the [`Core`] has shut down
+/// and all entries that were sent before have been received.
+///
+/// [`Core`]: struct.Core.html
+pub struct Subscription<T: Send + 'static> {
+    id: u64,
+    receiver: mpsc::Receiver<Entry<T>>,
+    txs: Vec<Sender<Command<T>>>,
+}
+
+/// A `Sink` is where a `Subscriber` puts the entries that match its filter
+pub trait Sink<T: Send + 'static>: Send {
+    /// Returns `false` if the receiving end is gone
+    fn send(&self, entry: Entry<T>) -> bool;
+
+    /// Determines if the receiving end can't keep up, in which case the worker stops expiring entries
+    fn is_full(&self) -> bool {
+        false
+    }
+}
+
+impl<T: Send + 'static> Sink<T> for mpsc::Sender<Entry<T>> {
+    fn send(&self, entry: Entry<T>) -> bool {
+        mpsc::Sender::send(self, entry).is_ok()
+    }
+}
+
+/// The part of a [`Subscription`] that is kept by the worker
+///
+/// [`Subscription`]: struct.Subscription.html
+pub struct Subscriber<T: Send + 'static> {
+    id: u64,
+    filter: Filter<T>,
+    sink: Box<Sink<T>>,
+    clone_entry: fn(&Entry<T>) -> Entry<T>,
+}
+
+impl<T: Send + 'static> Subscription<T> {
+    /// Creates a `Subscription` along with the `Subscriber`s that have to be handed to the worker
+    /// of each shard, one for each of the senders in `txs`
+    pub fn new<F>(filter: F, clone_entry: fn(&Entry<T>) -> Entry<T>, txs: Vec<Sender<Command<T>>>) -> (Self, Vec<Subscriber<T>>)
+    where
+        F: Fn(&Entry<T>) -> bool + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let id = next_id();
+        let filter: Filter<T> = Arc::new(Mutex::new(filter));
+
+        let subscribers = txs
+            .iter()
+            .map(|_| Subscriber::new(id, filter.clone(), clone_entry, sender.clone()))
+            .collect();
+
+        (Subscription { id, receiver, txs }, subscribers)
+    }
+
+    /// Blocks until the next matching entry expires
+    pub fn recv(&self) -> Result<Entry<T>, mpsc::RecvError> {
+        self.receiver.recv()
+    }
+
+    pub fn try_recv(&self) -> Result<Entry<T>, mpsc::TryRecvError> {
+        self.receiver.try_recv()
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Entry<T>, mpsc::RecvTimeoutError> {
+        self.receiver.recv_timeout(timeout)
+    }
+
+    /// Returns an iterator over the entries that have already been received
+    pub fn try_iter<'a>(&'a self) -> mpsc::TryIter<'a, Entry<T>> {
+        self.receiver.try_iter()
+    }
+
+    /// Returns an iterator that blocks for each entry and ends once the `Core` has shut down
+    pub fn iter<'a>(&'a self) -> mpsc::Iter<'a, Entry<T>> {
+        self.receiver.iter()
+    }
+}
+
+impl<T: Send + 'static> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        // The worker might already be gone, in which case there is nothing to unsubscribe from.
+        // If the queue is full, the worker removes the subscriber once it fails to deliver to it.
+        for tx in &self.txs {
+            let _ = tx.try_send(Command::Unsubscribe(self.id));
+        }
+    }
+}
+
+impl<T: Send + 'static> fmt::Debug for Subscription<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Subscription").field("id", &self.id).finish()
+    }
+}
+
+impl<T: Send + 'static> Subscriber<T> {
+    pub fn new<S>(id: u64, filter: Filter<T>, clone_entry: fn(&Entry<T>) -> Entry<T>, sink: S) -> Self
+    where
+        S: Sink<T> + 'static,
+    {
+        Subscriber {
+            id,
+            filter,
+            sink: Box::new(sink),
+            clone_entry,
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Sends a copy of the entry if it matches the filter.
+    /// Returns `false` if the `Subscription` has been dropped.
+    pub fn deliver(&self, entry: &Entry<T>) -> bool {
+        if !(*self.filter.lock().unwrap())(entry) {
+            return true;
+        }
+
+        self.sink.send((self.clone_entry)(entry))
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.sink.is_full()
+    }
+}
+
+impl<T: Send + 'static> fmt::Debug for Subscriber<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Subscriber").field("id", &self.id).finish()
+    }
+}
@@ -1,6 +1,5 @@
 use std::sync::mpsc;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 use std::convert;
 
 #[derive(Debug)]
@@ -9,12 +8,28 @@ pub struct SendError<T>(pub T);
 #[derive(Debug)]
 pub struct RecvError;
 
+#[derive(Debug)]
+pub enum TrySendError<T> {
+    /// The channel is bounded and has no room left
+    Full(T),
+    Disconnected(T),
+}
+
 impl<T> convert::From<mpsc::SendError<T>> for SendError<T> {
     fn from(err: mpsc::SendError<T>) -> Self {
         SendError(err.0)
     }
 }
 
+impl<T> convert::From<mpsc::TrySendError<T>> for TrySendError<T> {
+    fn from(err: mpsc::TrySendError<T>) -> Self {
+        match err {
+            mpsc::TrySendError::Full(t) => TrySendError::Full(t),
+            mpsc::TrySendError::Disconnected(t) => TrySendError::Disconnected(t),
+        }
+    }
+}
+
 impl convert::From<mpsc::RecvError> for RecvError {
     fn from(_: mpsc::RecvError) -> Self {
         RecvError {}
@@ -22,85 +37,82 @@ impl convert::From<mpsc::RecvError> for RecvError {
 }
 
 #[derive(Debug)]
-struct Inner {
-    /// Counter for the currently pending messages waiting to be received
-    pending: AtomicUsize,
+pub struct Sender<T> {
+    tx: SenderInner<T>,
 }
 
 #[derive(Debug)]
-pub struct Sender<T> {
-    tx: mpsc::Sender<T>,
-    inner: Arc<Inner>,
+enum SenderInner<T> {
+    Unbounded(mpsc::Sender<T>),
+    Bounded(mpsc::SyncSender<T>),
 }
 
 #[derive(Debug)]
 pub struct Receiver<T> {
     tx: mpsc::Receiver<T>,
-    inner: Arc<Inner>,
 }
 
 pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
     let (sender, receiver) = mpsc::channel();
-    let inner = Arc::new(Inner::new());
 
-    (
-        Sender::new(inner.clone(), sender),
-        Receiver::new(inner.clone(), receiver),
-    )
+    (Sender::new(SenderInner::Unbounded(sender)), Receiver::new(receiver))
 }
 
-impl Inner {
-    pub fn new() -> Self {
-        Inner { pending: AtomicUsize::new(0) }
-    }
+/// Creates a channel that holds at most `bound` values
+pub fn sync_channel<T>(bound: usize) -> (Sender<T>, Receiver<T>) {
+    let (sender, receiver) = mpsc::sync_channel(bound);
 
-    pub fn inc_pending(&self) -> usize {
-        self.pending.fetch_add(1, Ordering::Acquire)
-    }
-
-    pub fn dec_pending(&self) -> usize {
-        self.pending.fetch_sub(1, Ordering::Acquire)
-    }
-
-    pub fn pending(&self) -> usize {
-        self.pending.load(Ordering::SeqCst)
-    }
+    (Sender::new(SenderInner::Bounded(sender)), Receiver::new(receiver))
 }
 
 impl<T> Clone for Sender<T> {
     fn clone(&self) -> Self {
-        Sender::new(self.inner.clone(), self.tx.clone())
+        match self.tx {
+            SenderInner::Unbounded(ref tx) => Sender::new(SenderInner::Unbounded(tx.clone())),
+            SenderInner::Bounded(ref tx) => Sender::new(SenderInner::Bounded(tx.clone())),
+        }
     }
 }
 
 impl<T> Sender<T> {
-    fn new(inner: Arc<Inner>, tx: mpsc::Sender<T>) -> Self {
-        Sender { inner, tx }
+    fn new(tx: SenderInner<T>) -> Self {
+        Sender { tx }
     }
 
+    /// Sends a value, blocking until there is room if the channel is bounded
     pub fn send(&self, t: T) -> Result<(), SendError<T>> {
-        self.tx.send(t).and_then(|_| {
-            self.inner.inc_pending();
-            Ok(())
-        })?;
+        match self.tx {
+            SenderInner::Unbounded(ref tx) => Ok(tx.send(t)?),
+            SenderInner::Bounded(ref tx) => Ok(tx.send(t)?),
+        }
+    }
 
-        Ok(())
+    /// Sends a value without blocking, failing if a bounded channel is full
+    pub fn try_send(&self, t: T) -> Result<(), TrySendError<T>> {
+        match self.tx {
+            SenderInner::Unbounded(ref tx) => tx.send(t).map_err(|err| TrySendError::Disconnected(err.0)),
+            SenderInner::Bounded(ref tx) => Ok(tx.try_send(t)?),
+        }
     }
 }
 
 impl<T> Receiver<T> {
-    fn new(inner: Arc<Inner>, tx: mpsc::Receiver<T>) -> Self {
-        Receiver { inner, tx }
+    fn new(tx: mpsc::Receiver<T>) -> Self {
+        Receiver { tx }
     }
 
-    pub fn has_incoming(&self) -> bool {
-        self.inner.pending() > 0
+    /// Blocks until a value is received
+    pub fn recv(&self) -> Result<T, RecvError> {
+        Ok(self.tx.recv()?)
     }
 
-    pub fn recv(&self) -> Result<T, RecvError> {
-        Ok(self.tx.recv().and_then(|t| {
-            self.inner.dec_pending();
-            Ok(t)
-        })?)
+    /// Blocks until either a value is received or the `timeout` has elapsed.
+    /// Returns `Ok(None)` if no value was received in time.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Option<T>, RecvError> {
+        match self.tx.recv_timeout(timeout) {
+            Ok(t) => Ok(Some(t)),
+            Err(mpsc::RecvTimeoutError::Timeout) => Ok(None),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(RecvError {}),
+        }
     }
 }
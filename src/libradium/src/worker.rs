@@ -1,46 +1,91 @@
+use std::io;
 use std::thread;
-use std::time::Instant;
 use std::time::Duration;
-use super::entry::Entry;
+use std::sync::{Arc, Mutex};
+use super::entry::{Entry, EntryId, Timestamp};
 use super::storage::Storage;
 use super::sync::Receiver;
 use super::command::Command;
+use super::core::{CommandResult, CommandError, KeyConflict, Upserted, ShutdownPolicy};
+use super::capacity::{Capacity, OverflowPolicy};
+use super::clock::Clock;
+use super::subscription::Subscriber;
 
+/// A `Listener` is notified by the worker of a [`Core`] about changes to its entries.
 ///
-/// Minimum duration between expiration checks in seconds
+/// All methods are called on the worker thread, in the order in which the changes are applied
+/// to the [`Storage`]. A [`Core`] with several shards shares its listener between their workers,
+/// so changes to entries of different shards may be reported in any order.
+/// Only `on_expired` has to be implemented.
 ///
-const CHECK_INTERVAL: u64 = 1;
+/// [`Core`]: struct.Core.html
+/// [`Storage`]: trait.Storage.html
+pub trait Listener<T: Send + 'static>: Send {
+    /// Called with the entries that have expired, ordered by their id. Never called with an empty `Vec`.
+    ///
+    /// `fired_at` is the time at which the worker expired the entries, which lags behind
+    /// their timestamps if the worker was busy or the entries were added too late.
+    fn on_expired(&self, entries: Vec<Entry<T>>, fired_at: Timestamp);
 
-///
-/// Duration of sleep between loop turns (in milliseconds)
-///
-const SLEEP_DURATION: u64 = 100;
+    /// Called when an entry has been added, including the next occurrence of a recurring entry
+    fn on_added(&self, _entry: &Entry<T>) {}
 
-pub trait Listener<T: Send + 'static>: Send {
-    fn on_expired(&self, entry: Vec<Entry<T>>);
+    /// Called when an entry has been removed before it expired, also when the `Core` shuts down
+    /// and hands its entries back
+    fn on_removed(&self, _entry: &Entry<T>) {}
+
+    /// Called when the entry with the id `from` has been moved to the id `to`
+    fn on_rescheduled(&self, _from: EntryId, _to: EntryId) {}
+
+    /// Called when an entry could not be added
+    fn on_rejected(&self, _entry: &Entry<T>, _error: &CommandError) {}
 }
 
-#[derive(Debug)]
-enum Action {
-    ReceiveCommand,
-    CheckExpired,
-    Sleep,
+/// A `Listener` that is shared by the workers of all shards of a [`Core`], which call it one at a time
+///
+/// [`Core`]: struct.Core.html
+impl<T: Send + 'static, L: Listener<T>> Listener<T> for Arc<Mutex<L>> {
+    fn on_expired(&self, entries: Vec<Entry<T>>, fired_at: Timestamp) {
+        self.lock().unwrap().on_expired(entries, fired_at)
+    }
+
+    fn on_added(&self, entry: &Entry<T>) {
+        self.lock().unwrap().on_added(entry)
+    }
+
+    fn on_removed(&self, entry: &Entry<T>) {
+        self.lock().unwrap().on_removed(entry)
+    }
+
+    fn on_rescheduled(&self, from: EntryId, to: EntryId) {
+        self.lock().unwrap().on_rescheduled(from, to)
+    }
+
+    fn on_rejected(&self, entry: &Entry<T>, error: &CommandError) {
+        self.lock().unwrap().on_rejected(entry, error)
+    }
 }
 
 pub struct Worker<T: Send + 'static> {
-    storage: Storage<T>,
+    storage: Box<Storage<T>>,
+    clock: Box<Clock>,
     receiver: Receiver<Command<T>>,
     listener: Box<Listener<T>>,
-    last_checked: Option<Instant>,
+    subscribers: Vec<Subscriber<T>>,
+    capacity: Option<Capacity<T>>,
+    /// Total size of all stored entries as determined by the capacity
+    used_bytes: usize,
+    stopped: bool,
 }
 
 pub fn spawn_worker<T: Send + 'static>(
-    storage: Storage<T>,
+    storage: Box<Storage<T>>,
+    clock: Box<Clock>,
     receiver: Receiver<Command<T>>,
     listener: Box<Listener<T>>,
 ) -> thread::JoinHandle<()> {
     thread::spawn(move || {
-        let worker = Worker::new(storage, receiver, listener);
+        let worker = Worker::new(storage, clock, receiver, listener);
 
         worker.run();
     })
@@ -48,71 +93,358 @@ pub fn spawn_worker<T: Send + 'static>(
 
 impl<T: Send + 'static> Worker<T> {
     pub fn new(
-        storage: Storage<T>,
+        storage: Box<Storage<T>>,
+        clock: Box<Clock>,
         receiver: Receiver<Command<T>>,
         listener: Box<Listener<T>>,
     ) -> Worker<T> {
         Worker {
             storage,
+            clock,
             receiver,
             listener,
-            last_checked: None,
+            subscribers: Vec::new(),
+            capacity: None,
+            used_bytes: 0,
+            stopped: false,
         }
     }
 
     pub fn run(mut self) {
-        let sleep_dur = Duration::from_millis(SLEEP_DURATION);
+        while !self.stopped {
+            self.check_expired();
+            self.check_snapshot();
+            self.handle_incoming();
+        }
+    }
+
+    /// Blocks until either a command is received, the next entry expires or a snapshot is due
+    fn handle_incoming(&mut self) {
+        let incoming = match self.next_timeout() {
+            None => self.receiver.recv().map(Some),
+            Some(timeout) => self.receiver.recv_timeout(timeout),
+        };
+
+        match incoming {
+            // Every handle of the `Core` is gone, so no one is interested in the entries anymore
+            Err(_) => self.stopped = true,
+            Ok(Some(command)) => self.handle_command(command),
+            Ok(None) => {}
+        }
+    }
+
+    fn handle_command(&mut self, command: Command<T>) {
+        // A failing reply only means that the caller is no longer interested in the result
+        match command {
+            Command::AddEntry(entry, reply) => {
+                let _ = reply.send(self.add_entry(entry));
+            }
+            Command::RemoveEntry(id, reply) => {
+                let _ = reply.send(self.take_entry(id));
+            }
+            // Batches are applied without expiring entries or handling other commands in between
+            Command::AddEntries(entries, reply) => {
+                let results = entries.into_iter().map(|entry| self.add_entry(entry)).collect();
 
-        loop {
-            match self.determine_action() {
-                Action::ReceiveCommand => self.handle_incoming(),
-                Action::CheckExpired => self.check_expired(),
-                Action::Sleep => thread::sleep(sleep_dur),
+                let _ = reply.send(results);
+            }
+            Command::RemoveEntries(ids, reply) => {
+                let entries = ids.into_iter().map(|id| self.take_entry(id)).collect();
+
+                let _ = reply.send(entries);
+            }
+            Command::Upsert(entry, policy, reply) => {
+                let _ = reply.send(self.upsert(entry, policy));
+            }
+            Command::RemoveByKey(key, reply) => {
+                let entry = self.storage.find_key(&key).and_then(|id| self.take_entry(id));
+
+                let _ = reply.send(entry);
+            }
+            Command::Reschedule(id, timestamp, reply) => {
+                let _ = reply.send(self.reschedule(id, timestamp));
+            }
+            Command::Snapshot(reply) => {
+                let _ = reply.send(self.storage.snapshot());
+            }
+            Command::SetCapacity(capacity, reply) => {
+                self.set_capacity(capacity);
+                let _ = reply.send(());
+            }
+            Command::Query(query) => query.run(&*self.storage),
+            Command::Subscribe(subscriber, reply) => {
+                self.subscribers.push(subscriber);
+                let _ = reply.send(());
+            }
+            Command::Unsubscribe(id) => self.subscribers.retain(|subscriber| subscriber.id() != id),
+            Command::Tick => {}
+            Command::Shutdown(policy, reply) => {
+                let result = self.shutdown(policy);
+
+                self.stopped = result.is_ok();
+                let _ = reply.send(result);
             }
         }
     }
 
-    fn determine_action(&self) -> Action {
-        if self.receiver.has_incoming() {
-            return Action::ReceiveCommand;
+    fn shutdown(&mut self, policy: ShutdownPolicy) -> io::Result<Vec<Entry<T>>> {
+        match policy {
+            ShutdownPolicy::ExpireAll => {
+                let entries = self.drain();
+
+                if !entries.is_empty() {
+                    let now = self.clock.now();
+
+                    self.notify_expired(entries, now);
+                }
+
+                Ok(Vec::new())
+            }
+            ShutdownPolicy::Return => {
+                let entries = self.drain();
+
+                for entry in &entries {
+                    self.listener.on_removed(entry);
+                }
+
+                Ok(entries)
+            }
+            ShutdownPolicy::Persist => self.storage.snapshot().map(|_| Vec::new()),
         }
+    }
+
+    /// Removes and returns all entries ordered by their id, without re-arming recurring entries
+    fn drain(&mut self) -> Vec<Entry<T>> {
+        let mut ids: Vec<EntryId> = self.storage.entries().map(|entry| entry.id()).collect();
 
-        if self.needs_checking() {
-            return Action::CheckExpired;
+        ids.sort();
+
+        ids.into_iter().filter_map(|id| self.remove_entry(id)).collect()
+    }
+
+    fn add_entry(&mut self, entry: Entry<T>) -> CommandResult {
+        if let Err(err) = self.make_room(&entry) {
+            self.listener.on_rejected(&entry, &err);
+            return Err(err);
         }
 
-        Action::Sleep
+        self.store(entry);
+
+        Ok(())
     }
 
-    fn handle_incoming(&mut self) {
-        let incoming = self.receiver.recv();
+    /// Adds an entry unless an entry with the same key exists and `policy` decides to keep that one.
+    /// If the new entry replaces the existing one but can't be added, the existing entry is restored.
+    fn upsert(&mut self, entry: Entry<T>, policy: KeyConflict) -> CommandResult<Upserted> {
+        let id = entry.id();
 
-        match incoming {
-            Err(_) => panic!("channel disconnected"),
-            Ok(command) => self.handle_command(command),
+        let existing = match entry.key().and_then(|key| self.storage.find_key(key)) {
+            Some(existing) => existing,
+            None => return self.add_entry(entry).map(|_| Upserted::Added(id)),
+        };
+
+        match policy {
+            KeyConflict::KeepExisting => return Ok(Upserted::Kept(existing)),
+            KeyConflict::KeepEarliest if existing.timestamp() <= id.timestamp() => return Ok(Upserted::Kept(existing)),
+            _ => {}
+        }
+
+        let replaced = self.take_entry(existing);
+
+        match self.add_entry(entry) {
+            Ok(()) => Ok(Upserted::Added(id)),
+            Err(err) => {
+                if let Some(replaced) = replaced {
+                    self.store(replaced);
+                }
+
+                Err(err)
+            }
         }
     }
 
-    fn handle_command(&mut self, command: Command<T>) {
-        match command {
-            Command::AddEntry(entry) => self.storage.add_entry(entry),
-            Command::RemoveEntry(id) => {
-                self.storage.remove_entry(id);
+    /// Stores an entry that is known to fit
+    fn store(&mut self, entry: Entry<T>) {
+        self.used_bytes += self.weigh(&entry);
+        self.listener.on_added(&entry);
+        self.storage.add_entry(entry);
+    }
+
+    /// Checks that the entry can be added, expiring other entries to make room for it if the capacity allows it
+    fn make_room(&mut self, entry: &Entry<T>) -> CommandResult {
+        if self.storage.has_entry(entry.id()) || entry.key().map_or(false, |key| self.storage.find_key(key).is_some()) {
+            return Err(CommandError::DuplicateEntry);
+        }
+
+        let bytes = self.weigh(entry);
+
+        // An entry that doesn't even fit into an empty storage shouldn't expire any other entries
+        if self.capacity.as_ref().map_or(false, |capacity| !capacity.can_fit(bytes)) {
+            return Err(CommandError::StorageFull);
+        }
+
+        while !self.fits(bytes) {
+            let policy = self.capacity.as_ref().map(Capacity::overflow_policy);
+
+            if policy != Some(OverflowPolicy::ExpireNext) || !self.expire_next() {
+                return Err(CommandError::StorageFull);
             }
         }
+
+        Ok(())
     }
 
-    fn check_expired(&mut self) {
-        self.last_checked = Some(Instant::now());
-        self.listener.on_expired(self.storage.expire_entries());
+    /// Removes an entry on behalf of the user of the `Core`, notifying the listener
+    fn take_entry(&mut self, id: EntryId) -> Option<Entry<T>> {
+        let entry = self.remove_entry(id);
+
+        if let Some(ref entry) = entry {
+            self.listener.on_removed(entry);
+        }
+
+        entry
+    }
+
+    fn remove_entry(&mut self, id: EntryId) -> Option<Entry<T>> {
+        let entry = self.storage.remove_entry(id);
+
+        if let Some(ref entry) = entry {
+            self.used_bytes -= self.weigh(entry);
+        }
+
+        entry
     }
 
-    fn needs_checking(&self) -> bool {
-        match self.last_checked {
-            None => true,
-            Some(value) => {
-                return value.elapsed().as_secs() >= CHECK_INTERVAL;
+    /// Limits the entries that can be added from now on. Entries that are already stored are kept.
+    fn set_capacity(&mut self, capacity: Capacity<T>) {
+        self.used_bytes = self.storage.entries().map(|entry| capacity.weigh(entry.data())).sum();
+        self.capacity = Some(capacity);
+    }
+
+    /// Returns the size of the entry's data or 0 if there is no capacity
+    fn weigh(&self, entry: &Entry<T>) -> usize {
+        self.capacity.as_ref().map_or(0, |capacity| capacity.weigh(entry.data()))
+    }
+
+    /// Determines if an entry of `bytes` bytes can be added without exceeding the capacity
+    fn fits(&self, bytes: usize) -> bool {
+        self.capacity.as_ref().map_or(true, |capacity| capacity.fits(self.storage.len(), self.used_bytes, bytes))
+    }
+
+    /// Expires the entries that expire next ahead of time to make room for a new entry.
+    /// Returns `false` if there are no entries at all.
+    fn expire_next(&mut self) -> bool {
+        let deadline = match self.storage.next_deadline() {
+            Some(deadline) => deadline,
+            None => return false,
+        };
+
+        let expired = self.storage.expire_entries(deadline);
+
+        for entry in &expired {
+            self.used_bytes -= self.weigh(entry);
+        }
+
+        let now = self.clock.now();
+
+        self.notify_expired(expired, now);
+
+        true
+    }
+
+    fn reschedule(&mut self, id: EntryId, timestamp: Timestamp) -> Option<EntryId> {
+        let new_id = EntryId::new(timestamp, id.id());
+
+        if self.storage.move_entry(id, new_id) {
+            self.listener.on_rescheduled(id, new_id);
+            Some(new_id)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the next occurrence of every recurring entry. The entry keeps its id,
+    /// so that clients can recognize it.
+    fn next_occurrences(&self, expired: &[Entry<T>], now: Timestamp) -> Vec<Entry<T>> {
+        let mut next_occurrences = Vec::new();
+
+        for entry in expired {
+            let recurrence = match entry.recurrence() {
+                Some(recurrence) => recurrence,
+                None => continue,
+            };
+
+            if let Some((timestamp, next)) = recurrence.next(entry.id().timestamp(), now) {
+                let id = EntryId::new(timestamp, entry.id().id());
+                let data = next.clone_data(entry.data());
+
+                next_occurrences.push(Entry::recurring(id, data, next).with_key(entry.key().cloned()));
             }
         }
+
+        next_occurrences
+    }
+
+    fn check_expired(&mut self) {
+        if self.is_paused() {
+            return;
+        }
+
+        let now = self.clock.now();
+        let expired = self.storage.expire_entries(now);
+
+        if expired.is_empty() {
+            return;
+        }
+
+        for entry in &expired {
+            self.used_bytes -= self.weigh(entry);
+        }
+
+        let next_occurrences = self.next_occurrences(&expired, now);
+
+        self.notify_expired(expired, now);
+
+        // Re-arming happens after the listener has seen the expired entries,
+        // so that the next occurrence is announced after the last one
+        for entry in next_occurrences {
+            self.store(entry);
+        }
+    }
+
+    /// Writes the periodic snapshot of the storage once it is due, even if there are no changes that would trigger it
+    fn check_snapshot(&mut self) {
+        if self.storage.next_snapshot() == Some(Duration::from_secs(0)) {
+            self.storage.snapshot().expect("unable to write snapshot");
+        }
+    }
+
+    /// Hands the expired entries to all subscribers and then to the listener
+    fn notify_expired(&mut self, expired: Vec<Entry<T>>, fired_at: Timestamp) {
+        // Subscribers whose `Subscription` has been dropped are removed on the way
+        self.subscribers.retain(|subscriber| expired.iter().all(|entry| subscriber.deliver(entry)));
+
+        self.listener.on_expired(expired, fired_at);
+    }
+
+    /// Determines if a subscriber can't keep up, in which case entries are kept in the storage
+    /// until it has caught up and wakes up the worker
+    fn is_paused(&self) -> bool {
+        self.subscribers.iter().any(Subscriber::is_full)
+    }
+
+    /// Returns the duration until the next entry expires or the next snapshot is due,
+    /// or `None` if neither happens, e.g. because expiration is paused or the clock doesn't advance on its own
+    fn next_timeout(&self) -> Option<Duration> {
+        let snapshot = self.storage.next_snapshot();
+
+        if self.is_paused() {
+            return snapshot;
+        }
+
+        match (self.storage.next_deadline().and_then(|deadline| self.clock.timeout(deadline)), snapshot) {
+            (Some(expiration), Some(snapshot)) => Some(expiration.min(snapshot)),
+            (expiration, snapshot) => expiration.or(snapshot),
+        }
     }
 }
@@ -0,0 +1,81 @@
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use std::io;
+use super::errors::InvalidValueError;
+use super::{WriteTo, WriteResult, Reader, ReaderStatus};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default)]
+/// The `ConflictPolicy` of an [`UpsertEntry`] determines what happens
+/// if an entry with the same key and tag is already pending.
+///
+/// [`UpsertEntry`]: messages/struct.UpsertEntry.html
+pub enum ConflictPolicy {
+    /// The pending entry is replaced, which resets the deadline of a debounced timer
+    #[default]
+    Replace,
+    /// The pending entry is kept, so that retrying an `UpsertEntry` has no effect
+    KeepExisting,
+    /// Whichever entry expires first is kept
+    KeepEarliest,
+}
+
+#[derive(Debug)]
+pub struct ConflictPolicyReader;
+
+impl ConflictPolicy {
+    pub fn reader() -> ConflictPolicyReader {
+        ConflictPolicyReader {}
+    }
+}
+
+impl Reader<ConflictPolicy> for ConflictPolicyReader {
+    fn resume<R>(&mut self, input: &mut R) -> io::Result<ReaderStatus<ConflictPolicy>> where R: io::Read {
+        match input.read_u8()? {
+            0 => Ok(ReaderStatus::Complete(ConflictPolicy::Replace)),
+            1 => Ok(ReaderStatus::Complete(ConflictPolicy::KeepExisting)),
+            2 => Ok(ReaderStatus::Complete(ConflictPolicy::KeepEarliest)),
+            _ => Err(InvalidValueError::new()),
+        }
+    }
+
+    fn rewind(&mut self) {}
+}
+
+impl WriteTo for ConflictPolicy {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        let value = match self {
+            &ConflictPolicy::Replace => 0,
+            &ConflictPolicy::KeepExisting => 1,
+            &ConflictPolicy::KeepEarliest => 2,
+        };
+
+        target.write_u8(value)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write() {
+        let mut buf = vec![];
+        assert!(ConflictPolicy::KeepEarliest.write_to(&mut buf).is_ok());
+        assert_eq!(vec![2], buf);
+    }
+
+    #[test]
+    fn test_reader() {
+        let result = test_reader2!(ConflictPolicy::reader(), vec![1]);
+
+        assert_eq!(ConflictPolicy::KeepExisting, result.unwrap());
+    }
+
+    #[test]
+    fn test_reader_invalid() {
+        let result = test_reader2!(ConflictPolicy::reader(), vec![3]);
+
+        assert!(result.is_err());
+    }
+}
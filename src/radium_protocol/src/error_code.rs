@@ -18,6 +18,17 @@ pub enum ErrorCode {
     /// This message is sent when the connection is somehow broken
     /// e.g. reads and/or writes fail
     ConnectionFailure,
+    /// The entry that the action refers to does not exist
+    /// e.g. because it has already expired
+    EntryNotFound,
+    /// A value of the action is out of range, e.g. an empty interval
+    InvalidArgument,
+    /// The cron expression of the action can't be parsed or never matches
+    InvalidCronExpression,
+    /// The server has reached its limit of pending entries or payload bytes
+    StorageFull,
+    /// The server is too busy to accept the action right now, it can be retried later
+    QueueFull,
 }
 
 pub struct ErrorCodeReader;
@@ -36,6 +47,11 @@ impl Into<u8> for ErrorCode {
             ErrorCode::InvalidAction => 2,
             ErrorCode::ActionProcessingError => 3,
             ErrorCode::ConnectionFailure => 4,
+            ErrorCode::EntryNotFound => 5,
+            ErrorCode::InvalidArgument => 6,
+            ErrorCode::InvalidCronExpression => 7,
+            ErrorCode::StorageFull => 8,
+            ErrorCode::QueueFull => 9,
         }
     }
 }
@@ -50,6 +66,11 @@ impl TryFrom<u8> for ErrorCode {
             2 => Ok(ErrorCode::InvalidAction),
             3 => Ok(ErrorCode::ActionProcessingError),
             4 => Ok(ErrorCode::ConnectionFailure),
+            5 => Ok(ErrorCode::EntryNotFound),
+            6 => Ok(ErrorCode::InvalidArgument),
+            7 => Ok(ErrorCode::InvalidCronExpression),
+            8 => Ok(ErrorCode::StorageFull),
+            9 => Ok(ErrorCode::QueueFull),
             _ => Err(TryFromError::InvalidValue),
         }
     }
@@ -19,6 +19,8 @@ mod io;
 
 mod reader;
 mod watch_mode;
+mod precision;
+mod conflict_policy;
 mod error_code;
 
 pub mod messages;
@@ -29,4 +31,6 @@ pub use self::message::*;
 pub use self::io::*;
 pub use self::reader::*;
 pub use self::watch_mode::*;
+pub use self::precision::*;
+pub use self::conflict_policy::*;
 pub use self::error_code::*;
\ No newline at end of file
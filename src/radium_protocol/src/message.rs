@@ -5,9 +5,27 @@ use super::messages::{
     AddEntry, AddEntryReader,
     EntryAdded, EntryAddedReader,
     SetWatchMode, SetWatchModeReader,
+    SetPrecision, SetPrecisionReader,
     ErrorMessage, ErrorMessageReader,
     EntryExpired, EntryExpiredReader,
     RemoveEntry, RemoveEntryReader,
+    RescheduleEntry, RescheduleEntryReader,
+    AddRecurringEntry, AddRecurringEntryReader,
+    AddCronEntry, AddCronEntryReader,
+    EntryRemoved, EntryRemovedReader,
+    GetEntry, GetEntryReader,
+    ListEntries, ListEntriesReader,
+    EntryInfo, EntryInfoReader,
+    EntryList, EntryListReader,
+    EntryCount, EntryCountReader,
+    AddEntries, RemoveEntries, BatchReader,
+    EntriesAdded, EntriesRemoved, BatchResultsReader,
+    AckExpired, AckExpiredReader,
+    OwnerDisconnected, OwnerDisconnectedReader,
+    AddEphemeralEntry, AddEphemeralEntryReader,
+    GetEntryByKey, GetEntryByKeyReader,
+    RemoveEntryByKey, RemoveEntryByKeyReader,
+    UpsertEntry, UpsertEntryReader,
 };
 
 macro_rules! msg_reader {
@@ -45,15 +63,34 @@ pub enum Message {
     AddEntry(AddEntry),
     EntryAdded(EntryAdded),
     RemoveEntry(RemoveEntry),
-    // `EntryRemoved` should also contain the entry's data. However, this requires changing
-    // libradium, because the frontend does not block when adding or removing entries.
-    // until then, we use Ok as confirmation
-    #[doc(hidden)]
-    EntryRemoved,
+    EntryRemoved(EntryRemoved),
     EntryExpired(EntryExpired),
     SetWatchMode(SetWatchMode),
     Ok,
     Error(ErrorMessage),
+    SetPrecision(SetPrecision),
+    /// Writes a snapshot of all entries to disk, if the server persists its entries
+    Snapshot,
+    RescheduleEntry(RescheduleEntry),
+    AddRecurringEntry(AddRecurringEntry),
+    AddCronEntry(AddCronEntry),
+    GetEntry(GetEntry),
+    ListEntries(ListEntries),
+    /// Requests the number of pending entries
+    CountEntries,
+    EntryInfo(EntryInfo),
+    EntryList(EntryList),
+    EntryCount(EntryCount),
+    AddEntries(AddEntries),
+    RemoveEntries(RemoveEntries),
+    EntriesAdded(EntriesAdded),
+    EntriesRemoved(EntriesRemoved),
+    AckExpired(AckExpired),
+    OwnerDisconnected(OwnerDisconnected),
+    AddEphemeralEntry(AddEphemeralEntry),
+    GetEntryByKey(GetEntryByKey),
+    RemoveEntryByKey(RemoveEntryByKey),
+    UpsertEntry(UpsertEntry),
 }
 
 #[derive(Debug)]
@@ -61,11 +98,31 @@ enum ReaderState {
     Type,
     Message(MessageType),
     SetWatchMode(SetWatchModeReader),
+    SetPrecision(SetPrecisionReader),
     ErrorMessage(ErrorMessageReader),
     AddEntry(AddEntryReader),
     EntryAdded(EntryAddedReader),
     RemoveEntry(RemoveEntryReader),
+    EntryRemoved(EntryRemovedReader),
     EntryExpired(EntryExpiredReader),
+    RescheduleEntry(RescheduleEntryReader),
+    AddRecurringEntry(AddRecurringEntryReader),
+    AddCronEntry(AddCronEntryReader),
+    GetEntry(GetEntryReader),
+    ListEntries(ListEntriesReader),
+    EntryInfo(EntryInfoReader),
+    EntryList(EntryListReader),
+    EntryCount(EntryCountReader),
+    AddEntries(BatchReader<AddEntry, AddEntryReader>),
+    RemoveEntries(BatchReader<RemoveEntry, RemoveEntryReader>),
+    EntriesAdded(BatchResultsReader<EntryAdded, EntryAddedReader>),
+    EntriesRemoved(BatchResultsReader<EntryRemoved, EntryRemovedReader>),
+    AckExpired(AckExpiredReader),
+    OwnerDisconnected(OwnerDisconnectedReader),
+    AddEphemeralEntry(AddEphemeralEntryReader),
+    GetEntryByKey(GetEntryByKeyReader),
+    RemoveEntryByKey(RemoveEntryByKeyReader),
+    UpsertEntry(UpsertEntryReader),
 }
 
 #[derive(Debug)]
@@ -86,11 +143,32 @@ impl Message {
             &Message::AddEntry(..) => MessageType::AddEntry,
             &Message::EntryAdded(..) => MessageType::EntryAdded,
             &Message::RemoveEntry(..) => MessageType::RemoveEntry,
-            &Message::EntryRemoved => MessageType::EntryRemoved,
+            &Message::EntryRemoved(..) => MessageType::EntryRemoved,
             &Message::EntryExpired(..) => MessageType::EntryExpired,
             &Message::SetWatchMode(..) => MessageType::SetWatchMode,
             &Message::Ok => MessageType::Ok,
             &Message::Error(..) => MessageType::Error,
+            &Message::SetPrecision(..) => MessageType::SetPrecision,
+            &Message::Snapshot => MessageType::Snapshot,
+            &Message::RescheduleEntry(..) => MessageType::RescheduleEntry,
+            &Message::AddRecurringEntry(..) => MessageType::AddRecurringEntry,
+            &Message::AddCronEntry(..) => MessageType::AddCronEntry,
+            &Message::GetEntry(..) => MessageType::GetEntry,
+            &Message::ListEntries(..) => MessageType::ListEntries,
+            &Message::CountEntries => MessageType::CountEntries,
+            &Message::EntryInfo(..) => MessageType::EntryInfo,
+            &Message::EntryList(..) => MessageType::EntryList,
+            &Message::EntryCount(..) => MessageType::EntryCount,
+            &Message::AddEntries(..) => MessageType::AddEntries,
+            &Message::RemoveEntries(..) => MessageType::RemoveEntries,
+            &Message::EntriesAdded(..) => MessageType::EntriesAdded,
+            &Message::EntriesRemoved(..) => MessageType::EntriesRemoved,
+            &Message::AckExpired(..) => MessageType::AckExpired,
+            &Message::OwnerDisconnected(..) => MessageType::OwnerDisconnected,
+            &Message::AddEphemeralEntry(..) => MessageType::AddEphemeralEntry,
+            &Message::GetEntryByKey(..) => MessageType::GetEntryByKey,
+            &Message::RemoveEntryByKey(..) => MessageType::RemoveEntryByKey,
+            &Message::UpsertEntry(..) => MessageType::UpsertEntry,
         }
     }
 
@@ -121,24 +199,63 @@ impl Reader<Message> for MessageReader {
                 match msg_type {
                     MessageType::Ping => empty_msg!(Ping),
                     MessageType::Pong => empty_msg!(Pong),
-                    MessageType::EntryRemoved => empty_msg!(EntryRemoved),
                     MessageType::Ok => empty_msg!(Ok),
+                    MessageType::Snapshot => empty_msg!(Snapshot),
+                    MessageType::CountEntries => empty_msg!(CountEntries),
                     MessageType::SetWatchMode => into_msg_reader!(SetWatchMode),
                     MessageType::AddEntry => into_msg_reader!(AddEntry),
                     MessageType::Error => into_msg_reader!(ErrorMessage),
                     MessageType::EntryAdded => into_msg_reader!(EntryAdded),
                     MessageType::RemoveEntry => into_msg_reader!(RemoveEntry),
-                    // TODO: implement EntryRemoved
-                    MessageType::EntryRemoved => unreachable!(),
+                    MessageType::EntryRemoved => into_msg_reader!(EntryRemoved),
                     MessageType::EntryExpired => into_msg_reader!(EntryExpired),
+                    MessageType::SetPrecision => into_msg_reader!(SetPrecision),
+                    MessageType::RescheduleEntry => into_msg_reader!(RescheduleEntry),
+                    MessageType::AddRecurringEntry => into_msg_reader!(AddRecurringEntry),
+                    MessageType::AddCronEntry => into_msg_reader!(AddCronEntry),
+                    MessageType::GetEntry => into_msg_reader!(GetEntry),
+                    MessageType::ListEntries => into_msg_reader!(ListEntries),
+                    MessageType::EntryInfo => into_msg_reader!(EntryInfo),
+                    MessageType::EntryList => into_msg_reader!(EntryList),
+                    MessageType::EntryCount => into_msg_reader!(EntryCount),
+                    MessageType::AddEntries => into_msg_reader!(AddEntries),
+                    MessageType::RemoveEntries => into_msg_reader!(RemoveEntries),
+                    MessageType::EntriesAdded => into_msg_reader!(EntriesAdded),
+                    MessageType::EntriesRemoved => into_msg_reader!(EntriesRemoved),
+                    MessageType::AckExpired => into_msg_reader!(AckExpired),
+                    MessageType::OwnerDisconnected => into_msg_reader!(OwnerDisconnected),
+                    MessageType::AddEphemeralEntry => into_msg_reader!(AddEphemeralEntry),
+                    MessageType::GetEntryByKey => into_msg_reader!(GetEntryByKey),
+                    MessageType::RemoveEntryByKey => into_msg_reader!(RemoveEntryByKey),
+                    MessageType::UpsertEntry => into_msg_reader!(UpsertEntry),
                 }
             },
             ReaderState::SetWatchMode(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::SetPrecision(ref mut reader) => msg_reader!(reader, input),
             ReaderState::AddEntry(ref mut reader) => msg_reader!(reader, input),
             ReaderState::ErrorMessage(ref mut reader) => msg_reader!(reader, input),
             ReaderState::EntryAdded(ref mut reader) => msg_reader!(reader, input),
             ReaderState::RemoveEntry(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::EntryRemoved(ref mut reader) => msg_reader!(reader, input),
             ReaderState::EntryExpired(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::RescheduleEntry(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::AddRecurringEntry(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::AddCronEntry(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::GetEntry(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::ListEntries(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::EntryInfo(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::EntryList(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::EntryCount(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::AddEntries(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::RemoveEntries(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::EntriesAdded(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::EntriesRemoved(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::AckExpired(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::OwnerDisconnected(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::AddEphemeralEntry(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::GetEntryByKey(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::RemoveEntryByKey(ref mut reader) => msg_reader!(reader, input),
+            ReaderState::UpsertEntry(ref mut reader) => msg_reader!(reader, input),
         };
 
         if let Some(state) = state {
@@ -163,11 +280,32 @@ impl WriteTo for Message {
             &Message::AddEntry(ref msg) => msg.write_to(target),
             &Message::EntryAdded(ref msg) => msg.write_to(target),
             &Message::RemoveEntry(ref msg) => msg.write_to(target),
-            &Message::EntryRemoved => Ok(()),
+            &Message::EntryRemoved(ref msg) => msg.write_to(target),
             &Message::EntryExpired(ref msg) => msg.write_to(target),
             &Message::SetWatchMode(ref msg) => msg.write_to(target),
             &Message::Ok => Ok(()),
             &Message::Error(ref msg) => msg.write_to(target),
+            &Message::SetPrecision(ref msg) => msg.write_to(target),
+            &Message::Snapshot => Ok(()),
+            &Message::RescheduleEntry(ref msg) => msg.write_to(target),
+            &Message::AddRecurringEntry(ref msg) => msg.write_to(target),
+            &Message::AddCronEntry(ref msg) => msg.write_to(target),
+            &Message::GetEntry(ref msg) => msg.write_to(target),
+            &Message::ListEntries(ref msg) => msg.write_to(target),
+            &Message::CountEntries => Ok(()),
+            &Message::EntryInfo(ref msg) => msg.write_to(target),
+            &Message::EntryList(ref msg) => msg.write_to(target),
+            &Message::EntryCount(ref msg) => msg.write_to(target),
+            &Message::AddEntries(ref msg) => msg.write_to(target),
+            &Message::RemoveEntries(ref msg) => msg.write_to(target),
+            &Message::EntriesAdded(ref msg) => msg.write_to(target),
+            &Message::EntriesRemoved(ref msg) => msg.write_to(target),
+            &Message::AckExpired(ref msg) => msg.write_to(target),
+            &Message::OwnerDisconnected(ref msg) => msg.write_to(target),
+            &Message::AddEphemeralEntry(ref msg) => msg.write_to(target),
+            &Message::GetEntryByKey(ref msg) => msg.write_to(target),
+            &Message::RemoveEntryByKey(ref msg) => msg.write_to(target),
+            &Message::UpsertEntry(ref msg) => msg.write_to(target),
         }
     }
 }
@@ -175,8 +313,8 @@ impl WriteTo for Message {
 #[cfg(test)]
 mod test {
     use super::*;
-    use super::super::{WatchMode, ErrorCode};
-    use super::super::messages::SetWatchMode;
+    use super::super::{WatchMode, Precision, ErrorCode, ConflictPolicy};
+    use super::super::messages::{SetWatchMode, SetPrecision};
 
     macro_rules! test_message {
         ($test:ident, $ty:ident) => {
@@ -211,17 +349,98 @@ mod test {
                   Message::RemoveEntry(RemoveEntry::new(0, 0)),
                   MessageType::RemoveEntry);
 
-    test_message!(test_entry_removed, EntryRemoved);
+    test_message!(test_add_recurring_entry,
+                  Message::AddRecurringEntry(AddRecurringEntry::new(1, 0, 0, AddEntry::new(0, 0, vec![]))),
+                  MessageType::AddRecurringEntry);
+
+    test_message!(test_add_cron_entry,
+                  Message::AddCronEntry(AddCronEntry::new("* * * * *", 0, 0, AddEntry::new(0, 0, vec![]))),
+                  MessageType::AddCronEntry);
+
+    test_message!(test_reschedule_entry,
+                  Message::RescheduleEntry(RescheduleEntry::new(0, 0, 0)),
+                  MessageType::RescheduleEntry);
+
+    test_message!(test_entry_removed,
+                  Message::EntryRemoved(EntryRemoved::new(12, vec![1, 2, 3])),
+                  MessageType::EntryRemoved);
 
     test_message!(test_entry_expired,
-                  Message::EntryExpired(EntryExpired::new(0, 7, 12, vec![])),
+                  Message::EntryExpired(EntryExpired::new(0, 7, 1, 12, vec![])),
                   MessageType::EntryExpired);
 
+    test_message!(test_get_entry,
+                  Message::GetEntry(GetEntry::new(0, 0)),
+                  MessageType::GetEntry);
+
+    test_message!(test_list_entries,
+                  Message::ListEntries(ListEntries::new(0, 10, 5)),
+                  MessageType::ListEntries);
+
+    test_message!(test_count_entries, CountEntries);
+
+    test_message!(test_entry_info,
+                  Message::EntryInfo(EntryInfo::new(0, 7, 12, vec![])),
+                  MessageType::EntryInfo);
+
+    test_message!(test_entry_list,
+                  Message::EntryList(EntryList::new(vec![EntryInfo::new(0, 7, 12, vec![])])),
+                  MessageType::EntryList);
+
+    test_message!(test_entry_count,
+                  Message::EntryCount(EntryCount::new(3)),
+                  MessageType::EntryCount);
+
+    test_message!(test_add_entries,
+                  Message::AddEntries(AddEntries::new(vec![AddEntry::new(0, 0, vec![])])),
+                  MessageType::AddEntries);
+
+    test_message!(test_remove_entries,
+                  Message::RemoveEntries(RemoveEntries::new(vec![RemoveEntry::new(0, 0)])),
+                  MessageType::RemoveEntries);
+
+    test_message!(test_entries_added,
+                  Message::EntriesAdded(EntriesAdded::new(vec![Ok(EntryAdded::new(0, 0)), Err(ErrorCode::StorageFull)])),
+                  MessageType::EntriesAdded);
+
+    test_message!(test_entries_removed,
+                  Message::EntriesRemoved(EntriesRemoved::new(vec![Err(ErrorCode::EntryNotFound)])),
+                  MessageType::EntriesRemoved);
+
+    test_message!(test_ack_expired,
+                  Message::AckExpired(AckExpired::new(0, 0)),
+                  MessageType::AckExpired);
+
+    test_message!(test_owner_disconnected,
+                  Message::OwnerDisconnected(OwnerDisconnected::new(EntryInfo::new(0, 0, 0, vec![]))),
+                  MessageType::OwnerDisconnected);
+
+    test_message!(test_add_ephemeral_entry,
+                  Message::AddEphemeralEntry(AddEphemeralEntry::new(AddEntry::new(0, 0, vec![]))),
+                  MessageType::AddEphemeralEntry);
+
+    test_message!(test_get_entry_by_key,
+                  Message::GetEntryByKey(GetEntryByKey::new(0, vec![1])),
+                  MessageType::GetEntryByKey);
+
+    test_message!(test_remove_entry_by_key,
+                  Message::RemoveEntryByKey(RemoveEntryByKey::new(0, vec![1])),
+                  MessageType::RemoveEntryByKey);
+
+    test_message!(test_upsert_entry,
+                  Message::UpsertEntry(UpsertEntry::new(vec![1], ConflictPolicy::Replace, AddEntry::new(0, 0, vec![]))),
+                  MessageType::UpsertEntry);
+
     test_message!(test_ok, Ok);
+    test_message!(test_snapshot, Snapshot);
 
     test_message!(test_set_watch_mode,
                   Message::SetWatchMode(SetWatchMode::new(WatchMode::None)),
                   MessageType::SetWatchMode);
 
+    test_message!(test_set_precision,
+                  Message::SetPrecision(SetPrecision::new(Precision::Milliseconds)),
+                  MessageType::SetPrecision);
+
     test_message!(test_error, Message::Error(ErrorMessage::new(ErrorCode::ClientRejected)), MessageType::Error);
 }
\ No newline at end of file
@@ -17,7 +17,6 @@ pub enum MessageType {
     /// 0x04
     RemoveEntry,
     /// 0x05
-    #[doc(hidden)]
     EntryRemoved,
     /// 0x06
     EntryExpired,
@@ -27,6 +26,48 @@ pub enum MessageType {
     Ok,
     /// 0x09
     Error,
+    /// 0x0A
+    SetPrecision,
+    /// 0x0B
+    Snapshot,
+    /// 0x0C
+    RescheduleEntry,
+    /// 0x0D
+    AddRecurringEntry,
+    /// 0x0E
+    AddCronEntry,
+    /// 0x0F
+    GetEntry,
+    /// 0x10
+    ListEntries,
+    /// 0x11
+    CountEntries,
+    /// 0x12
+    EntryInfo,
+    /// 0x13
+    EntryList,
+    /// 0x14
+    EntryCount,
+    /// 0x15
+    AddEntries,
+    /// 0x16
+    RemoveEntries,
+    /// 0x17
+    EntriesAdded,
+    /// 0x18
+    EntriesRemoved,
+    /// 0x19
+    AckExpired,
+    /// 0x1A
+    OwnerDisconnected,
+    /// 0x1B
+    AddEphemeralEntry,
+    /// 0x1C
+    GetEntryByKey,
+    /// 0x1D
+    RemoveEntryByKey,
+    /// 0x1E
+    UpsertEntry,
 }
 
 pub struct MessageTypeReader;
@@ -38,7 +79,22 @@ impl MessageType {
             MessageType::Ping |
             MessageType::AddEntry |
             MessageType::RemoveEntry |
-            MessageType::SetWatchMode => true,
+            MessageType::SetWatchMode |
+            MessageType::SetPrecision |
+            MessageType::Snapshot |
+            MessageType::RescheduleEntry |
+            MessageType::AddRecurringEntry |
+            MessageType::AddCronEntry |
+            MessageType::GetEntry |
+            MessageType::ListEntries |
+            MessageType::CountEntries |
+            MessageType::AddEntries |
+            MessageType::RemoveEntries |
+            MessageType::AckExpired |
+            MessageType::AddEphemeralEntry |
+            MessageType::GetEntryByKey |
+            MessageType::RemoveEntryByKey |
+            MessageType::UpsertEntry => true,
             _ => false
         }
     }
@@ -91,6 +147,27 @@ impl Into<u8> for MessageType {
             MessageType::SetWatchMode => 7,
             MessageType::Ok => 8,
             MessageType::Error => 9,
+            MessageType::SetPrecision => 10,
+            MessageType::Snapshot => 11,
+            MessageType::RescheduleEntry => 12,
+            MessageType::AddRecurringEntry => 13,
+            MessageType::AddCronEntry => 14,
+            MessageType::GetEntry => 15,
+            MessageType::ListEntries => 16,
+            MessageType::CountEntries => 17,
+            MessageType::EntryInfo => 18,
+            MessageType::EntryList => 19,
+            MessageType::EntryCount => 20,
+            MessageType::AddEntries => 21,
+            MessageType::RemoveEntries => 22,
+            MessageType::EntriesAdded => 23,
+            MessageType::EntriesRemoved => 24,
+            MessageType::AckExpired => 25,
+            MessageType::OwnerDisconnected => 26,
+            MessageType::AddEphemeralEntry => 27,
+            MessageType::GetEntryByKey => 28,
+            MessageType::RemoveEntryByKey => 29,
+            MessageType::UpsertEntry => 30,
         }
     }
 }
@@ -110,6 +187,27 @@ impl TryFrom<u8> for MessageType {
             7 => Ok(MessageType::SetWatchMode),
             8 => Ok(MessageType::Ok),
             9 => Ok(MessageType::Error),
+            10 => Ok(MessageType::SetPrecision),
+            11 => Ok(MessageType::Snapshot),
+            12 => Ok(MessageType::RescheduleEntry),
+            13 => Ok(MessageType::AddRecurringEntry),
+            14 => Ok(MessageType::AddCronEntry),
+            15 => Ok(MessageType::GetEntry),
+            16 => Ok(MessageType::ListEntries),
+            17 => Ok(MessageType::CountEntries),
+            18 => Ok(MessageType::EntryInfo),
+            19 => Ok(MessageType::EntryList),
+            20 => Ok(MessageType::EntryCount),
+            21 => Ok(MessageType::AddEntries),
+            22 => Ok(MessageType::RemoveEntries),
+            23 => Ok(MessageType::EntriesAdded),
+            24 => Ok(MessageType::EntriesRemoved),
+            25 => Ok(MessageType::AckExpired),
+            26 => Ok(MessageType::OwnerDisconnected),
+            27 => Ok(MessageType::AddEphemeralEntry),
+            28 => Ok(MessageType::GetEntryByKey),
+            29 => Ok(MessageType::RemoveEntryByKey),
+            30 => Ok(MessageType::UpsertEntry),
             _ => Err(TryFromError::InvalidValue),
         }
     }
@@ -186,4 +284,109 @@ mod test {
     fn test_error() {
         test_message_type!(MessageType::Error, 9, false);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_set_precision() {
+        test_message_type!(MessageType::SetPrecision, 10, true);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        test_message_type!(MessageType::Snapshot, 11, true);
+    }
+
+    #[test]
+    fn test_reschedule_entry() {
+        test_message_type!(MessageType::RescheduleEntry, 12, true);
+    }
+
+    #[test]
+    fn test_add_recurring_entry() {
+        test_message_type!(MessageType::AddRecurringEntry, 13, true);
+    }
+
+    #[test]
+    fn test_add_cron_entry() {
+        test_message_type!(MessageType::AddCronEntry, 14, true);
+    }
+
+    #[test]
+    fn test_get_entry() {
+        test_message_type!(MessageType::GetEntry, 15, true);
+    }
+
+    #[test]
+    fn test_list_entries() {
+        test_message_type!(MessageType::ListEntries, 16, true);
+    }
+
+    #[test]
+    fn test_count_entries() {
+        test_message_type!(MessageType::CountEntries, 17, true);
+    }
+
+    #[test]
+    fn test_entry_info() {
+        test_message_type!(MessageType::EntryInfo, 18, false);
+    }
+
+    #[test]
+    fn test_entry_list() {
+        test_message_type!(MessageType::EntryList, 19, false);
+    }
+
+    #[test]
+    fn test_entry_count() {
+        test_message_type!(MessageType::EntryCount, 20, false);
+    }
+
+    #[test]
+    fn test_add_entries() {
+        test_message_type!(MessageType::AddEntries, 21, true);
+    }
+
+    #[test]
+    fn test_remove_entries() {
+        test_message_type!(MessageType::RemoveEntries, 22, true);
+    }
+
+    #[test]
+    fn test_entries_added() {
+        test_message_type!(MessageType::EntriesAdded, 23, false);
+    }
+
+    #[test]
+    fn test_entries_removed() {
+        test_message_type!(MessageType::EntriesRemoved, 24, false);
+    }
+
+    #[test]
+    fn test_ack_expired() {
+        test_message_type!(MessageType::AckExpired, 25, true);
+    }
+
+    #[test]
+    fn test_owner_disconnected() {
+        test_message_type!(MessageType::OwnerDisconnected, 26, false);
+    }
+
+    #[test]
+    fn test_add_ephemeral_entry() {
+        test_message_type!(MessageType::AddEphemeralEntry, 27, true);
+    }
+
+    #[test]
+    fn test_get_entry_by_key() {
+        test_message_type!(MessageType::GetEntryByKey, 28, true);
+    }
+
+    #[test]
+    fn test_remove_entry_by_key() {
+        test_message_type!(MessageType::RemoveEntryByKey, 29, true);
+    }
+
+    #[test]
+    fn test_upsert_entry() {
+        test_message_type!(MessageType::UpsertEntry, 30, true);
+    }
+}
@@ -0,0 +1,227 @@
+use std::io;
+use std::io::Read;
+use std::mem;
+use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
+use super::super::{WriteTo, WriteResult, ReaderStatus, Reader, MessageInner, Message};
+use super::super::errors::{WriteError, DataLengthError, InvalidValueError};
+use super::{AddEntry, AddEntryReader};
+
+use ReaderStatus::{Complete, Pending};
+
+/// len: u8 | expression: len bytes | count: u32 | until: i64 | entry: [`AddEntry`]
+///
+/// Adds an entry that expires whenever the cron `expression` matches, until it has expired
+/// `count` times or the next expiration would be after `until`.
+/// A `count` of 0 means unlimited, an `until` of 0 means there is no end.
+/// The entry first expires at the first match after the timestamp of `entry`, or after now if that is earlier.
+///
+/// The server replies with an [`EntryAdded`] message containing the first expiration.
+///
+/// `until` is given in the [`Precision`] of the connection.
+///
+/// [`AddEntry`]: struct.AddEntry.html
+/// [`EntryAdded`]: struct.EntryAdded.html
+/// [`Precision`]: ../enum.Precision.html
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct AddCronEntry {
+    expression: String,
+    count: u32,
+    until: i64,
+    entry: AddEntry,
+}
+
+#[derive(Debug)]
+enum ReaderState {
+    Length,
+    Expression(u64),
+    Count,
+    Until(u32),
+    Entry(u32, i64),
+}
+
+#[derive(Debug)]
+pub struct AddCronEntryReader {
+    state: ReaderState,
+    expression: String,
+    entry: AddEntryReader,
+}
+
+impl AddCronEntry {
+    pub fn new<S: Into<String>>(expression: S, count: u32, until: i64, entry: AddEntry) -> Self {
+        AddCronEntry {
+            expression: expression.into(),
+            count,
+            until,
+            entry,
+        }
+    }
+
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn until(&self) -> i64 {
+        self.until
+    }
+
+    pub fn entry(&self) -> &AddEntry {
+        &self.entry
+    }
+
+    pub fn consume_entry(self) -> AddEntry {
+        self.entry
+    }
+
+    pub fn reader() -> AddCronEntryReader {
+        AddCronEntryReader {
+            state: ReaderState::Length,
+            expression: String::new(),
+            entry: AddEntry::reader(),
+        }
+    }
+}
+
+impl MessageInner for AddCronEntry {
+    fn wrap(self) -> Message {
+        Message::AddCronEntry(self)
+    }
+}
+
+impl Reader<AddCronEntry> for AddCronEntryReader {
+    fn resume<R>(&mut self, input: &mut R) -> io::Result<ReaderStatus<AddCronEntry>> where R: io::Read {
+        let (state, status) = match self.state {
+            ReaderState::Length => {
+                let length = input.read_u8()? as u64;
+
+                (ReaderState::Expression(length), Pending)
+            },
+            ReaderState::Expression(length) => {
+                let mut buf = Vec::new();
+                let bytes_read = input.take(length).read_to_end(&mut buf)?;
+
+                if (bytes_read as u64) < length {
+                    return Err(DataLengthError::new());
+                }
+
+                self.expression = String::from_utf8(buf).map_err(|_| InvalidValueError::new())?;
+
+                (ReaderState::Count, Pending)
+            },
+            ReaderState::Count => {
+                let count = input.read_u32::<NetworkEndian>()?;
+
+                (ReaderState::Until(count), Pending)
+            },
+            ReaderState::Until(count) => {
+                let until = input.read_i64::<NetworkEndian>()?;
+
+                (ReaderState::Entry(count, until), Pending)
+            },
+            ReaderState::Entry(count, until) => {
+                match self.entry.resume(input)? {
+                    Pending => return Ok(Pending),
+                    Complete(entry) => {
+                        let expression = mem::replace(&mut self.expression, String::new());
+
+                        (ReaderState::Length, Complete(AddCronEntry::new(expression, count, until, entry)))
+                    }
+                }
+            },
+        };
+
+        self.state = state;
+
+        Ok(status)
+    }
+
+    fn rewind(&mut self) {
+        self.state = ReaderState::Length;
+        self.expression.clear();
+        self.entry.rewind();
+    }
+}
+
+impl WriteTo for AddCronEntry {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        let len = self.expression.len();
+
+        if len > u8::max_value() as usize {
+            return Err(WriteError::DataLengthOverflow);
+        }
+
+        target.write_u8(len as u8)?;
+        target.write_all(self.expression.as_bytes())?;
+        target.write_u32::<NetworkEndian>(self.count)?;
+        target.write_i64::<NetworkEndian>(self.until)?;
+
+        self.entry.write_to(target)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+    use super::super::super::{Message, MessageType};
+
+    #[test]
+    fn test_reader() {
+        let input = vec![
+            /* type  */ MessageType::AddCronEntry.into(),
+            /* len   */ 9,
+            /* expr  */ b'0', b' ', b'*', b' ', b'*', b' ', b'*', b' ', b'*',
+            /* count */ 0, 0, 0, 5,
+            /* until */ 0, 0, 0, 0, 0, 0, 1, 0,
+            /* ts    */ 0, 0, 0, 0, 0, 0, 0, 10,
+            /* tag   */ 0, 0, 0, 0, 0, 0, 0, 42,
+            /* len   */ 0, 3,
+            /* data  */ 1, 2, 3
+        ];
+
+        let result = test_reader2!(Message::reader(), input);
+
+        assert!(result.is_ok());
+        assert_eq!(
+            Message::AddCronEntry(AddCronEntry::new("0 * * * *", 5, 256, AddEntry::new(10, 42, vec![1, 2, 3]))),
+            result.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_reader_fails_on_invalid_utf8() {
+        let input = vec![
+            /* len   */ 1,
+            /* expr  */ 255,
+        ];
+
+        let result = test_reader2!(AddCronEntry::reader(), input);
+
+        assert_eq!(InvalidValueError::new().description(), result.unwrap_err().description());
+    }
+
+    #[test]
+    fn test_write() {
+        let cmd = AddCronEntry::new("@", 0, 0, AddEntry::new(10, 128, vec![1, 2, 3]));
+        let mut vec = Vec::<u8>::new();
+
+        assert!(cmd.write_to(&mut vec).is_ok());
+
+        assert_eq!(
+            vec![
+                /* len   */ 1,
+                /* expr  */ b'@',
+                /* count */ 0, 0, 0, 0,
+                /* until */ 0, 0, 0, 0, 0, 0, 0, 0,
+                /* ts    */ 0, 0, 0, 0, 0, 0, 0, 10,
+                /* tag   */ 0, 0, 0, 0, 0, 0, 0, 128,
+                /* len   */ 0, 3,
+                /* data  */ 1, 2, 3
+            ],
+            vec
+        );
+    }
+}
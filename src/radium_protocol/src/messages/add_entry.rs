@@ -21,6 +21,10 @@ fn get_max_data_bytes() -> u64 {
 }
 
 /// ts: i64 | tag: u64 | len: u16 | data: (len < 2**16)
+///
+/// `ts` is given in the [`Precision`] of the connection.
+///
+/// [`Precision`]: ../enum.Precision.html
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct AddEntry {
     timestamp: i64,
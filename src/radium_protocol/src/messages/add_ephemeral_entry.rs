@@ -0,0 +1,103 @@
+use std::io;
+use super::super::{WriteTo, WriteResult, Reader, ReaderStatus, Message, MessageInner};
+use super::{AddEntry, AddEntryReader};
+
+/// entry: [`AddEntry`]
+///
+/// Adds an entry like [`AddEntry`], which is removed when the connection that added it is closed.
+/// The server replies with [`EntryAdded`].
+///
+/// [`AddEntry`]: struct.AddEntry.html
+/// [`EntryAdded`]: struct.EntryAdded.html
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct AddEphemeralEntry {
+    entry: AddEntry,
+}
+
+#[derive(Debug)]
+pub struct AddEphemeralEntryReader {
+    inner: AddEntryReader,
+}
+
+impl AddEphemeralEntry {
+    pub fn new(entry: AddEntry) -> Self {
+        AddEphemeralEntry { entry }
+    }
+
+    pub fn entry(&self) -> &AddEntry {
+        &self.entry
+    }
+
+    pub fn consume_entry(self) -> AddEntry {
+        self.entry
+    }
+
+    pub fn reader() -> AddEphemeralEntryReader {
+        AddEphemeralEntryReader { inner: AddEntry::reader() }
+    }
+}
+
+impl MessageInner for AddEphemeralEntry {
+    fn wrap(self) -> Message {
+        Message::AddEphemeralEntry(self)
+    }
+}
+
+impl Reader<AddEphemeralEntry> for AddEphemeralEntryReader {
+    fn resume<I>(&mut self, input: &mut I) -> io::Result<ReaderStatus<AddEphemeralEntry>> where I: io::Read {
+        let status = self.inner.resume(input)?;
+
+        Ok(status.map(|entry| AddEphemeralEntry::new(entry)))
+    }
+
+    fn rewind(&mut self) {
+        self.inner.rewind();
+    }
+}
+
+impl WriteTo for AddEphemeralEntry {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        self.entry.write_to(target)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::super::{Message, MessageType};
+
+    #[test]
+    fn test_write() {
+        let msg = Message::AddEphemeralEntry(AddEphemeralEntry::new(AddEntry::new(10, 42, vec![1])));
+        let mut vec = Vec::<u8>::new();
+
+        assert!(msg.write_to(&mut vec).is_ok());
+
+        assert_eq!(
+            vec![
+                /* cmd  */ 27,
+                /* ts   */ 0, 0, 0, 0, 0, 0, 0, 10,
+                /* tag  */ 0, 0, 0, 0, 0, 0, 0, 42,
+                /* len  */ 0, 1,
+                /* data */ 1
+            ],
+            vec
+        );
+    }
+
+    #[test]
+    fn test_reader() {
+        let input = vec![
+            /* type */ MessageType::AddEphemeralEntry.into(),
+            /* ts   */ 0, 0, 0, 0, 0, 0, 0, 10,
+            /* tag  */ 0, 0, 0, 0, 0, 0, 0, 42,
+            /* len  */ 0, 1,
+            /* data */ 1
+        ];
+
+        let result = test_reader2!(Message::reader(), input);
+
+        assert!(result.is_ok());
+        assert_eq!(Message::AddEphemeralEntry(AddEphemeralEntry::new(AddEntry::new(10, 42, vec![1]))), result.unwrap());
+    }
+}
@@ -0,0 +1,178 @@
+use std::io;
+use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
+use super::super::{WriteTo, WriteResult, ReaderStatus, Reader, MessageInner, Message};
+use super::{AddEntry, AddEntryReader};
+
+use ReaderStatus::{Complete, Pending};
+
+/// interval: u64 | count: u32 | until: i64 | entry: [`AddEntry`]
+///
+/// Adds an entry that first expires at the timestamp of `entry` and is then re-armed by the server
+/// every `interval` until it has expired `count` times or the next expiration would be after `until`.
+/// A `count` of 0 means unlimited, an `until` of 0 means there is no end.
+///
+/// `interval` and `until` are given in the [`Precision`] of the connection.
+///
+/// [`AddEntry`]: struct.AddEntry.html
+/// [`Precision`]: ../enum.Precision.html
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct AddRecurringEntry {
+    interval: u64,
+    count: u32,
+    until: i64,
+    entry: AddEntry,
+}
+
+#[derive(Debug)]
+enum ReaderState {
+    Interval,
+    Count(u64),
+    Until(u64, u32),
+    Entry(u64, u32, i64),
+}
+
+#[derive(Debug)]
+pub struct AddRecurringEntryReader {
+    state: ReaderState,
+    entry: AddEntryReader,
+}
+
+impl AddRecurringEntry {
+    pub fn new(interval: u64, count: u32, until: i64, entry: AddEntry) -> Self {
+        AddRecurringEntry {
+            interval,
+            count,
+            until,
+            entry,
+        }
+    }
+
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn until(&self) -> i64 {
+        self.until
+    }
+
+    pub fn entry(&self) -> &AddEntry {
+        &self.entry
+    }
+
+    pub fn consume_entry(self) -> AddEntry {
+        self.entry
+    }
+
+    pub fn reader() -> AddRecurringEntryReader {
+        AddRecurringEntryReader {
+            state: ReaderState::Interval,
+            entry: AddEntry::reader(),
+        }
+    }
+}
+
+impl MessageInner for AddRecurringEntry {
+    fn wrap(self) -> Message {
+        Message::AddRecurringEntry(self)
+    }
+}
+
+impl Reader<AddRecurringEntry> for AddRecurringEntryReader {
+    fn resume<R>(&mut self, input: &mut R) -> io::Result<ReaderStatus<AddRecurringEntry>> where R: io::Read {
+        let (state, status) = match self.state {
+            ReaderState::Interval => {
+                let interval = input.read_u64::<NetworkEndian>()?;
+
+                (ReaderState::Count(interval), Pending)
+            },
+            ReaderState::Count(interval) => {
+                let count = input.read_u32::<NetworkEndian>()?;
+
+                (ReaderState::Until(interval, count), Pending)
+            },
+            ReaderState::Until(interval, count) => {
+                let until = input.read_i64::<NetworkEndian>()?;
+
+                (ReaderState::Entry(interval, count, until), Pending)
+            },
+            ReaderState::Entry(interval, count, until) => {
+                match self.entry.resume(input)? {
+                    Pending => return Ok(Pending),
+                    Complete(entry) => (ReaderState::Interval, Complete(AddRecurringEntry::new(interval, count, until, entry))),
+                }
+            },
+        };
+
+        self.state = state;
+
+        Ok(status)
+    }
+
+    fn rewind(&mut self) {
+        self.state = ReaderState::Interval;
+        self.entry.rewind();
+    }
+}
+
+impl WriteTo for AddRecurringEntry {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        target.write_u64::<NetworkEndian>(self.interval)?;
+        target.write_u32::<NetworkEndian>(self.count)?;
+        target.write_i64::<NetworkEndian>(self.until)?;
+
+        self.entry.write_to(target)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::super::{Message, MessageType};
+
+    #[test]
+    fn test_reader() {
+        let input = vec![
+            /* type     */ MessageType::AddRecurringEntry.into(),
+            /* interval */ 0, 0, 0, 0, 0, 0, 0, 30,
+            /* count    */ 0, 0, 0, 5,
+            /* until    */ 0, 0, 0, 0, 0, 0, 1, 0,
+            /* ts       */ 0, 0, 0, 0, 0, 0, 0, 10,
+            /* tag      */ 0, 0, 0, 0, 0, 0, 0, 42,
+            /* len      */ 0, 3,
+            /* data     */ 1, 2, 3
+        ];
+
+        let result = test_reader2!(Message::reader(), input);
+
+        assert!(result.is_ok());
+        assert_eq!(
+            Message::AddRecurringEntry(AddRecurringEntry::new(30, 5, 256, AddEntry::new(10, 42, vec![1, 2, 3]))),
+            result.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_write() {
+        let cmd = AddRecurringEntry::new(30, 0, 0, AddEntry::new(10, 128, vec![1, 2, 3]));
+        let mut vec = Vec::<u8>::new();
+
+        assert!(cmd.write_to(&mut vec).is_ok());
+
+        assert_eq!(
+            vec![
+                /* interval */ 0, 0, 0, 0, 0, 0, 0, 30,
+                /* count    */ 0, 0, 0, 0,
+                /* until    */ 0, 0, 0, 0, 0, 0, 0, 0,
+                /* ts       */ 0, 0, 0, 0, 0, 0, 0, 10,
+                /* tag      */ 0, 0, 0, 0, 0, 0, 0, 128,
+                /* len      */ 0, 3,
+                /* data     */ 1, 2, 3
+            ],
+            vec
+        );
+    }
+}
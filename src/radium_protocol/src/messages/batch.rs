@@ -0,0 +1,414 @@
+use std::io;
+use std::mem;
+use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
+use super::super::{WriteTo, WriteResult, Reader, ReaderStatus, Message, MessageInner, ErrorCode};
+use super::super::errors::{WriteError, InvalidValueError};
+use super::{AddEntry, AddEntryReader, RemoveEntry, RemoveEntryReader, EntryAdded, EntryAddedReader, EntryRemoved, EntryRemovedReader};
+use ReaderStatus::{Pending, Complete};
+
+/// Maximum number of items in a single [`Batch`]
+///
+/// [`Batch`]: struct.Batch.html
+pub const MAX_BATCH_ITEMS: u32 = 10000;
+
+/// count: u32 | items: count * `T`
+///
+/// A batch of actions of the same kind, which the server applies in one go.
+/// A batch holds at most [`MAX_BATCH_ITEMS`] items.
+///
+/// [`MAX_BATCH_ITEMS`]: constant.MAX_BATCH_ITEMS.html
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Batch<T> {
+    items: Vec<T>,
+}
+
+/// Adds all entries of the batch, answered with [`EntriesAdded`]
+///
+/// [`EntriesAdded`]: type.EntriesAdded.html
+pub type AddEntries = Batch<AddEntry>;
+
+/// Removes all entries of the batch, answered with [`EntriesRemoved`]
+///
+/// [`EntriesRemoved`]: type.EntriesRemoved.html
+pub type RemoveEntries = Batch<RemoveEntry>;
+
+/// count: u32 | results: count * (ok: u8 | `T` if ok is 1, [`ErrorCode`] if ok is 0)
+///
+/// The results of a [`Batch`], in the order of its items.
+///
+/// [`ErrorCode`]: ../enum.ErrorCode.html
+/// [`Batch`]: struct.Batch.html
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct BatchResults<T> {
+    results: Vec<Result<T, ErrorCode>>,
+}
+
+/// The ids of the entries of an [`AddEntries`] batch or the reason why they could not be added
+///
+/// [`AddEntries`]: type.AddEntries.html
+pub type EntriesAdded = BatchResults<EntryAdded>;
+
+/// The entries removed by a [`RemoveEntries`] batch or the reason why they could not be removed
+///
+/// [`RemoveEntries`]: type.RemoveEntries.html
+pub type EntriesRemoved = BatchResults<EntryRemoved>;
+
+#[derive(Debug)]
+enum BatchReaderState {
+    Count,
+    Items(u32),
+}
+
+#[derive(Debug)]
+pub struct BatchReader<T, R> {
+    state: BatchReaderState,
+    items: Vec<T>,
+    item: R,
+}
+
+#[derive(Debug)]
+enum ResultsReaderState {
+    Count,
+    Status(u32),
+    Item(u32),
+    Error(u32),
+}
+
+#[derive(Debug)]
+pub struct BatchResultsReader<T, R> {
+    state: ResultsReaderState,
+    results: Vec<Result<T, ErrorCode>>,
+    item: R,
+}
+
+impl<T> Batch<T> {
+    pub fn new(items: Vec<T>) -> Self {
+        Batch { items }
+    }
+
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    pub fn consume_items(self) -> Vec<T> {
+        self.items
+    }
+}
+
+impl Batch<AddEntry> {
+    pub fn reader() -> BatchReader<AddEntry, AddEntryReader> {
+        BatchReader::new(AddEntry::reader())
+    }
+}
+
+impl Batch<RemoveEntry> {
+    pub fn reader() -> BatchReader<RemoveEntry, RemoveEntryReader> {
+        BatchReader::new(RemoveEntry::reader())
+    }
+}
+
+impl MessageInner for Batch<AddEntry> {
+    fn wrap(self) -> Message {
+        Message::AddEntries(self)
+    }
+}
+
+impl MessageInner for Batch<RemoveEntry> {
+    fn wrap(self) -> Message {
+        Message::RemoveEntries(self)
+    }
+}
+
+impl<T> BatchResults<T> {
+    pub fn new(results: Vec<Result<T, ErrorCode>>) -> Self {
+        BatchResults { results }
+    }
+
+    pub fn results(&self) -> &[Result<T, ErrorCode>] {
+        &self.results
+    }
+
+    pub fn consume_results(self) -> Vec<Result<T, ErrorCode>> {
+        self.results
+    }
+}
+
+impl BatchResults<EntryAdded> {
+    pub fn reader() -> BatchResultsReader<EntryAdded, EntryAddedReader> {
+        BatchResultsReader::new(EntryAdded::reader())
+    }
+}
+
+impl BatchResults<EntryRemoved> {
+    pub fn reader() -> BatchResultsReader<EntryRemoved, EntryRemovedReader> {
+        BatchResultsReader::new(EntryRemoved::reader())
+    }
+}
+
+impl MessageInner for BatchResults<EntryAdded> {
+    fn wrap(self) -> Message {
+        Message::EntriesAdded(self)
+    }
+}
+
+impl MessageInner for BatchResults<EntryRemoved> {
+    fn wrap(self) -> Message {
+        Message::EntriesRemoved(self)
+    }
+}
+
+impl<T, R> BatchReader<T, R> {
+    fn new(item: R) -> Self {
+        BatchReader {
+            state: BatchReaderState::Count,
+            items: Vec::new(),
+            item,
+        }
+    }
+}
+
+impl<T, R: Reader<T>> Reader<Batch<T>> for BatchReader<T, R> {
+    fn resume<I>(&mut self, input: &mut I) -> io::Result<ReaderStatus<Batch<T>>> where I: io::Read {
+        let (state, status) = match self.state {
+            BatchReaderState::Count => {
+                let count = input.read_u32::<NetworkEndian>()?;
+
+                if count > MAX_BATCH_ITEMS {
+                    return Err(InvalidValueError::new());
+                }
+
+                (BatchReaderState::Items(count), Pending)
+            }
+            BatchReaderState::Items(remaining) if remaining > 0 => {
+                match self.item.resume(input)? {
+                    Pending => return Ok(Pending),
+                    Complete(item) => {
+                        self.items.push(item);
+
+                        (BatchReaderState::Items(remaining - 1), Pending)
+                    }
+                }
+            }
+            BatchReaderState::Items(_) => {
+                let items = mem::replace(&mut self.items, Vec::new());
+
+                (BatchReaderState::Count, Complete(Batch::new(items)))
+            }
+        };
+
+        self.state = state;
+
+        Ok(status)
+    }
+
+    fn rewind(&mut self) {
+        self.state = BatchReaderState::Count;
+        self.items.clear();
+        self.item.rewind();
+    }
+}
+
+impl<T, R> BatchResultsReader<T, R> {
+    fn new(item: R) -> Self {
+        BatchResultsReader {
+            state: ResultsReaderState::Count,
+            results: Vec::new(),
+            item,
+        }
+    }
+}
+
+impl<T, R: Reader<T>> Reader<BatchResults<T>> for BatchResultsReader<T, R> {
+    fn resume<I>(&mut self, input: &mut I) -> io::Result<ReaderStatus<BatchResults<T>>> where I: io::Read {
+        let (state, status) = match self.state {
+            ResultsReaderState::Count => {
+                let count = input.read_u32::<NetworkEndian>()?;
+
+                (ResultsReaderState::Status(count), Pending)
+            }
+            ResultsReaderState::Status(remaining) if remaining > 0 => {
+                match input.read_u8()? {
+                    1 => (ResultsReaderState::Item(remaining), Pending),
+                    0 => (ResultsReaderState::Error(remaining), Pending),
+                    _ => return Err(InvalidValueError::new()),
+                }
+            }
+            ResultsReaderState::Status(_) => {
+                let results = mem::replace(&mut self.results, Vec::new());
+
+                (ResultsReaderState::Count, Complete(BatchResults::new(results)))
+            }
+            ResultsReaderState::Item(remaining) => {
+                match self.item.resume(input)? {
+                    Pending => return Ok(Pending),
+                    Complete(item) => {
+                        self.results.push(Ok(item));
+
+                        (ResultsReaderState::Status(remaining - 1), Pending)
+                    }
+                }
+            }
+            ResultsReaderState::Error(remaining) => {
+                match ErrorCode::reader().resume(input)? {
+                    Pending => return Ok(Pending),
+                    Complete(code) => {
+                        self.results.push(Err(code));
+
+                        (ResultsReaderState::Status(remaining - 1), Pending)
+                    }
+                }
+            }
+        };
+
+        self.state = state;
+
+        Ok(status)
+    }
+
+    fn rewind(&mut self) {
+        self.state = ResultsReaderState::Count;
+        self.results.clear();
+        self.item.rewind();
+    }
+}
+
+impl<T: WriteTo> WriteTo for Batch<T> {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        if self.items.len() > MAX_BATCH_ITEMS as usize {
+            return Err(WriteError::DataLengthOverflow);
+        }
+
+        target.write_u32::<NetworkEndian>(self.items.len() as u32)?;
+
+        for item in &self.items {
+            item.write_to(target)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: WriteTo> WriteTo for BatchResults<T> {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        if self.results.len() > u32::max_value() as usize {
+            return Err(WriteError::DataLengthOverflow);
+        }
+
+        target.write_u32::<NetworkEndian>(self.results.len() as u32)?;
+
+        for result in &self.results {
+            match result {
+                &Ok(ref item) => {
+                    target.write_u8(1)?;
+                    item.write_to(target)?;
+                }
+                &Err(ref code) => {
+                    target.write_u8(0)?;
+                    code.write_to(target)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::super::WriteTo;
+
+    fn batch_input() -> Vec<u8> {
+        vec![
+            /* count */ 0, 0, 0, 2,
+            /* ts    */ 0, 0, 0, 0, 0, 0, 0, 10,
+            /* tag   */ 0, 0, 0, 0, 0, 0, 0, 42,
+            /* len   */ 0, 1,
+            /* data  */ 1,
+            /* ts    */ 0, 0, 0, 0, 0, 0, 0, 20,
+            /* tag   */ 0, 0, 0, 0, 0, 0, 0, 43,
+            /* len   */ 0, 0,
+        ]
+    }
+
+    fn batch() -> AddEntries {
+        Batch::new(vec![AddEntry::new(10, 42, vec![1]), AddEntry::new(20, 43, vec![])])
+    }
+
+    fn results_input() -> Vec<u8> {
+        vec![
+            /* count */ 0, 0, 0, 2,
+            /* ok    */ 1,
+            /* ts    */ 0, 0, 0, 0, 0, 0, 0, 10,
+            /* id    */ 0, 0, 0, 0, 0, 0, 0, 7,
+            /* ok    */ 0,
+            /* code  */ 8,
+        ]
+    }
+
+    fn results() -> EntriesAdded {
+        BatchResults::new(vec![Ok(EntryAdded::new(10, 7)), Err(ErrorCode::StorageFull)])
+    }
+
+    #[test]
+    fn test_write_batch() {
+        let mut vec = Vec::<u8>::new();
+
+        assert!(batch().write_to(&mut vec).is_ok());
+        assert_eq!(batch_input(), vec);
+    }
+
+    #[test]
+    fn test_batch_reader() {
+        let result = test_reader2!(AddEntries::reader(), batch_input());
+
+        assert!(result.is_ok());
+        assert_eq!(batch(), result.unwrap());
+    }
+
+    #[test]
+    fn test_batch_reader_empty() {
+        let result = test_reader2!(RemoveEntries::reader(), vec![0, 0, 0, 0]);
+
+        assert!(result.is_ok());
+        assert_eq!(RemoveEntries::new(vec![]), result.unwrap());
+    }
+
+    #[test]
+    fn test_batch_reader_too_many_items() {
+        // Fails on the count alone, without waiting for the items
+        let result = test_reader2!(AddEntries::reader(), vec![0, 0, 0x27, 0x11]);
+
+        assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+    }
+
+    #[test]
+    fn test_write_batch_too_many_items() {
+        let batch = RemoveEntries::new(vec![RemoveEntry::new(1, 2); MAX_BATCH_ITEMS as usize + 1]);
+
+        assert!(batch.write_to(&mut Vec::<u8>::new()).is_err());
+    }
+
+    #[test]
+    fn test_write_results() {
+        let mut vec = Vec::<u8>::new();
+
+        assert!(results().write_to(&mut vec).is_ok());
+        assert_eq!(results_input(), vec);
+    }
+
+    #[test]
+    fn test_results_reader() {
+        let result = test_reader2!(EntriesAdded::reader(), results_input());
+
+        assert!(result.is_ok());
+        assert_eq!(results(), result.unwrap());
+    }
+
+    #[test]
+    fn test_results_reader_invalid_status() {
+        let result = test_reader2!(EntriesRemoved::reader(), vec![0, 0, 0, 1, 2]);
+
+        assert!(result.is_err());
+    }
+}
@@ -3,11 +3,15 @@ use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
 use super::super::{WriteTo, WriteResult, Reader, ReaderStatus, MessageInner, Message};
 use ReaderStatus::{Pending, Complete};
 
-/// ts: i64 | id: u16
+/// ts: i64 | id: u64
+///
+/// `ts` is given in the [`Precision`] of the connection.
+///
+/// [`Precision`]: ../enum.Precision.html
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct EntryAdded {
     timestamp: i64,
-    id: u16,
+    id: u64,
 }
 
 #[derive(Debug)]
@@ -22,7 +26,7 @@ pub struct EntryAddedReader {
 }
 
 impl EntryAdded {
-    pub fn new(timestamp: i64, id: u16) -> Self {
+    pub fn new(timestamp: i64, id: u64) -> Self {
         EntryAdded { timestamp, id }
     }
 
@@ -34,7 +38,7 @@ impl EntryAdded {
         self.timestamp
     }
 
-    pub fn id(&self) -> u16 {
+    pub fn id(&self) -> u64 {
         self.id
     }
 }
@@ -48,7 +52,7 @@ impl MessageInner for EntryAdded {
 impl WriteTo for EntryAdded {
     fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
         target.write_i64::<NetworkEndian>(self.timestamp)?;
-        target.write_u16::<NetworkEndian>(self.id)?;
+        target.write_u64::<NetworkEndian>(self.id)?;
 
         Ok(())
     }
@@ -63,7 +67,7 @@ impl Reader<EntryAdded> for EntryAddedReader {
                 (ReaderState::Id(timestamp), Pending)
             }
             ReaderState::Id(timestamp) => {
-                let id = input.read_u16::<NetworkEndian>()?;
+                let id = input.read_u64::<NetworkEndian>()?;
                 let inner = EntryAdded::new(timestamp, id);
 
                 (ReaderState::Timestamp, Complete(inner))
@@ -95,7 +99,7 @@ mod test {
         assert_eq!(
             vec![
                 /* ts  */ 0, 0, 0, 0, 0, 0, 48, 57,
-                /* id  */ 0, 23,
+                /* id  */ 0, 0, 0, 0, 0, 0, 0, 23,
             ],
             vec
         );
@@ -105,7 +109,7 @@ mod test {
     fn test_read() {
         let input = vec![
             /* ts  */ 0, 0, 0, 0, 0, 0, 48, 57,
-            /* id  */ 0, 23,
+            /* id  */ 0, 0, 0, 0, 0, 0, 0, 23,
         ];
 
         let result = test_reader2!(EntryAdded::reader(), input);
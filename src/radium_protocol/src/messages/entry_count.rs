@@ -0,0 +1,77 @@
+use std::io;
+use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
+use super::super::{WriteTo, WriteResult, Reader, ReaderStatus, Message, MessageInner};
+
+/// count: u64
+///
+/// The number of pending entries, sent in reply to `CountEntries`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct EntryCount {
+    count: u64,
+}
+
+#[derive(Debug)]
+pub struct EntryCountReader;
+
+impl EntryCount {
+    pub fn new(count: u64) -> Self {
+        EntryCount { count }
+    }
+
+    pub fn reader() -> EntryCountReader {
+        EntryCountReader {}
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl MessageInner for EntryCount {
+    fn wrap(self) -> Message {
+        Message::EntryCount(self)
+    }
+}
+
+impl Reader<EntryCount> for EntryCountReader {
+    fn resume<I>(&mut self, input: &mut I) -> io::Result<ReaderStatus<EntryCount>> where I: io::Read {
+        let count = input.read_u64::<NetworkEndian>()?;
+
+        Ok(ReaderStatus::Complete(EntryCount::new(count)))
+    }
+
+    fn rewind(&mut self) {}
+}
+
+impl WriteTo for EntryCount {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        target.write_u64::<NetworkEndian>(self.count)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::super::WriteTo;
+
+    #[test]
+    fn test_write() {
+        let msg = EntryCount::new(12345);
+        let mut vec = Vec::<u8>::new();
+
+        assert!(msg.write_to(&mut vec).is_ok());
+        assert_eq!(vec![0, 0, 0, 0, 0, 0, 48, 57], vec);
+    }
+
+    #[test]
+    fn test_reader() {
+        let input = vec![0, 0, 0, 0, 0, 0, 48, 57];
+
+        let result = test_reader2!(EntryCount::reader(), input);
+
+        assert!(result.is_ok());
+        assert_eq!(EntryCount::new(12345), result.unwrap());
+    }
+}
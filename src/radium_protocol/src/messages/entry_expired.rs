@@ -1,26 +1,40 @@
 use std::io;
 use std::io::Read;
+use std::mem;
 use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
 use super::super::{WriteTo, WriteResult, Reader, ReaderStatus, Message, MessageInner, HasReader};
-use super::super::errors::{WriteError, DataLengthError};
+use super::super::errors::{WriteError, DataLengthError, InvalidValueError};
 use ReaderStatus::{Pending, Complete};
 
-/// ts: i64 | id: u16 | tag: u64 | len: u16 | data: (len < 2**16)
+/// ts: i64 | id: u64 | occurrence: u32 | tag: u64 | len: u16 | data: (len < 2**16) | fired: u8 | fired_at: i64 (if fired is 1)
+///
+/// `ts` and `fired_at` are given in the [`Precision`] of the connection.
+/// `occurrence` counts the expirations of a recurring entry, starting at 1.
+/// It is always 1 for entries that don't recur.
+/// `fired_at` is the time at which the server actually expired the entry, if it is known,
+/// so that the difference to `ts` tells how late the entry fired.
+///
+/// [`Precision`]: ../enum.Precision.html
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct EntryExpired {
     timestamp: i64,
-    id: u16,
+    id: u64,
+    occurrence: u32,
     tag: u64,
     data: Vec<u8>,
+    fired_at: Option<i64>,
 }
 
 #[derive(Debug)]
 enum ReaderState {
     Timestamp,
     Id(i64),
-    Tag(i64, u16),
-    Length(i64, u16, u64),
-    Data(i64, u16, u64, u64),
+    Occurrence(i64, u64),
+    Tag(i64, u64, u32),
+    Length(i64, u64, u32, u64),
+    Data(i64, u64, u32, u64, u64),
+    Fired(i64, u64, u32, u64),
+    FiredAt(i64, u64, u32, u64),
 }
 
 impl ReaderState {
@@ -33,18 +47,26 @@ impl ReaderState {
 #[derive(Debug)]
 pub struct EntryExpiredReader {
     state: ReaderState,
+    data: Vec<u8>,
 }
 
 impl EntryExpired {
-    pub fn new<T: Into<i64>>(timestamp: T, id: u16, tag: u64, data: Vec<u8>) -> Self {
+    pub fn new<T: Into<i64>>(timestamp: T, id: u64, occurrence: u32, tag: u64, data: Vec<u8>) -> Self {
         EntryExpired {
             timestamp: timestamp.into(),
             id,
+            occurrence,
             tag,
             data,
+            fired_at: None,
         }
     }
 
+    pub fn with_fired_at(mut self, fired_at: i64) -> Self {
+        self.fired_at = Some(fired_at);
+        self
+    }
+
     pub fn timestamp(&self) -> i64 {
         self.timestamp
     }
@@ -53,13 +75,21 @@ impl EntryExpired {
         self.tag
     }
 
-    pub fn id(&self) -> u16 {
+    pub fn id(&self) -> u64 {
         self.id
     }
 
+    pub fn occurrence(&self) -> u32 {
+        self.occurrence
+    }
+
     pub fn data(&self) -> &[u8] {
         &self.data
     }
+
+    pub fn fired_at(&self) -> Option<i64> {
+        self.fired_at
+    }
 }
 
 impl MessageInner for EntryExpired {
@@ -72,7 +102,7 @@ impl HasReader for EntryExpired {
     type Reader = EntryExpiredReader;
 
     fn reader() -> Self::Reader {
-        EntryExpiredReader { state: ReaderState::initial() }
+        EntryExpiredReader { state: ReaderState::initial(), data: Vec::new() }
     }
 }
 
@@ -85,12 +115,21 @@ impl WriteTo for EntryExpired {
         }
 
         target.write_i64::<NetworkEndian>(self.timestamp)?;
-        target.write_u16::<NetworkEndian>(self.id)?;
+        target.write_u64::<NetworkEndian>(self.id)?;
+        target.write_u32::<NetworkEndian>(self.occurrence)?;
         target.write_u64::<NetworkEndian>(self.tag)?;
         target.write_u16::<NetworkEndian>(len as u16)?;
 
         target.write(&self.data)?;
 
+        match self.fired_at {
+            Some(fired_at) => {
+                target.write_u8(1)?;
+                target.write_i64::<NetworkEndian>(fired_at)?;
+            }
+            None => target.write_u8(0)?,
+        }
+
         Ok(())
     }
 }
@@ -104,21 +143,26 @@ impl Reader<EntryExpired> for EntryExpiredReader {
                 (ReaderState::Id(timestamp), Pending)
             }
             ReaderState::Id(timestamp) => {
-                let id = input.read_u16::<NetworkEndian>()?;
+                let id = input.read_u64::<NetworkEndian>()?;
+
+                (ReaderState::Occurrence(timestamp, id), Pending)
+            }
+            ReaderState::Occurrence(timestamp, id) => {
+                let occurrence = input.read_u32::<NetworkEndian>()?;
 
-                (ReaderState::Tag(timestamp, id), Pending)
+                (ReaderState::Tag(timestamp, id, occurrence), Pending)
             }
-            ReaderState::Tag(timestamp, id) => {
+            ReaderState::Tag(timestamp, id, occurrence) => {
                 let tag = input.read_u64::<NetworkEndian>()?;
 
-                (ReaderState::Length(timestamp, id, tag), Pending)
+                (ReaderState::Length(timestamp, id, occurrence, tag), Pending)
             }
-            ReaderState::Length(timestamp, id, tag) => {
+            ReaderState::Length(timestamp, id, occurrence, tag) => {
                 let length = input.read_u16::<NetworkEndian>()?;
 
-                (ReaderState::Data(timestamp, id, tag, length as u64), Pending)
+                (ReaderState::Data(timestamp, id, occurrence, tag, length as u64), Pending)
             }
-            ReaderState::Data(timestamp, id, tag, length) => {
+            ReaderState::Data(timestamp, id, occurrence, tag, length) => {
                 let mut buf = Vec::new();
                 let bytes_read = input.take(length).read_to_end(&mut buf)?;
 
@@ -126,7 +170,27 @@ impl Reader<EntryExpired> for EntryExpiredReader {
                     return Err(DataLengthError::new());
                 }
 
-                (ReaderState::initial(), Complete(EntryExpired::new(timestamp, id, tag, buf)))
+                self.data = buf;
+
+                (ReaderState::Fired(timestamp, id, occurrence, tag), Pending)
+            }
+            ReaderState::Fired(timestamp, id, occurrence, tag) => {
+                match input.read_u8()? {
+                    0 => {
+                        let data = mem::replace(&mut self.data, Vec::new());
+
+                        (ReaderState::initial(), Complete(EntryExpired::new(timestamp, id, occurrence, tag, data)))
+                    }
+                    1 => (ReaderState::FiredAt(timestamp, id, occurrence, tag), Pending),
+                    _ => return Err(InvalidValueError::new()),
+                }
+            }
+            ReaderState::FiredAt(timestamp, id, occurrence, tag) => {
+                let fired_at = input.read_i64::<NetworkEndian>()?;
+                let data = mem::replace(&mut self.data, Vec::new());
+                let msg = EntryExpired::new(timestamp, id, occurrence, tag, data).with_fired_at(fired_at);
+
+                (ReaderState::initial(), Complete(msg))
             }
         };
 
@@ -137,6 +201,7 @@ impl Reader<EntryExpired> for EntryExpiredReader {
 
     fn rewind(&mut self) {
         self.state = ReaderState::initial();
+        self.data.clear();
     }
 }
 
@@ -149,39 +214,63 @@ mod test {
     fn test_read() {
         let input = vec![
             /* ts   */ 0, 0, 0, 0, 0, 0, 0, 10,
-            /* id   */ 0, 7,
+            /* id   */ 0, 0, 0, 0, 0, 0, 0, 7,
+            /* occ  */ 0, 0, 0, 1,
             /* tag  */ 0, 0, 0, 0, 0, 0, 0, 42,
             /* len  */ 0, 3,
             /* data */ 1, 2, 3,
+            /* fired */ 0,
+        ];
+
+        let result = test_reader2!(EntryExpired::reader(), input);
+
+        assert!(result.is_ok());
+        assert_eq!(EntryExpired::new(10, 7, 1, 42, vec![1, 2, 3]), result.unwrap());
+    }
+
+    #[test]
+    fn test_read_fired_at() {
+        let input = vec![
+            /* ts   */ 0, 0, 0, 0, 0, 0, 0, 10,
+            /* id   */ 0, 0, 0, 0, 0, 0, 0, 7,
+            /* occ  */ 0, 0, 0, 1,
+            /* tag  */ 0, 0, 0, 0, 0, 0, 0, 42,
+            /* len  */ 0, 0,
+            /* fired */ 1,
+            /* at   */ 0, 0, 0, 0, 0, 0, 0, 13,
         ];
 
         let result = test_reader2!(EntryExpired::reader(), input);
 
         assert!(result.is_ok());
-        assert_eq!(EntryExpired::new(10, 7, 42, vec![1, 2, 3]), result.unwrap());
+        assert_eq!(EntryExpired::new(10, 7, 1, 42, vec![]).with_fired_at(13), result.unwrap());
     }
 
     #[test]
     fn test_read_respects_size() {
         let input = vec![
             /* ts   */ 0, 0, 0, 0, 0, 0, 0, 10,
-            /* id   */ 0, 7,
+            /* id   */ 0, 0, 0, 0, 0, 0, 0, 7,
+            /* occ  */ 0, 0, 0, 1,
             /* tag  */ 0, 0, 0, 0, 0, 0, 0, 32,
             /* len  */ 0, 3,
-            /* data */ 1, 2, 3, 4,
+            /* data */ 1, 2, 3,
+            /* fired */ 0,
+            /* next */ 4,
         ];
 
         let result = test_reader2!(EntryExpired::reader(), input);
 
         assert!(result.is_ok());
-        assert_eq!(EntryExpired::new(10, 7, 32, vec![1, 2, 3]), result.unwrap());
+        assert_eq!(EntryExpired::new(10, 7, 1, 32, vec![1, 2, 3]), result.unwrap());
     }
 
     #[test]
     fn test_fails_on_data_eof() {
         let input = vec![
             /* ts   */ 0, 0, 0, 0, 0, 0, 0, 10,
-            /* id   */ 0, 7,
+            /* id   */ 0, 0, 0, 0, 0, 0, 0, 7,
+            /* occ  */ 0, 0, 0, 1,
             /* tag  */ 0, 0, 0, 0, 0, 0, 0, 42,
             /* len  */ 0, 10,
             /* data */ 1, 2, 3,
@@ -195,7 +284,7 @@ mod test {
 
     #[test]
     fn test_write() {
-        let cmd = EntryExpired::new(10, 7, 12, vec![1, 2, 3]);
+        let cmd = EntryExpired::new(10, 7, 1, 12, vec![1, 2, 3]).with_fired_at(11);
         let mut vec = Vec::<u8>::new();
 
         assert!(cmd.write_to(&mut vec).is_ok());
@@ -203,10 +292,13 @@ mod test {
         assert_eq!(
             vec![
                 /* ts   */ 0, 0, 0, 0, 0, 0, 0, 10,
-                /* id   */ 0, 7,
+                /* id   */ 0, 0, 0, 0, 0, 0, 0, 7,
+                /* occ  */ 0, 0, 0, 1,
                 /* tag  */ 0, 0, 0, 0, 0, 0, 0, 12,
                 /* len  */ 0, 3,
                 /* data */ 1, 2, 3,
+                /* fired */ 1,
+                /* at   */ 0, 0, 0, 0, 0, 0, 0, 11,
             ],
             vec
         );
@@ -220,7 +312,7 @@ mod test {
             data.push(0);
         }
 
-        let cmd = EntryExpired::new(0, 7, 0, data);
+        let cmd = EntryExpired::new(0, 7, 1, 0, data);
         let mut target = Vec::<u8>::new();
 
         let result = cmd.write_to(&mut target);
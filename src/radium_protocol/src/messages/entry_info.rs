@@ -0,0 +1,194 @@
+use std::io;
+use std::io::Read;
+use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
+use super::super::{WriteTo, WriteResult, Reader, ReaderStatus, Message, MessageInner};
+use super::super::errors::{WriteError, DataLengthError};
+use ReaderStatus::{Pending, Complete};
+
+/// ts: i64 | id: u64 | tag: u64 | len: u16 | data: (len < 2**16)
+///
+/// A pending entry, sent in reply to `GetEntry` and as part of an [`EntryList`].
+///
+/// `ts` is given in the [`Precision`] of the connection.
+///
+/// [`EntryList`]: struct.EntryList.html
+/// [`Precision`]: ../enum.Precision.html
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct EntryInfo {
+    timestamp: i64,
+    id: u64,
+    tag: u64,
+    data: Vec<u8>,
+}
+
+#[derive(Debug)]
+enum ReaderState {
+    Timestamp,
+    Id(i64),
+    Tag(i64, u64),
+    Length(i64, u64, u64),
+    Data(i64, u64, u64, u64),
+}
+
+#[derive(Debug)]
+pub struct EntryInfoReader {
+    state: ReaderState,
+}
+
+impl EntryInfo {
+    pub fn new(timestamp: i64, id: u64, tag: u64, data: Vec<u8>) -> Self {
+        EntryInfo {
+            timestamp,
+            id,
+            tag,
+            data,
+        }
+    }
+
+    pub fn reader() -> EntryInfoReader {
+        EntryInfoReader { state: ReaderState::Timestamp }
+    }
+
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn tag(&self) -> u64 {
+        self.tag
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl MessageInner for EntryInfo {
+    fn wrap(self) -> Message {
+        Message::EntryInfo(self)
+    }
+}
+
+impl WriteTo for EntryInfo {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        let len = self.data.len();
+
+        if len > u16::max_value() as usize {
+            return Err(WriteError::DataLengthOverflow);
+        }
+
+        target.write_i64::<NetworkEndian>(self.timestamp)?;
+        target.write_u64::<NetworkEndian>(self.id)?;
+        target.write_u64::<NetworkEndian>(self.tag)?;
+        target.write_u16::<NetworkEndian>(len as u16)?;
+
+        target.write(&self.data)?;
+
+        Ok(())
+    }
+}
+
+impl Reader<EntryInfo> for EntryInfoReader {
+    fn resume<I>(&mut self, input: &mut I) -> io::Result<ReaderStatus<EntryInfo>> where I: io::Read {
+        let (state, status) = match self.state {
+            ReaderState::Timestamp => {
+                let timestamp = input.read_i64::<NetworkEndian>()?;
+
+                (ReaderState::Id(timestamp), Pending)
+            }
+            ReaderState::Id(timestamp) => {
+                let id = input.read_u64::<NetworkEndian>()?;
+
+                (ReaderState::Tag(timestamp, id), Pending)
+            }
+            ReaderState::Tag(timestamp, id) => {
+                let tag = input.read_u64::<NetworkEndian>()?;
+
+                (ReaderState::Length(timestamp, id, tag), Pending)
+            }
+            ReaderState::Length(timestamp, id, tag) => {
+                let length = input.read_u16::<NetworkEndian>()?;
+
+                (ReaderState::Data(timestamp, id, tag, length as u64), Pending)
+            }
+            ReaderState::Data(timestamp, id, tag, length) => {
+                let mut buf = Vec::new();
+                let bytes_read = input.take(length).read_to_end(&mut buf)?;
+
+                if (bytes_read as u64) < length {
+                    return Err(DataLengthError::new());
+                }
+
+                (ReaderState::Timestamp, Complete(EntryInfo::new(timestamp, id, tag, buf)))
+            }
+        };
+
+        self.state = state;
+
+        Ok(status)
+    }
+
+    fn rewind(&mut self) {
+        self.state = ReaderState::Timestamp;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        let input = vec![
+            /* ts   */ 0, 0, 0, 0, 0, 0, 0, 10,
+            /* id   */ 0, 0, 0, 0, 0, 0, 0, 7,
+            /* tag  */ 0, 0, 0, 0, 0, 0, 0, 42,
+            /* len  */ 0, 3,
+            /* data */ 1, 2, 3,
+        ];
+
+        let result = test_reader2!(EntryInfo::reader(), input);
+
+        assert!(result.is_ok());
+        assert_eq!(EntryInfo::new(10, 7, 42, vec![1, 2, 3]), result.unwrap());
+    }
+
+    #[test]
+    fn test_fails_on_data_eof() {
+        let input = vec![
+            /* ts   */ 0, 0, 0, 0, 0, 0, 0, 10,
+            /* id   */ 0, 0, 0, 0, 0, 0, 0, 7,
+            /* tag  */ 0, 0, 0, 0, 0, 0, 0, 42,
+            /* len  */ 0, 10,
+            /* data */ 1, 2, 3,
+        ];
+
+        let result = test_reader2!(EntryInfo::reader(), input);
+
+        assert!(result.is_err());
+        assert_eq!(DataLengthError::new().description(), result.unwrap_err().description());
+    }
+
+    #[test]
+    fn test_write() {
+        let cmd = EntryInfo::new(10, 7, 12, vec![1, 2, 3]);
+        let mut vec = Vec::<u8>::new();
+
+        assert!(cmd.write_to(&mut vec).is_ok());
+
+        assert_eq!(
+            vec![
+                /* ts   */ 0, 0, 0, 0, 0, 0, 0, 10,
+                /* id   */ 0, 0, 0, 0, 0, 0, 0, 7,
+                /* tag  */ 0, 0, 0, 0, 0, 0, 0, 12,
+                /* len  */ 0, 3,
+                /* data */ 1, 2, 3,
+            ],
+            vec
+        );
+    }
+}
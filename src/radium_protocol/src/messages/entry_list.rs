@@ -0,0 +1,160 @@
+use std::io;
+use std::mem;
+use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
+use super::super::{WriteTo, WriteResult, Reader, ReaderStatus, Message, MessageInner};
+use super::super::errors::WriteError;
+use super::{EntryInfo, EntryInfoReader};
+use ReaderStatus::{Pending, Complete};
+
+/// count: u32 | entries: count * [`EntryInfo`]
+///
+/// The entries matching a `ListEntries` message.
+///
+/// [`EntryInfo`]: struct.EntryInfo.html
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct EntryList {
+    entries: Vec<EntryInfo>,
+}
+
+#[derive(Debug)]
+enum ReaderState {
+    Count,
+    Entries(u32),
+}
+
+#[derive(Debug)]
+pub struct EntryListReader {
+    state: ReaderState,
+    entries: Vec<EntryInfo>,
+    entry: EntryInfoReader,
+}
+
+impl EntryList {
+    pub fn new(entries: Vec<EntryInfo>) -> Self {
+        EntryList { entries }
+    }
+
+    pub fn reader() -> EntryListReader {
+        EntryListReader {
+            state: ReaderState::Count,
+            entries: Vec::new(),
+            entry: EntryInfo::reader(),
+        }
+    }
+
+    pub fn entries(&self) -> &[EntryInfo] {
+        &self.entries
+    }
+
+    pub fn consume_entries(self) -> Vec<EntryInfo> {
+        self.entries
+    }
+}
+
+impl MessageInner for EntryList {
+    fn wrap(self) -> Message {
+        Message::EntryList(self)
+    }
+}
+
+impl Reader<EntryList> for EntryListReader {
+    fn resume<I>(&mut self, input: &mut I) -> io::Result<ReaderStatus<EntryList>> where I: io::Read {
+        let (state, status) = match self.state {
+            ReaderState::Count => {
+                let count = input.read_u32::<NetworkEndian>()?;
+
+                (ReaderState::Entries(count), Pending)
+            }
+            ReaderState::Entries(remaining) if remaining > 0 => {
+                match self.entry.resume(input)? {
+                    Pending => return Ok(Pending),
+                    Complete(entry) => {
+                        self.entries.push(entry);
+
+                        (ReaderState::Entries(remaining - 1), Pending)
+                    }
+                }
+            }
+            ReaderState::Entries(_) => {
+                let entries = mem::replace(&mut self.entries, Vec::new());
+
+                (ReaderState::Count, Complete(EntryList::new(entries)))
+            }
+        };
+
+        self.state = state;
+
+        Ok(status)
+    }
+
+    fn rewind(&mut self) {
+        self.state = ReaderState::Count;
+        self.entries.clear();
+        self.entry.rewind();
+    }
+}
+
+impl WriteTo for EntryList {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        if self.entries.len() > u32::max_value() as usize {
+            return Err(WriteError::DataLengthOverflow);
+        }
+
+        target.write_u32::<NetworkEndian>(self.entries.len() as u32)?;
+
+        for entry in &self.entries {
+            entry.write_to(target)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::super::WriteTo;
+
+    fn input() -> Vec<u8> {
+        vec![
+            /* count */ 0, 0, 0, 2,
+            /* ts    */ 0, 0, 0, 0, 0, 0, 0, 10,
+            /* id    */ 0, 0, 0, 0, 0, 0, 0, 7,
+            /* tag   */ 0, 0, 0, 0, 0, 0, 0, 42,
+            /* len   */ 0, 1,
+            /* data  */ 1,
+            /* ts    */ 0, 0, 0, 0, 0, 0, 0, 20,
+            /* id    */ 0, 0, 0, 0, 0, 0, 0, 8,
+            /* tag   */ 0, 0, 0, 0, 0, 0, 0, 43,
+            /* len   */ 0, 0,
+        ]
+    }
+
+    fn list() -> EntryList {
+        EntryList::new(vec![EntryInfo::new(10, 7, 42, vec![1]), EntryInfo::new(20, 8, 43, vec![])])
+    }
+
+    #[test]
+    fn test_write() {
+        let mut vec = Vec::<u8>::new();
+
+        assert!(list().write_to(&mut vec).is_ok());
+        assert_eq!(input(), vec);
+    }
+
+    #[test]
+    fn test_reader() {
+        let result = test_reader2!(EntryList::reader(), input());
+
+        assert!(result.is_ok());
+        assert_eq!(list(), result.unwrap());
+    }
+
+    #[test]
+    fn test_reader_empty() {
+        let result = test_reader2!(EntryList::reader(), vec![0, 0, 0, 0]);
+
+        assert!(result.is_ok());
+        assert_eq!(EntryList::new(vec![]), result.unwrap());
+    }
+}
@@ -0,0 +1,156 @@
+use std::io;
+use std::io::Read;
+use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
+use super::super::{WriteTo, WriteResult, Reader, ReaderStatus, Message, MessageInner};
+use super::super::errors::{WriteError, DataLengthError};
+use ReaderStatus::{Pending, Complete};
+
+/// tag: u64 | len: u16 | data: (len < 2**16)
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct EntryRemoved {
+    tag: u64,
+    data: Vec<u8>,
+}
+
+#[derive(Debug)]
+enum ReaderState {
+    Tag,
+    Length(u64),
+    Data(u64, u64),
+}
+
+#[derive(Debug)]
+pub struct EntryRemovedReader {
+    state: ReaderState,
+}
+
+impl EntryRemoved {
+    pub fn new(tag: u64, data: Vec<u8>) -> Self {
+        EntryRemoved { tag, data }
+    }
+
+    pub fn reader() -> EntryRemovedReader {
+        EntryRemovedReader { state: ReaderState::Tag }
+    }
+
+    pub fn tag(&self) -> u64 {
+        self.tag
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn consume_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl MessageInner for EntryRemoved {
+    fn wrap(self) -> Message {
+        Message::EntryRemoved(self)
+    }
+}
+
+impl Reader<EntryRemoved> for EntryRemovedReader {
+    fn resume<I>(&mut self, input: &mut I) -> io::Result<ReaderStatus<EntryRemoved>> where I: io::Read {
+        let (state, status) = match self.state {
+            ReaderState::Tag => {
+                let tag = input.read_u64::<NetworkEndian>()?;
+
+                (ReaderState::Length(tag), Pending)
+            }
+            ReaderState::Length(tag) => {
+                let length = input.read_u16::<NetworkEndian>()?;
+
+                (ReaderState::Data(tag, length as u64), Pending)
+            }
+            ReaderState::Data(tag, length) => {
+                let mut buf = Vec::new();
+                let bytes_read = input.take(length).read_to_end(&mut buf)?;
+
+                if (bytes_read as u64) < length {
+                    return Err(DataLengthError::new());
+                }
+
+                (ReaderState::Tag, Complete(EntryRemoved::new(tag, buf)))
+            }
+        };
+
+        self.state = state;
+
+        Ok(status)
+    }
+
+    fn rewind(&mut self) {
+        self.state = ReaderState::Tag;
+    }
+}
+
+impl WriteTo for EntryRemoved {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        let len = self.data.len();
+
+        if len > u16::max_value() as usize {
+            return Err(WriteError::DataLengthOverflow);
+        }
+
+        target.write_u64::<NetworkEndian>(self.tag)?;
+        target.write_u16::<NetworkEndian>(len as u16)?;
+
+        target.write(&self.data)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        let input = vec![
+            /* tag  */ 0, 0, 0, 0, 0, 0, 0, 42,
+            /* len  */ 0, 3,
+            /* data */ 1, 2, 3,
+        ];
+
+        let result = test_reader2!(EntryRemoved::reader(), input);
+
+        assert!(result.is_ok());
+        assert_eq!(EntryRemoved::new(42, vec![1, 2, 3]), result.unwrap());
+    }
+
+    #[test]
+    fn test_fails_on_data_eof() {
+        let input = vec![
+            /* tag  */ 0, 0, 0, 0, 0, 0, 0, 42,
+            /* len  */ 0, 10,
+            /* data */ 1, 2, 3,
+        ];
+
+        let result = test_reader2!(EntryRemoved::reader(), input);
+
+        assert!(result.is_err());
+        assert_eq!(DataLengthError::new().description(), result.unwrap_err().description());
+    }
+
+    #[test]
+    fn test_write() {
+        let msg = EntryRemoved::new(12, vec![1, 2, 3]);
+        let mut vec = Vec::<u8>::new();
+
+        assert!(msg.write_to(&mut vec).is_ok());
+
+        assert_eq!(
+            vec![
+                /* tag  */ 0, 0, 0, 0, 0, 0, 0, 12,
+                /* len  */ 0, 3,
+                /* data */ 1, 2, 3,
+            ],
+            vec
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use std::io;
+use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
+use super::super::{WriteTo, WriteResult, Reader, ReaderStatus, Message, MessageInner};
+use ReaderStatus::{Pending, Complete};
+
+/// ts: i64 | id: u64
+///
+/// Looks up a pending entry. The server replies with an [`EntryInfo`] message
+/// or an error if the entry doesn't exist.
+///
+/// `ts` is given in the [`Precision`] of the connection.
+///
+/// [`EntryInfo`]: struct.EntryInfo.html
+/// [`Precision`]: ../enum.Precision.html
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct GetEntry {
+    timestamp: i64,
+    id: u64,
+}
+
+#[derive(Debug)]
+enum ReaderState {
+    Timestamp,
+    Id(i64),
+}
+
+#[derive(Debug)]
+pub struct GetEntryReader {
+    state: ReaderState,
+}
+
+impl GetEntry {
+    pub fn new(timestamp: i64, id: u64) -> Self {
+        GetEntry { timestamp, id }
+    }
+
+    pub fn reader() -> GetEntryReader {
+        GetEntryReader { state: ReaderState::Timestamp }
+    }
+
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl MessageInner for GetEntry {
+    fn wrap(self) -> Message {
+        Message::GetEntry(self)
+    }
+}
+
+impl Reader<GetEntry> for GetEntryReader {
+    fn resume<I>(&mut self, input: &mut I) -> io::Result<ReaderStatus<GetEntry>> where I: io::Read {
+        let (state, status) = match self.state {
+            ReaderState::Timestamp => {
+                let timestamp = input.read_i64::<NetworkEndian>()?;
+
+                (ReaderState::Id(timestamp), Pending)
+            }
+            ReaderState::Id(timestamp) => {
+                let id = input.read_u64::<NetworkEndian>()?;
+
+                (ReaderState::Timestamp, Complete(GetEntry::new(timestamp, id)))
+            }
+        };
+
+        self.state = state;
+
+        Ok(status)
+    }
+
+    fn rewind(&mut self) {
+        self.state = ReaderState::Timestamp;
+    }
+}
+
+impl WriteTo for GetEntry {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        target.write_i64::<NetworkEndian>(self.timestamp)?;
+        target.write_u64::<NetworkEndian>(self.id)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::super::Message;
+    use super::super::super::WriteTo;
+
+    #[test]
+    fn test_write() {
+        let msg = Message::GetEntry(GetEntry::new(12345, 23));
+        let mut vec = Vec::<u8>::new();
+
+        assert!(msg.write_to(&mut vec).is_ok());
+
+        assert_eq!(
+            vec![
+                /* cmd */ 15,
+                /* ts  */ 0, 0, 0, 0, 0, 0, 48, 57,
+                /* id  */ 0, 0, 0, 0, 0, 0, 0, 23,
+            ],
+            vec
+        );
+    }
+
+    #[test]
+    fn test_reader() {
+        let input = vec![
+            /* ts  */ 0, 0, 0, 0, 0, 0, 48, 57,
+            /* id  */ 0, 0, 0, 0, 0, 0, 0, 23,
+        ];
+
+        let result = test_reader2!(GetEntry::reader(), input);
+
+        assert!(result.is_ok());
+        assert_eq!(GetEntry::new(12345, 23), result.unwrap());
+    }
+}
\ No newline at end of file
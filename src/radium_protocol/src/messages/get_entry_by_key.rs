@@ -0,0 +1,99 @@
+use std::io;
+use super::super::{WriteTo, WriteResult, Reader, ReaderStatus, Message, MessageInner};
+use super::{TaggedKey, TaggedKeyReader};
+
+/// key: [`TaggedKey`]
+///
+/// Looks up the pending entry with the given key and tag. The server replies with an [`EntryInfo`] message
+/// or an error if there is no such entry.
+///
+/// [`EntryInfo`]: struct.EntryInfo.html
+/// [`TaggedKey`]: struct.TaggedKey.html
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct GetEntryByKey {
+    key: TaggedKey,
+}
+
+#[derive(Debug)]
+pub struct GetEntryByKeyReader {
+    inner: TaggedKeyReader,
+}
+
+impl GetEntryByKey {
+    pub fn new(tag: u64, key: Vec<u8>) -> Self {
+        GetEntryByKey { key: TaggedKey::new(tag, key) }
+    }
+
+    pub fn tag(&self) -> u64 {
+        self.key.tag()
+    }
+
+    pub fn key(&self) -> &[u8] {
+        self.key.key()
+    }
+
+    pub fn reader() -> GetEntryByKeyReader {
+        GetEntryByKeyReader { inner: TaggedKey::reader() }
+    }
+}
+
+impl MessageInner for GetEntryByKey {
+    fn wrap(self) -> Message {
+        Message::GetEntryByKey(self)
+    }
+}
+
+impl Reader<GetEntryByKey> for GetEntryByKeyReader {
+    fn resume<I>(&mut self, input: &mut I) -> io::Result<ReaderStatus<GetEntryByKey>> where I: io::Read {
+        let status = self.inner.resume(input)?;
+
+        Ok(status.map(|key| GetEntryByKey { key }))
+    }
+
+    fn rewind(&mut self) {
+        self.inner.rewind();
+    }
+}
+
+impl WriteTo for GetEntryByKey {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        self.key.write_to(target)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::super::Message;
+
+    #[test]
+    fn test_write() {
+        let msg = Message::GetEntryByKey(GetEntryByKey::new(12, vec![107]));
+        let mut vec = Vec::<u8>::new();
+
+        assert!(msg.write_to(&mut vec).is_ok());
+
+        assert_eq!(
+            vec![
+                /* cmd */ 28,
+                /* tag */ 0, 0, 0, 0, 0, 0, 0, 12,
+                /* len */ 1,
+                /* key */ 107,
+            ],
+            vec
+        );
+    }
+
+    #[test]
+    fn test_reader() {
+        let input = vec![
+            /* tag */ 0, 0, 0, 0, 0, 0, 0, 12,
+            /* len */ 1,
+            /* key */ 107,
+        ];
+
+        let result = test_reader2!(GetEntryByKey::reader(), input);
+
+        assert_eq!(GetEntryByKey::new(12, vec![107]), result.unwrap());
+    }
+}
@@ -0,0 +1,139 @@
+use std::io;
+use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
+use super::super::{WriteTo, WriteResult, Reader, ReaderStatus, Message, MessageInner};
+use ReaderStatus::{Pending, Complete};
+
+/// from: i64 | to: i64 | limit: u32
+///
+/// Lists up to `limit` pending entries that expire at or after `from` and before `to`,
+/// ordered by their expiration. The server replies with an [`EntryList`] message.
+///
+/// `from` and `to` are given in the [`Precision`] of the connection.
+///
+/// [`EntryList`]: struct.EntryList.html
+/// [`Precision`]: ../enum.Precision.html
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ListEntries {
+    from: i64,
+    to: i64,
+    limit: u32,
+}
+
+#[derive(Debug)]
+enum ReaderState {
+    From,
+    To(i64),
+    Limit(i64, i64),
+}
+
+#[derive(Debug)]
+pub struct ListEntriesReader {
+    state: ReaderState,
+}
+
+impl ListEntries {
+    pub fn new(from: i64, to: i64, limit: u32) -> Self {
+        ListEntries { from, to, limit }
+    }
+
+    pub fn reader() -> ListEntriesReader {
+        ListEntriesReader { state: ReaderState::From }
+    }
+
+    pub fn from(&self) -> i64 {
+        self.from
+    }
+
+    pub fn to(&self) -> i64 {
+        self.to
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+}
+
+impl MessageInner for ListEntries {
+    fn wrap(self) -> Message {
+        Message::ListEntries(self)
+    }
+}
+
+impl Reader<ListEntries> for ListEntriesReader {
+    fn resume<I>(&mut self, input: &mut I) -> io::Result<ReaderStatus<ListEntries>> where I: io::Read {
+        let (state, status) = match self.state {
+            ReaderState::From => {
+                let from = input.read_i64::<NetworkEndian>()?;
+
+                (ReaderState::To(from), Pending)
+            }
+            ReaderState::To(from) => {
+                let to = input.read_i64::<NetworkEndian>()?;
+
+                (ReaderState::Limit(from, to), Pending)
+            }
+            ReaderState::Limit(from, to) => {
+                let limit = input.read_u32::<NetworkEndian>()?;
+
+                (ReaderState::From, Complete(ListEntries::new(from, to, limit)))
+            }
+        };
+
+        self.state = state;
+
+        Ok(status)
+    }
+
+    fn rewind(&mut self) {
+        self.state = ReaderState::From;
+    }
+}
+
+impl WriteTo for ListEntries {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        target.write_i64::<NetworkEndian>(self.from)?;
+        target.write_i64::<NetworkEndian>(self.to)?;
+        target.write_u32::<NetworkEndian>(self.limit)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::super::Message;
+    use super::super::super::WriteTo;
+
+    #[test]
+    fn test_write() {
+        let msg = Message::ListEntries(ListEntries::new(10, 12345, 100));
+        let mut vec = Vec::<u8>::new();
+
+        assert!(msg.write_to(&mut vec).is_ok());
+
+        assert_eq!(
+            vec![
+                /* cmd   */ 16,
+                /* from  */ 0, 0, 0, 0, 0, 0, 0, 10,
+                /* to    */ 0, 0, 0, 0, 0, 0, 48, 57,
+                /* limit */ 0, 0, 0, 100,
+            ],
+            vec
+        );
+    }
+
+    #[test]
+    fn test_reader() {
+        let input = vec![
+            /* from  */ 0, 0, 0, 0, 0, 0, 0, 10,
+            /* to    */ 0, 0, 0, 0, 0, 0, 48, 57,
+            /* limit */ 0, 0, 0, 100,
+        ];
+
+        let result = test_reader2!(ListEntries::reader(), input);
+
+        assert!(result.is_ok());
+        assert_eq!(ListEntries::new(10, 12345, 100), result.unwrap());
+    }
+}
@@ -1,13 +1,49 @@
 mod add_entry;
+mod add_recurring_entry;
+mod add_cron_entry;
 mod entry_expired;
 mod remove_entry;
+mod reschedule_entry;
+mod entry_removed;
 mod entry_added;
 mod set_watch_mode;
+mod set_precision;
+mod get_entry;
+mod list_entries;
+mod entry_info;
+mod entry_list;
+mod entry_count;
+mod batch;
+mod ack_expired;
+mod owner_disconnected;
+mod tagged_key;
+mod get_entry_by_key;
+mod remove_entry_by_key;
+mod add_ephemeral_entry;
+mod upsert_entry;
 mod error;
 
 pub use self::add_entry::*;
+pub use self::add_recurring_entry::*;
+pub use self::add_cron_entry::*;
 pub use self::entry_expired::*;
 pub use self::entry_added::*;
 pub use self::remove_entry::*;
+pub use self::reschedule_entry::*;
+pub use self::entry_removed::*;
 pub use self::set_watch_mode::*;
+pub use self::set_precision::*;
+pub use self::get_entry::*;
+pub use self::list_entries::*;
+pub use self::entry_info::*;
+pub use self::entry_list::*;
+pub use self::entry_count::*;
+pub use self::batch::*;
+pub use self::ack_expired::*;
+pub use self::owner_disconnected::*;
+pub use self::tagged_key::*;
+pub use self::get_entry_by_key::*;
+pub use self::remove_entry_by_key::*;
+pub use self::add_ephemeral_entry::*;
+pub use self::upsert_entry::*;
 pub use self::error::*;
\ No newline at end of file
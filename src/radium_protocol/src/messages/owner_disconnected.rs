@@ -0,0 +1,104 @@
+use std::io;
+use super::super::{WriteTo, WriteResult, Reader, ReaderStatus, Message, MessageInner};
+use super::{EntryInfo, EntryInfoReader};
+
+/// entry: [`EntryInfo`]
+///
+/// Sent to the watchers of an entry added with [`AddEphemeralEntry`], which was removed because the connection that added it was closed.
+/// All members of a group that receives the tag of the entry are notified.
+///
+/// [`AddEphemeralEntry`]: struct.AddEphemeralEntry.html
+/// [`EntryInfo`]: struct.EntryInfo.html
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct OwnerDisconnected {
+    entry: EntryInfo,
+}
+
+#[derive(Debug)]
+pub struct OwnerDisconnectedReader {
+    inner: EntryInfoReader,
+}
+
+impl OwnerDisconnected {
+    pub fn new(entry: EntryInfo) -> Self {
+        OwnerDisconnected { entry }
+    }
+
+    pub fn entry(&self) -> &EntryInfo {
+        &self.entry
+    }
+
+    pub fn consume_entry(self) -> EntryInfo {
+        self.entry
+    }
+
+    pub fn reader() -> OwnerDisconnectedReader {
+        OwnerDisconnectedReader { inner: EntryInfo::reader() }
+    }
+}
+
+impl MessageInner for OwnerDisconnected {
+    fn wrap(self) -> Message {
+        Message::OwnerDisconnected(self)
+    }
+}
+
+impl Reader<OwnerDisconnected> for OwnerDisconnectedReader {
+    fn resume<I>(&mut self, input: &mut I) -> io::Result<ReaderStatus<OwnerDisconnected>> where I: io::Read {
+        let status = self.inner.resume(input)?;
+
+        Ok(status.map(|entry| OwnerDisconnected::new(entry)))
+    }
+
+    fn rewind(&mut self) {
+        self.inner.rewind();
+    }
+}
+
+impl WriteTo for OwnerDisconnected {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        self.entry.write_to(target)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::super::Message;
+
+    #[test]
+    fn test_write() {
+        let msg = Message::OwnerDisconnected(OwnerDisconnected::new(EntryInfo::new(10, 7, 12, vec![1])));
+        let mut vec = Vec::<u8>::new();
+
+        assert!(msg.write_to(&mut vec).is_ok());
+
+        assert_eq!(
+            vec![
+                /* cmd  */ 26,
+                /* ts   */ 0, 0, 0, 0, 0, 0, 0, 10,
+                /* id   */ 0, 0, 0, 0, 0, 0, 0, 7,
+                /* tag  */ 0, 0, 0, 0, 0, 0, 0, 12,
+                /* len  */ 0, 1,
+                /* data */ 1,
+            ],
+            vec
+        );
+    }
+
+    #[test]
+    fn test_reader() {
+        let input = vec![
+            /* ts   */ 0, 0, 0, 0, 0, 0, 0, 10,
+            /* id   */ 0, 0, 0, 0, 0, 0, 0, 7,
+            /* tag  */ 0, 0, 0, 0, 0, 0, 0, 12,
+            /* len  */ 0, 1,
+            /* data */ 1,
+        ];
+
+        let result = test_reader2!(OwnerDisconnected::reader(), input);
+
+        assert!(result.is_ok());
+        assert_eq!(OwnerDisconnected::new(EntryInfo::new(10, 7, 12, vec![1])), result.unwrap());
+    }
+}
@@ -3,11 +3,15 @@ use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
 use super::super::{WriteTo, WriteResult, Reader, ReaderStatus, Message, MessageInner};
 use ReaderStatus::{Pending, Complete};
 
-/// ts: i64 | id: u16
+/// ts: i64 | id: u64
+///
+/// `ts` is given in the [`Precision`] of the connection.
+///
+/// [`Precision`]: ../enum.Precision.html
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct RemoveEntry {
     timestamp: i64,
-    id: u16,
+    id: u64,
 }
 
 #[derive(Debug)]
@@ -22,7 +26,7 @@ pub struct RemoveEntryReader {
 }
 
 impl RemoveEntry {
-    pub fn new(timestamp: i64, id: u16) -> Self {
+    pub fn new(timestamp: i64, id: u64) -> Self {
         RemoveEntry { timestamp, id }
     }
 
@@ -34,7 +38,7 @@ impl RemoveEntry {
         self.timestamp
     }
 
-    pub fn id(&self) -> u16 {
+    pub fn id(&self) -> u64 {
         self.id
     }
 }
@@ -54,7 +58,7 @@ impl Reader<RemoveEntry> for RemoveEntryReader {
                 (ReaderState::Id(timestamp), Pending)
             }
             ReaderState::Id(timestamp) => {
-                let id = input.read_u16::<NetworkEndian>()?;
+                let id = input.read_u64::<NetworkEndian>()?;
 
                 (ReaderState::Timestamp, Complete(RemoveEntry::new(timestamp, id)))
             }
@@ -73,7 +77,7 @@ impl Reader<RemoveEntry> for RemoveEntryReader {
 impl WriteTo for RemoveEntry {
     fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
         target.write_i64::<NetworkEndian>(self.timestamp)?;
-        target.write_u16::<NetworkEndian>(self.id)?;
+        target.write_u64::<NetworkEndian>(self.id)?;
 
         Ok(())
     }
@@ -96,7 +100,7 @@ mod test {
             vec![
                 /* cmd */ 4,
                 /* ts  */ 0, 0, 0, 0, 0, 0, 48, 57,
-                /* id  */ 0, 23,
+                /* id  */ 0, 0, 0, 0, 0, 0, 0, 23,
             ],
             vec
         );
@@ -106,7 +110,7 @@ mod test {
     fn test_reader() {
         let input = vec![
             /* ts  */ 0, 0, 0, 0, 0, 0, 48, 57,
-            /* id  */ 0, 23,
+            /* id  */ 0, 0, 0, 0, 0, 0, 0, 23,
         ];
 
         let result = test_reader2!(RemoveEntry::reader(), input);
@@ -0,0 +1,99 @@
+use std::io;
+use super::super::{WriteTo, WriteResult, Reader, ReaderStatus, Message, MessageInner};
+use super::{TaggedKey, TaggedKeyReader};
+
+/// key: [`TaggedKey`]
+///
+/// Removes the pending entry with the given key and tag. The server replies with an [`EntryRemoved`] message
+/// or an error if there is no such entry.
+///
+/// [`EntryRemoved`]: struct.EntryRemoved.html
+/// [`TaggedKey`]: struct.TaggedKey.html
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct RemoveEntryByKey {
+    key: TaggedKey,
+}
+
+#[derive(Debug)]
+pub struct RemoveEntryByKeyReader {
+    inner: TaggedKeyReader,
+}
+
+impl RemoveEntryByKey {
+    pub fn new(tag: u64, key: Vec<u8>) -> Self {
+        RemoveEntryByKey { key: TaggedKey::new(tag, key) }
+    }
+
+    pub fn tag(&self) -> u64 {
+        self.key.tag()
+    }
+
+    pub fn key(&self) -> &[u8] {
+        self.key.key()
+    }
+
+    pub fn reader() -> RemoveEntryByKeyReader {
+        RemoveEntryByKeyReader { inner: TaggedKey::reader() }
+    }
+}
+
+impl MessageInner for RemoveEntryByKey {
+    fn wrap(self) -> Message {
+        Message::RemoveEntryByKey(self)
+    }
+}
+
+impl Reader<RemoveEntryByKey> for RemoveEntryByKeyReader {
+    fn resume<I>(&mut self, input: &mut I) -> io::Result<ReaderStatus<RemoveEntryByKey>> where I: io::Read {
+        let status = self.inner.resume(input)?;
+
+        Ok(status.map(|key| RemoveEntryByKey { key }))
+    }
+
+    fn rewind(&mut self) {
+        self.inner.rewind();
+    }
+}
+
+impl WriteTo for RemoveEntryByKey {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        self.key.write_to(target)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::super::Message;
+
+    #[test]
+    fn test_write() {
+        let msg = Message::RemoveEntryByKey(RemoveEntryByKey::new(12, vec![107]));
+        let mut vec = Vec::<u8>::new();
+
+        assert!(msg.write_to(&mut vec).is_ok());
+
+        assert_eq!(
+            vec![
+                /* cmd */ 29,
+                /* tag */ 0, 0, 0, 0, 0, 0, 0, 12,
+                /* len */ 1,
+                /* key */ 107,
+            ],
+            vec
+        );
+    }
+
+    #[test]
+    fn test_reader() {
+        let input = vec![
+            /* tag */ 0, 0, 0, 0, 0, 0, 0, 12,
+            /* len */ 1,
+            /* key */ 107,
+        ];
+
+        let result = test_reader2!(RemoveEntryByKey::reader(), input);
+
+        assert_eq!(RemoveEntryByKey::new(12, vec![107]), result.unwrap());
+    }
+}
@@ -0,0 +1,139 @@
+use std::io;
+use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
+use super::super::{WriteTo, WriteResult, Reader, ReaderStatus, Message, MessageInner};
+use ReaderStatus::{Pending, Complete};
+
+/// ts: i64 | id: u64 | new_ts: i64
+///
+/// Moves the entry identified by `ts` and `id` to `new_ts`, keeping its tag and data.
+/// The server replies with an [`EntryAdded`] message that carries the new id of the entry.
+///
+/// `ts` and `new_ts` are given in the [`Precision`] of the connection.
+///
+/// [`EntryAdded`]: struct.EntryAdded.html
+/// [`Precision`]: ../enum.Precision.html
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct RescheduleEntry {
+    timestamp: i64,
+    id: u64,
+    new_timestamp: i64,
+}
+
+#[derive(Debug)]
+enum ReaderState {
+    Timestamp,
+    Id(i64),
+    NewTimestamp(i64, u64),
+}
+
+#[derive(Debug)]
+pub struct RescheduleEntryReader {
+    state: ReaderState,
+}
+
+impl RescheduleEntry {
+    pub fn new(timestamp: i64, id: u64, new_timestamp: i64) -> Self {
+        RescheduleEntry { timestamp, id, new_timestamp }
+    }
+
+    pub fn reader() -> RescheduleEntryReader {
+        RescheduleEntryReader { state: ReaderState::Timestamp }
+    }
+
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn new_timestamp(&self) -> i64 {
+        self.new_timestamp
+    }
+}
+
+impl MessageInner for RescheduleEntry {
+    fn wrap(self) -> Message {
+        Message::RescheduleEntry(self)
+    }
+}
+
+impl Reader<RescheduleEntry> for RescheduleEntryReader {
+    fn resume<I>(&mut self, input: &mut I) -> io::Result<ReaderStatus<RescheduleEntry>> where I: io::Read {
+        let (state, status) = match self.state {
+            ReaderState::Timestamp => {
+                let timestamp = input.read_i64::<NetworkEndian>()?;
+
+                (ReaderState::Id(timestamp), Pending)
+            }
+            ReaderState::Id(timestamp) => {
+                let id = input.read_u64::<NetworkEndian>()?;
+
+                (ReaderState::NewTimestamp(timestamp, id), Pending)
+            }
+            ReaderState::NewTimestamp(timestamp, id) => {
+                let new_timestamp = input.read_i64::<NetworkEndian>()?;
+
+                (ReaderState::Timestamp, Complete(RescheduleEntry::new(timestamp, id, new_timestamp)))
+            }
+        };
+
+        self.state = state;
+
+        Ok(status)
+    }
+
+    fn rewind(&mut self) {
+        self.state = ReaderState::Timestamp;
+    }
+}
+
+impl WriteTo for RescheduleEntry {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        target.write_i64::<NetworkEndian>(self.timestamp)?;
+        target.write_u64::<NetworkEndian>(self.id)?;
+        target.write_i64::<NetworkEndian>(self.new_timestamp)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::super::Message;
+    use super::super::super::WriteTo;
+
+    #[test]
+    fn test_write() {
+        let msg = Message::RescheduleEntry(RescheduleEntry::new(12345, 23, 12375));
+        let mut vec = Vec::<u8>::new();
+
+        assert!(msg.write_to(&mut vec).is_ok());
+
+        assert_eq!(
+            vec![
+                /* cmd    */ 12,
+                /* ts     */ 0, 0, 0, 0, 0, 0, 48, 57,
+                /* id     */ 0, 0, 0, 0, 0, 0, 0, 23,
+                /* new_ts */ 0, 0, 0, 0, 0, 0, 48, 87,
+            ],
+            vec
+        );
+    }
+
+    #[test]
+    fn test_reader() {
+        let input = vec![
+            /* ts     */ 0, 0, 0, 0, 0, 0, 48, 57,
+            /* id     */ 0, 0, 0, 0, 0, 0, 0, 23,
+            /* new_ts */ 0, 0, 0, 0, 0, 0, 48, 87,
+        ];
+
+        let result = test_reader2!(RescheduleEntry::reader(), input);
+
+        assert!(result.is_ok());
+        assert_eq!(RescheduleEntry::new(12345, 23, 12375), result.unwrap());
+    }
+}
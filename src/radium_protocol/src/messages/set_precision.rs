@@ -0,0 +1,73 @@
+use std::io;
+use super::super::{WriteTo, Precision, PrecisionReader, WriteResult, Reader, ReaderStatus, MessageInner, Message};
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SetPrecision {
+    precision: Precision,
+}
+
+#[derive(Debug)]
+pub struct SetPrecisionReader {
+    inner: PrecisionReader,
+}
+
+impl SetPrecision {
+    pub fn new(precision: Precision) -> Self {
+        SetPrecision { precision }
+    }
+
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    pub fn reader() -> SetPrecisionReader {
+        SetPrecisionReader { inner: Precision::reader() }
+    }
+}
+
+impl MessageInner for SetPrecision {
+    #[inline]
+    fn wrap(self) -> Message {
+        Message::SetPrecision(self)
+    }
+}
+
+impl Reader<SetPrecision> for SetPrecisionReader {
+    fn resume<R>(&mut self, input: &mut R) -> io::Result<ReaderStatus<SetPrecision>> where R: io::Read {
+        let status = self.inner.resume(input)?;
+
+        Ok(status.map(|precision| SetPrecision::new(precision)))
+    }
+
+    fn rewind(&mut self) {
+        self.inner.rewind();
+    }
+}
+
+impl WriteTo for SetPrecision {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        self.precision.write_to(target)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::super::{MessageType, Precision};
+
+    #[test]
+    fn test_reader() {
+        let input = vec![
+            /* type           */ MessageType::SetPrecision.into(),
+            /* precision = ms */ 1
+        ];
+
+        test_reader! {
+            Message::reader(),
+            input,
+            ReaderStatus::Pending,
+            ReaderStatus::Pending,
+            ReaderStatus::Complete(Message::SetPrecision(SetPrecision::new(Precision::Milliseconds)))
+        };
+    }
+}
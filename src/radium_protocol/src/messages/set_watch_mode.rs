@@ -16,7 +16,11 @@ impl SetWatchMode {
         SetWatchMode { mode }
     }
 
-    pub fn mode(&self) -> WatchMode {
+    pub fn mode(&self) -> &WatchMode {
+        &self.mode
+    }
+
+    pub fn consume_mode(self) -> WatchMode {
         self.mode
     }
 
@@ -73,6 +77,26 @@ mod test {
         };
     }
 
+    #[test]
+    fn test_reader_with_group() {
+        let input = vec![
+            /* type         */ MessageType::SetWatchMode.into(),
+            /* mode = group */ 3,
+            /* len          */ 4,
+            /* name         */ 106, 111, 98, 115,
+        ];
+
+        test_reader! {
+            Message::reader(),
+            input,
+            ReaderStatus::Pending,
+            ReaderStatus::Pending,
+            ReaderStatus::Pending,
+            ReaderStatus::Pending,
+            ReaderStatus::Complete(Message::SetWatchMode(SetWatchMode::new(WatchMode::Group("jobs".to_string()))))
+        };
+    }
+
     #[test]
     fn test_reader() {
         let input = vec![
@@ -0,0 +1,134 @@
+use std::io;
+use std::io::Read;
+use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
+use super::super::{WriteTo, WriteResult, Reader, ReaderStatus};
+use super::super::errors::{WriteError, DataLengthError};
+use ReaderStatus::{Pending, Complete};
+
+/// tag: u64 | len: u8 | key: len bytes
+///
+/// Addresses the entry that has been added with the given key and tag, see [`UpsertEntry`].
+///
+/// [`UpsertEntry`]: struct.UpsertEntry.html
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TaggedKey {
+    tag: u64,
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+enum ReaderState {
+    Tag,
+    Length(u64),
+    Key(u64, u64),
+}
+
+#[derive(Debug)]
+pub struct TaggedKeyReader {
+    state: ReaderState,
+}
+
+impl TaggedKey {
+    pub fn new(tag: u64, key: Vec<u8>) -> Self {
+        TaggedKey { tag, key }
+    }
+
+    pub fn reader() -> TaggedKeyReader {
+        TaggedKeyReader { state: ReaderState::Tag }
+    }
+
+    pub fn tag(&self) -> u64 {
+        self.tag
+    }
+
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+}
+
+impl Reader<TaggedKey> for TaggedKeyReader {
+    fn resume<I>(&mut self, input: &mut I) -> io::Result<ReaderStatus<TaggedKey>> where I: io::Read {
+        let (state, status) = match self.state {
+            ReaderState::Tag => {
+                let tag = input.read_u64::<NetworkEndian>()?;
+
+                (ReaderState::Length(tag), Pending)
+            }
+            ReaderState::Length(tag) => {
+                let length = input.read_u8()? as u64;
+
+                (ReaderState::Key(tag, length), Pending)
+            }
+            ReaderState::Key(tag, length) => {
+                let mut key = Vec::new();
+                let bytes_read = input.take(length).read_to_end(&mut key)?;
+
+                if (bytes_read as u64) < length {
+                    return Err(DataLengthError::new());
+                }
+
+                (ReaderState::Tag, Complete(TaggedKey::new(tag, key)))
+            }
+        };
+
+        self.state = state;
+
+        Ok(status)
+    }
+
+    fn rewind(&mut self) {
+        self.state = ReaderState::Tag;
+    }
+}
+
+impl WriteTo for TaggedKey {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        if self.key.len() > u8::max_value() as usize {
+            return Err(WriteError::DataLengthOverflow);
+        }
+
+        target.write_u64::<NetworkEndian>(self.tag)?;
+        target.write_u8(self.key.len() as u8)?;
+        target.write_all(&self.key)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reader() {
+        let input = vec![
+            /* tag */ 0, 0, 0, 0, 0, 0, 0, 12,
+            /* len */ 2,
+            /* key */ 107, 49,
+        ];
+
+        let result = test_reader2!(TaggedKey::reader(), input);
+
+        assert_eq!(TaggedKey::new(12, b"k1".to_vec()), result.unwrap());
+    }
+
+    #[test]
+    fn test_fails_on_key_eof() {
+        let input = vec![
+            /* tag */ 0, 0, 0, 0, 0, 0, 0, 12,
+            /* len */ 3,
+            /* key */ 107,
+        ];
+
+        let result = test_reader2!(TaggedKey::reader(), input);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_write_checks_size() {
+        let mut target = Vec::<u8>::new();
+
+        assert!(TaggedKey::new(0, vec![0; 256]).write_to(&mut target).is_err());
+    }
+}
@@ -0,0 +1,236 @@
+use std::io;
+use std::io::Read;
+use std::mem;
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use super::super::{WriteTo, WriteResult, Reader, ReaderStatus, Message, MessageInner, ConflictPolicy, ConflictPolicyReader};
+use super::super::errors::{WriteError, DataLengthError, InvalidValueError};
+use super::{AddEntry, AddEntryReader};
+use ReaderStatus::{Pending, Complete};
+
+/// len: u8 | key: len bytes | on_conflict: [`ConflictPolicy`] | ephemeral: u8 | entry: [`AddEntry`]
+///
+/// Adds an entry with a key, which is unique per tag, so that the entry can be looked up
+/// and removed by its key. If an entry with the same key and tag is already pending,
+/// `on_conflict` decides which one is kept. The server replies with [`EntryAdded`] for the entry that is kept.
+///
+/// An ephemeral entry (`ephemeral` is 1) is removed when the connection that added it is closed,
+/// see [`AddEphemeralEntry`]. The key must not be empty.
+///
+/// [`ConflictPolicy`]: ../enum.ConflictPolicy.html
+/// [`AddEntry`]: struct.AddEntry.html
+/// [`EntryAdded`]: struct.EntryAdded.html
+/// [`AddEphemeralEntry`]: struct.AddEphemeralEntry.html
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct UpsertEntry {
+    key: Vec<u8>,
+    on_conflict: ConflictPolicy,
+    ephemeral: bool,
+    entry: AddEntry,
+}
+
+#[derive(Debug)]
+enum ReaderState {
+    Length,
+    Key(u64),
+    Conflict,
+    Ephemeral(ConflictPolicy),
+    Entry(ConflictPolicy, bool),
+}
+
+#[derive(Debug)]
+pub struct UpsertEntryReader {
+    state: ReaderState,
+    key: Vec<u8>,
+    on_conflict: ConflictPolicyReader,
+    entry: AddEntryReader,
+}
+
+impl UpsertEntry {
+    pub fn new(key: Vec<u8>, on_conflict: ConflictPolicy, entry: AddEntry) -> Self {
+        UpsertEntry {
+            key,
+            on_conflict,
+            ephemeral: false,
+            entry,
+        }
+    }
+
+    pub fn with_ephemeral(mut self, ephemeral: bool) -> Self {
+        self.ephemeral = ephemeral;
+        self
+    }
+
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    pub fn conflict_policy(&self) -> ConflictPolicy {
+        self.on_conflict
+    }
+
+    pub fn is_ephemeral(&self) -> bool {
+        self.ephemeral
+    }
+
+    pub fn entry(&self) -> &AddEntry {
+        &self.entry
+    }
+
+    pub fn consume_entry(self) -> AddEntry {
+        self.entry
+    }
+
+    pub fn reader() -> UpsertEntryReader {
+        UpsertEntryReader {
+            state: ReaderState::Length,
+            key: Vec::new(),
+            on_conflict: ConflictPolicy::reader(),
+            entry: AddEntry::reader(),
+        }
+    }
+}
+
+impl MessageInner for UpsertEntry {
+    fn wrap(self) -> Message {
+        Message::UpsertEntry(self)
+    }
+}
+
+impl Reader<UpsertEntry> for UpsertEntryReader {
+    fn resume<I>(&mut self, input: &mut I) -> io::Result<ReaderStatus<UpsertEntry>> where I: io::Read {
+        let (state, status) = match self.state {
+            ReaderState::Length => {
+                match input.read_u8()? as u64 {
+                    0 => return Err(InvalidValueError::new()),
+                    length => (ReaderState::Key(length), Pending),
+                }
+            }
+            ReaderState::Key(length) => {
+                let mut buf = Vec::new();
+                let bytes_read = input.take(length).read_to_end(&mut buf)?;
+
+                if (bytes_read as u64) < length {
+                    return Err(DataLengthError::new());
+                }
+
+                self.key = buf;
+
+                (ReaderState::Conflict, Pending)
+            }
+            ReaderState::Conflict => {
+                match self.on_conflict.resume(input)? {
+                    Pending => return Ok(Pending),
+                    Complete(on_conflict) => (ReaderState::Ephemeral(on_conflict), Pending),
+                }
+            }
+            ReaderState::Ephemeral(on_conflict) => {
+                let ephemeral = match input.read_u8()? {
+                    0 => false,
+                    1 => true,
+                    _ => return Err(InvalidValueError::new()),
+                };
+
+                (ReaderState::Entry(on_conflict, ephemeral), Pending)
+            }
+            ReaderState::Entry(on_conflict, ephemeral) => {
+                match self.entry.resume(input)? {
+                    Pending => return Ok(Pending),
+                    Complete(entry) => {
+                        let key = mem::replace(&mut self.key, Vec::new());
+
+                        (ReaderState::Length, Complete(UpsertEntry::new(key, on_conflict, entry).with_ephemeral(ephemeral)))
+                    }
+                }
+            }
+        };
+
+        self.state = state;
+
+        Ok(status)
+    }
+
+    fn rewind(&mut self) {
+        self.state = ReaderState::Length;
+        self.key.clear();
+        self.on_conflict.rewind();
+        self.entry.rewind();
+    }
+}
+
+impl WriteTo for UpsertEntry {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        if self.key.is_empty() || self.key.len() > u8::max_value() as usize {
+            return Err(WriteError::DataLengthOverflow);
+        }
+
+        target.write_u8(self.key.len() as u8)?;
+        target.write_all(&self.key)?;
+        self.on_conflict.write_to(target)?;
+        target.write_u8(self.ephemeral as u8)?;
+
+        self.entry.write_to(target)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::super::{Message, MessageType};
+
+    fn input() -> Vec<u8> {
+        vec![
+            /* klen */ 2,
+            /* key  */ 107, 49,
+            /* conf */ 2,
+            /* eph  */ 1,
+            /* ts   */ 0, 0, 0, 0, 0, 0, 0, 10,
+            /* tag  */ 0, 0, 0, 0, 0, 0, 0, 42,
+            /* len  */ 0, 1,
+            /* data */ 7,
+        ]
+    }
+
+    fn upsert() -> UpsertEntry {
+        UpsertEntry::new(b"k1".to_vec(), ConflictPolicy::KeepEarliest, AddEntry::new(10, 42, vec![7])).with_ephemeral(true)
+    }
+
+    #[test]
+    fn test_write() {
+        let mut vec = Vec::<u8>::new();
+
+        assert!(upsert().write_to(&mut vec).is_ok());
+        assert_eq!(input(), vec);
+    }
+
+    #[test]
+    fn test_reader() {
+        let mut input = input();
+        input.insert(0, MessageType::UpsertEntry.into());
+
+        let result = test_reader2!(Message::reader(), input);
+
+        assert_eq!(Message::UpsertEntry(upsert()), result.unwrap());
+    }
+
+    #[test]
+    fn test_reader_empty_key() {
+        let result = test_reader2!(UpsertEntry::reader(), vec![0]);
+
+        assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+    }
+
+    #[test]
+    fn test_reader_invalid_ephemeral() {
+        let result = test_reader2!(UpsertEntry::reader(), vec![1, 107, 0, 2]);
+
+        assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+    }
+
+    #[test]
+    fn test_write_checks_key() {
+        let mut target = Vec::<u8>::new();
+
+        assert!(UpsertEntry::new(vec![], ConflictPolicy::Replace, AddEntry::new(0, 0, vec![])).write_to(&mut target).is_err());
+        assert!(UpsertEntry::new(vec![0; 256], ConflictPolicy::Replace, AddEntry::new(0, 0, vec![])).write_to(&mut target).is_err());
+    }
+}
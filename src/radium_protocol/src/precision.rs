@@ -0,0 +1,100 @@
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use std::io;
+use super::errors::InvalidValueError;
+use super::{WriteTo, WriteResult, Reader, ReaderStatus};
+
+const MILLIS_PER_SEC: i64 = 1_000;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default)]
+/// The `Precision` determines the unit of all timestamps a client sends and receives.
+///
+/// Connections start out with `Precision::Seconds`, so clients that are not aware
+/// of millisecond timestamps keep working unchanged.
+pub enum Precision {
+    /// Timestamps are unix timestamps in seconds
+    #[default]
+    Seconds,
+    /// Timestamps are unix timestamps in milliseconds
+    Milliseconds,
+}
+
+#[derive(Debug)]
+pub struct PrecisionReader;
+
+impl Precision {
+    /// Converts a timestamp given in this precision to milliseconds.
+    /// Returns `None` if the timestamp is out of range in milliseconds.
+    pub fn to_millis(&self, timestamp: i64) -> Option<i64> {
+        match self {
+            &Precision::Seconds => timestamp.checked_mul(MILLIS_PER_SEC),
+            &Precision::Milliseconds => Some(timestamp),
+        }
+    }
+
+    /// Converts a timestamp in milliseconds to this precision.
+    /// Timestamps are rounded towards negative infinity when converting to seconds.
+    pub fn from_millis(&self, millis: i64) -> i64 {
+        match self {
+            &Precision::Seconds => millis.div_euclid(MILLIS_PER_SEC),
+            &Precision::Milliseconds => millis,
+        }
+    }
+
+    pub fn reader() -> PrecisionReader {
+        PrecisionReader {}
+    }
+}
+
+impl Reader<Precision> for PrecisionReader {
+    fn resume<R>(&mut self, input: &mut R) -> io::Result<ReaderStatus<Precision>> where R: io::Read {
+        match input.read_u8()? {
+            0 => Ok(ReaderStatus::Complete(Precision::Seconds)),
+            1 => Ok(ReaderStatus::Complete(Precision::Milliseconds)),
+            _ => Err(InvalidValueError::new()),
+        }
+    }
+
+    fn rewind(&mut self) {}
+}
+
+impl WriteTo for Precision {
+    fn write_to<W: io::Write>(&self, target: &mut W) -> WriteResult {
+        let value = match self {
+            &Precision::Seconds => 0,
+            &Precision::Milliseconds => 1,
+        };
+
+        target.write_u8(value)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write() {
+        let mut buf = vec![];
+        assert!(Precision::Milliseconds.write_to(&mut buf).is_ok());
+        assert_eq!(vec![1], buf);
+    }
+
+    #[test]
+    fn test_reader() {
+        let result = test_reader2!(Precision::reader(), vec![0]);
+
+        assert_eq!(Precision::Seconds, result.unwrap());
+    }
+
+    #[test]
+    fn test_conversion() {
+        assert_eq!(Some(12000), Precision::Seconds.to_millis(12));
+        assert_eq!(None, Precision::Seconds.to_millis(i64::max_value() / 1000 + 1));
+        assert_eq!(Some(i64::max_value()), Precision::Milliseconds.to_millis(i64::max_value()));
+        assert_eq!(12, Precision::Seconds.from_millis(12999));
+        assert_eq!(-1, Precision::Seconds.from_millis(-1));
+        assert_eq!(12999, Precision::Milliseconds.from_millis(12999));
+    }
+}
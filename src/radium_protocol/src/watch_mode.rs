@@ -1,9 +1,10 @@
 use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
 use std::io;
-use super::errors::InvalidValueError;
+use std::io::Read;
+use super::errors::{InvalidValueError, DataLengthError, WriteError};
 use super::{WriteTo, WriteResult, Reader, ReaderStatus};
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 /// The `WatchMode` indicates whether the client wants to be notified about
 /// expired entries or not.
 pub enum WatchMode {
@@ -12,13 +13,22 @@ pub enum WatchMode {
     /// The client will receive notifications for all tags
     All,
     /// The client will receive notifications only for one tag
-    Tagged(u64)
+    Tagged(u64),
+    /// The client joins the named group, whose members share the notifications for all tags.
+    /// Each expired entry is sent to only one member of the group.
+    Group(String),
+    /// The client joins the named group, whose members share the notifications for one tag.
+    /// A group for one tag is separate from a group of the same name for another or for all tags.
+    TaggedGroup(String, u64),
 }
 
 #[derive(Debug)]
 enum WatchModeReaderState {
     Mode,
-    Tag
+    Tag,
+    GroupTag,
+    GroupLength(Option<u64>),
+    Group(Option<u64>, u64),
 }
 
 #[derive(Debug)]
@@ -27,11 +37,34 @@ pub struct WatchModeReader {
 }
 
 impl WatchMode {
+    /// Determines if the client is notified about every entry with the given tag.
+    /// Members of a group are not, they receive the entries that the server assigns to them instead.
     pub fn matches_tag(&self, tag: u64) -> bool {
         match self {
             &WatchMode::None => false,
             &WatchMode::All => true,
             &WatchMode::Tagged(val) => val == tag,
+            &WatchMode::Group(..) => false,
+            &WatchMode::TaggedGroup(..) => false,
+        }
+    }
+
+    /// Determines if the client receives entries with the given tag at all,
+    /// either every one of them or, as a member of a group, those that are assigned to it
+    pub fn receives_tag(&self, tag: u64) -> bool {
+        match self {
+            &WatchMode::Group(..) => true,
+            &WatchMode::TaggedGroup(_, val) => val == tag,
+            mode => mode.matches_tag(tag),
+        }
+    }
+
+    /// Returns the name of the group the client joined and the tag the group is limited to, if any
+    pub fn group(&self) -> Option<(&str, Option<u64>)> {
+        match self {
+            &WatchMode::Group(ref name) => Some((name, None)),
+            &WatchMode::TaggedGroup(ref name, tag) => Some((name, Some(tag))),
+            _ => None,
         }
     }
 
@@ -50,6 +83,8 @@ impl Reader<WatchMode> for WatchModeReader {
                     0 => (WatchModeReaderState::Mode, ReaderStatus::Complete(WatchMode::None)),
                     1 => (WatchModeReaderState::Mode, ReaderStatus::Complete(WatchMode::All)),
                     2 => (WatchModeReaderState::Tag, ReaderStatus::Pending),
+                    3 => (WatchModeReaderState::GroupLength(None), ReaderStatus::Pending),
+                    4 => (WatchModeReaderState::GroupTag, ReaderStatus::Pending),
                     _ => { return Err(InvalidValueError::new()) }
                 }
             },
@@ -58,6 +93,33 @@ impl Reader<WatchMode> for WatchModeReader {
 
                 (WatchModeReaderState::Mode, ReaderStatus::Complete(WatchMode::Tagged(tag)))
             },
+            WatchModeReaderState::GroupTag => {
+                let tag = input.read_u64::<NetworkEndian>()?;
+
+                (WatchModeReaderState::GroupLength(Some(tag)), ReaderStatus::Pending)
+            },
+            WatchModeReaderState::GroupLength(tag) => {
+                let length = input.read_u8()? as u64;
+
+                (WatchModeReaderState::Group(tag, length), ReaderStatus::Pending)
+            },
+            WatchModeReaderState::Group(tag, length) => {
+                let mut buf = Vec::new();
+                let bytes_read = input.take(length).read_to_end(&mut buf)?;
+
+                if (bytes_read as u64) < length {
+                    return Err(DataLengthError::new());
+                }
+
+                let name = String::from_utf8(buf).map_err(|_| InvalidValueError::new())?;
+
+                let mode = match tag {
+                    Some(tag) => WatchMode::TaggedGroup(name, tag),
+                    None => WatchMode::Group(name),
+                };
+
+                (WatchModeReaderState::Mode, ReaderStatus::Complete(mode))
+            },
         };
 
         self.state = state;
@@ -76,12 +138,25 @@ impl WriteTo for WatchMode {
             &WatchMode::None => 0,
             &WatchMode::All => 1,
             &WatchMode::Tagged(..) => 2,
+            &WatchMode::Group(..) => 3,
+            &WatchMode::TaggedGroup(..) => 4,
         };
 
         target.write_u8(mode)?;
 
-        if let &WatchMode::Tagged(tag) = self {
-            target.write_u64::<NetworkEndian>(tag)?;
+        match self {
+            &WatchMode::Tagged(tag) => target.write_u64::<NetworkEndian>(tag)?,
+            &WatchMode::TaggedGroup(_, tag) => target.write_u64::<NetworkEndian>(tag)?,
+            _ => {}
+        }
+
+        if let Some((name, _)) = self.group() {
+            if name.len() > u8::max_value() as usize {
+                return Err(WriteError::DataLengthOverflow);
+            }
+
+            target.write_u8(name.len() as u8)?;
+            target.write_all(name.as_bytes())?;
         }
 
         Ok(())
@@ -106,4 +181,30 @@ mod test {
     test_watch_mode!(test_none, WatchMode::None, &mut [0]);
     test_watch_mode!(test_all, WatchMode::All, &mut [1]);
     test_watch_mode!(test_tagged, WatchMode::Tagged(42), &mut [2, 0, 0, 0, 0, 0, 0, 0, 42]);
+    test_watch_mode!(test_group, WatchMode::Group("jobs".to_string()), &mut [3, 4, 106, 111, 98, 115]);
+    test_watch_mode!(test_tagged_group, WatchMode::TaggedGroup("jobs".to_string(), 42), &mut [4, 0, 0, 0, 0, 0, 0, 0, 42, 4, 106, 111, 98, 115]);
+
+    #[test]
+    fn test_reader_tagged_group() {
+        let result = test_reader2!(WatchMode::reader(), vec![4, 0, 0, 0, 0, 0, 0, 0, 42, 4, 106, 111, 98, 115]);
+
+        assert_eq!(WatchMode::TaggedGroup("jobs".to_string(), 42), result.unwrap());
+    }
+
+    #[test]
+    fn test_receives_tag() {
+        assert!(WatchMode::All.receives_tag(1));
+        assert!(!WatchMode::Tagged(2).receives_tag(1));
+        assert!(WatchMode::Group("jobs".to_string()).receives_tag(1));
+        assert!(WatchMode::TaggedGroup("jobs".to_string(), 1).receives_tag(1));
+        assert!(!WatchMode::TaggedGroup("jobs".to_string(), 2).receives_tag(1));
+        assert!(!WatchMode::TaggedGroup("jobs".to_string(), 1).matches_tag(1));
+    }
+
+    #[test]
+    fn test_group_name_too_long() {
+        let mut buf = vec![];
+
+        assert!(WatchMode::Group("x".repeat(256)).write_to(&mut buf).is_err());
+    }
 }
\ No newline at end of file
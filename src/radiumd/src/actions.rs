@@ -1,16 +1,29 @@
 use std::error::Error;
 use std::fmt;
-use libradium::{Core, Entry, EntryId, CommandError};
-use radium_protocol::{Message, ErrorCode};
-use radium_protocol::messages::{SetWatchMode, AddEntry, EntryAdded, RemoveEntry, ErrorMessage};
+use std::time::Duration;
+use libradium::{Core, Entry, EntryId, EntryKey, KeyConflict, Upserted, Timestamp, CommandError, Recurrence, Schedule, CronExpression};
+use radium_protocol::{Message, ErrorCode, Precision, ConflictPolicy};
+use radium_protocol::messages::{SetWatchMode, SetPrecision, AddEntry, AddRecurringEntry, AddCronEntry, EntryAdded, RemoveEntry, EntryRemoved, RescheduleEntry, ErrorMessage};
+use radium_protocol::messages::{GetEntry, ListEntries, EntryInfo, EntryList, EntryCount};
+use radium_protocol::messages::{AddEntries, RemoveEntries, EntriesAdded, EntriesRemoved, AckExpired};
+use radium_protocol::messages::{GetEntryByKey, RemoveEntryByKey, AddEphemeralEntry, UpsertEntry};
 use super::connection::Connection;
 use super::entry::EntryData;
 
+/// Maximum number of entries returned for a single `ListEntries` message
+const MAX_LIST_ENTRIES: usize = 1000;
+
 #[derive(Debug)]
 pub enum ActionError {
     NotACommand,
     Unimplemented,
     FrontendError,
+    EntryNotFound,
+    StorageError,
+    InvalidArgument,
+    InvalidCronExpression,
+    StorageFull,
+    QueueFull,
 }
 
 pub type ActionResult = Result<Message, ActionError>;
@@ -20,8 +33,13 @@ pub trait Action {
 }
 
 impl From<CommandError> for ActionError {
-    fn from(_: CommandError) -> Self {
-        ActionError::FrontendError
+    fn from(err: CommandError) -> Self {
+        match err {
+            CommandError::StorageError(_) => ActionError::StorageError,
+            CommandError::StorageFull => ActionError::StorageFull,
+            CommandError::QueueFull => ActionError::QueueFull,
+            _ => ActionError::FrontendError,
+        }
     }
 }
 
@@ -31,6 +49,12 @@ impl Into<ErrorCode> for ActionError {
             ActionError::NotACommand => ErrorCode::InvalidAction,
             ActionError::Unimplemented => ErrorCode::ActionNotImplemented,
             ActionError::FrontendError => ErrorCode::ActionProcessingError,
+            ActionError::EntryNotFound => ErrorCode::EntryNotFound,
+            ActionError::StorageError => ErrorCode::ActionProcessingError,
+            ActionError::InvalidArgument => ErrorCode::InvalidArgument,
+            ActionError::InvalidCronExpression => ErrorCode::InvalidCronExpression,
+            ActionError::StorageFull => ErrorCode::StorageFull,
+            ActionError::QueueFull => ErrorCode::QueueFull,
         }
     }
 }
@@ -52,39 +76,325 @@ impl Error for ActionError {
         match self {
             &ActionError::NotACommand => "Action is not a command",
             &ActionError::Unimplemented => "Action is not implemented",
-            &ActionError::FrontendError => "Unable to communicate with frontend"
+            &ActionError::FrontendError => "Unable to communicate with frontend",
+            &ActionError::EntryNotFound => "Entry does not exist",
+            &ActionError::StorageError => "Unable to access storage",
+            &ActionError::InvalidArgument => "Argument is out of range",
+            &ActionError::InvalidCronExpression => "Cron expression is invalid",
+            &ActionError::StorageFull => "Storage is full",
+            &ActionError::QueueFull => "Server is busy",
         }
     }
 }
 
 impl Action for SetWatchMode {
     fn process(self, conn: &mut Connection, _: &mut Core<EntryData>) -> ActionResult {
-        conn.set_watch_mode(self.mode());
+        conn.set_watch_mode(self.consume_mode());
+        Ok(Message::Ok)
+    }
+}
+
+impl Action for SetPrecision {
+    fn process(self, conn: &mut Connection, _: &mut Core<EntryData>) -> ActionResult {
+        conn.set_precision(self.precision());
         Ok(Message::Ok)
     }
 }
 
 impl Action for AddEntry {
-    fn process(self, _: &mut Connection, frontend: &mut Core<EntryData>) -> ActionResult {
-        let id = EntryId::gen(self.timestamp());
+    fn process(self, conn: &mut Connection, frontend: &mut Core<EntryData>) -> ActionResult {
+        let precision = conn.precision();
+        let id = EntryId::gen(to_timestamp(precision, self.timestamp())?);
         let entry = Entry::new(id, EntryData::new(self.tag(), self.consume_data()));
 
         frontend.add_entry(entry)?;
 
-        Ok(Message::EntryAdded(EntryAdded::new(id.timestamp().sec, id.id())))
+        Ok(Message::EntryAdded(EntryAdded::new(precision.from_millis(id.timestamp().millis()), id.id())))
+    }
+}
+
+impl Action for AddEphemeralEntry {
+    fn process(self, conn: &mut Connection, frontend: &mut Core<EntryData>) -> ActionResult {
+        let precision = conn.precision();
+        let msg = self.consume_entry();
+        let id = EntryId::gen(to_timestamp(precision, msg.timestamp())?);
+        let entry = Entry::new(id, EntryData::new(msg.tag(), msg.consume_data()));
+
+        frontend.add_entry(entry)?;
+        conn.own(id);
+
+        Ok(Message::EntryAdded(EntryAdded::new(precision.from_millis(id.timestamp().millis()), id.id())))
+    }
+}
+
+impl Action for UpsertEntry {
+    fn process(self, conn: &mut Connection, frontend: &mut Core<EntryData>) -> ActionResult {
+        let precision = conn.precision();
+        let ephemeral = self.is_ephemeral();
+        let key = entry_key(self.entry().tag(), self.key());
+
+        let policy = match self.conflict_policy() {
+            ConflictPolicy::Replace => KeyConflict::Replace,
+            ConflictPolicy::KeepExisting => KeyConflict::KeepExisting,
+            ConflictPolicy::KeepEarliest => KeyConflict::KeepEarliest,
+        };
+
+        let msg = self.consume_entry();
+        let entry = Entry::new(EntryId::gen(to_timestamp(precision, msg.timestamp())?), EntryData::new(msg.tag(), msg.consume_data()));
+
+        let id = match frontend.upsert_entry(entry.with_key(key), policy)? {
+            Upserted::Added(id) => {
+                if ephemeral {
+                    conn.own(id);
+                }
+
+                id
+            }
+            // An existing entry that has been kept remains owned by its connection, if any
+            Upserted::Kept(id) => id,
+        };
+
+        Ok(Message::EntryAdded(EntryAdded::new(precision.from_millis(id.timestamp().millis()), id.id())))
+    }
+}
+
+impl Action for AddRecurringEntry {
+    fn process(self, conn: &mut Connection, frontend: &mut Core<EntryData>) -> ActionResult {
+        let precision = conn.precision();
+        let interval = precision.to_millis(self.interval() as i64).ok_or(ActionError::InvalidArgument)?;
+
+        if interval <= 0 {
+            return Err(ActionError::InvalidArgument);
+        }
+
+        let mut recurrence = Recurrence::new(Schedule::Interval(Duration::from_millis(interval as u64)));
+
+        if self.count() > 0 {
+            recurrence = recurrence.times(self.count());
+        }
+
+        if self.until() != 0 {
+            recurrence = recurrence.until(to_timestamp(precision, self.until())?);
+        }
+
+        let msg = self.consume_entry();
+        let id = EntryId::gen(to_timestamp(precision, msg.timestamp())?);
+        let entry = Entry::recurring(id, EntryData::new(msg.tag(), msg.consume_data()), recurrence);
+
+        frontend.add_entry(entry)?;
+
+        Ok(Message::EntryAdded(EntryAdded::new(precision.from_millis(id.timestamp().millis()), id.id())))
+    }
+}
+
+impl Action for AddCronEntry {
+    fn process(self, conn: &mut Connection, frontend: &mut Core<EntryData>) -> ActionResult {
+        let precision = conn.precision();
+        let expression = CronExpression::parse(self.expression()).map_err(|_| ActionError::InvalidCronExpression)?;
+
+        let mut recurrence = Recurrence::new(Schedule::Cron(expression.clone()));
+
+        if self.count() > 0 {
+            recurrence = recurrence.times(self.count());
+        }
+
+        if self.until() != 0 {
+            recurrence = recurrence.until(to_timestamp(precision, self.until())?);
+        }
+
+        let msg = self.consume_entry();
+        let start = to_timestamp(precision, msg.timestamp())?.max(Timestamp::now());
+
+        let timestamp = match expression.next_after(start) {
+            Some(timestamp) => timestamp,
+            None => return Err(ActionError::InvalidCronExpression),
+        };
+
+        let id = EntryId::gen(timestamp);
+        let entry = Entry::recurring(id, EntryData::new(msg.tag(), msg.consume_data()), recurrence);
+
+        frontend.add_entry(entry)?;
+
+        Ok(Message::EntryAdded(EntryAdded::new(precision.from_millis(id.timestamp().millis()), id.id())))
     }
 }
 
 impl Action for RemoveEntry {
-    fn process(self, _: &mut Connection, frontend: &mut Core<EntryData>) -> ActionResult {
-        let id = EntryId::new(self.timestamp(), self.id());
+    fn process(self, conn: &mut Connection, frontend: &mut Core<EntryData>) -> ActionResult {
+        let timestamp = to_timestamp(conn.precision(), self.timestamp())?;
+        let id = EntryId::new(timestamp, self.id());
 
-        frontend.remove_entry(id)?;
+        match frontend.remove_entry(id)? {
+            Some(entry) => {
+                conn.disown(id);
 
-        Ok(Message::Ok)
+                let data = entry.consume_data();
+
+                Ok(Message::EntryRemoved(EntryRemoved::new(data.tag(), data.consume_data())))
+            }
+            None => Err(ActionError::EntryNotFound),
+        }
+    }
+}
+
+impl Action for AddEntries {
+    fn process(self, conn: &mut Connection, frontend: &mut Core<EntryData>) -> ActionResult {
+        let precision = conn.precision();
+
+        let entries = self.consume_items()
+            .into_iter()
+            .map(|msg| {
+                let id = EntryId::gen(to_timestamp(precision, msg.timestamp())?);
+
+                Ok(Entry::new(id, EntryData::new(msg.tag(), msg.consume_data())))
+            })
+            .collect::<Result<Vec<_>, ActionError>>()?;
+
+        let ids: Vec<EntryId> = entries.iter().map(|entry| entry.id()).collect();
+
+        let results = frontend.add_entries(entries)?
+            .into_iter()
+            .zip(ids)
+            .map(|(result, id)| match result {
+                Ok(()) => Ok(EntryAdded::new(precision.from_millis(id.timestamp().millis()), id.id())),
+                Err(err) => Err(ActionError::from(err).into()),
+            })
+            .collect();
+
+        Ok(Message::EntriesAdded(EntriesAdded::new(results)))
+    }
+}
+
+impl Action for RemoveEntries {
+    fn process(self, conn: &mut Connection, frontend: &mut Core<EntryData>) -> ActionResult {
+        let precision = conn.precision();
+
+        let ids = self.consume_items()
+            .into_iter()
+            .map(|msg| Ok(EntryId::new(to_timestamp(precision, msg.timestamp())?, msg.id())))
+            .collect::<Result<Vec<_>, ActionError>>()?;
+
+        let results = frontend.remove_entries(ids)?
+            .into_iter()
+            .map(|entry| match entry {
+                Some(entry) => {
+                    conn.disown(entry.id());
+
+                    let data = entry.consume_data();
+
+                    Ok(EntryRemoved::new(data.tag(), data.consume_data()))
+                }
+                None => Err(ErrorCode::EntryNotFound),
+            })
+            .collect();
+
+        Ok(Message::EntriesRemoved(EntriesRemoved::new(results)))
     }
 }
 
+impl Action for RescheduleEntry {
+    fn process(self, conn: &mut Connection, frontend: &mut Core<EntryData>) -> ActionResult {
+        let precision = conn.precision();
+        let id = EntryId::new(to_timestamp(precision, self.timestamp())?, self.id());
+        let timestamp = to_timestamp(precision, self.new_timestamp())?;
+
+        match frontend.reschedule(id, timestamp)? {
+            Some(new_id) => {
+                // An ephemeral entry keeps its owner, also if it was rescheduled by another connection
+                conn.reschedule_owned(id, new_id);
+
+                Ok(Message::EntryAdded(EntryAdded::new(precision.from_millis(new_id.timestamp().millis()), new_id.id())))
+            }
+            None => Err(ActionError::EntryNotFound),
+        }
+    }
+}
+
+impl Action for GetEntry {
+    fn process(self, conn: &mut Connection, frontend: &mut Core<EntryData>) -> ActionResult {
+        let precision = conn.precision();
+        let id = EntryId::new(to_timestamp(precision, self.timestamp())?, self.id());
+
+        match frontend.get_entry(id)? {
+            Some(entry) => Ok(Message::EntryInfo(entry_info(precision, entry))),
+            None => Err(ActionError::EntryNotFound),
+        }
+    }
+}
+
+impl Action for ListEntries {
+    fn process(self, conn: &mut Connection, frontend: &mut Core<EntryData>) -> ActionResult {
+        let precision = conn.precision();
+        let from = to_timestamp(precision, self.from())?;
+        let to = to_timestamp(precision, self.to())?;
+        let limit = (self.limit() as usize).min(MAX_LIST_ENTRIES);
+
+        let entries = frontend.entries_between(from, to, limit)?
+            .into_iter()
+            .map(|entry| entry_info(precision, entry))
+            .collect();
+
+        Ok(Message::EntryList(EntryList::new(entries)))
+    }
+}
+
+impl Action for AckExpired {
+    fn process(self, conn: &mut Connection, _: &mut Core<EntryData>) -> ActionResult {
+        match conn.deliveries() {
+            Some(deliveries) if deliveries.ack(self.id(), self.timestamp(), conn.precision()) => Ok(Message::Ok),
+            Some(_) => Err(ActionError::EntryNotFound),
+            // Expired entries are only redelivered if the server was started with an ack timeout
+            None => Err(ActionError::Unimplemented),
+        }
+    }
+}
+
+impl Action for GetEntryByKey {
+    fn process(self, conn: &mut Connection, frontend: &mut Core<EntryData>) -> ActionResult {
+        match frontend.get_entry_by_key(entry_key(self.tag(), self.key()))? {
+            Some(entry) => Ok(Message::EntryInfo(entry_info(conn.precision(), entry))),
+            None => Err(ActionError::EntryNotFound),
+        }
+    }
+}
+
+impl Action for RemoveEntryByKey {
+    fn process(self, conn: &mut Connection, frontend: &mut Core<EntryData>) -> ActionResult {
+        match frontend.remove_entry_by_key(entry_key(self.tag(), self.key()))? {
+            Some(entry) => {
+                conn.disown(entry.id());
+
+                let data = entry.consume_data();
+
+                Ok(Message::EntryRemoved(EntryRemoved::new(data.tag(), data.consume_data())))
+            }
+            None => Err(ActionError::EntryNotFound),
+        }
+    }
+}
+
+/// Keys are unique per tag, so the tag is part of the key the entry is stored with
+fn entry_key(tag: u64, key: &[u8]) -> EntryKey {
+    let mut bytes = Vec::with_capacity(8 + key.len());
+
+    bytes.extend_from_slice(&tag.to_be_bytes());
+    bytes.extend_from_slice(key);
+
+    EntryKey::new(bytes)
+}
+
+/// Converts a timestamp given in the precision of a connection, rejecting timestamps that are out of range
+fn to_timestamp(precision: Precision, timestamp: i64) -> Result<Timestamp, ActionError> {
+    precision.to_millis(timestamp).map(Timestamp::from_millis).ok_or(ActionError::InvalidArgument)
+}
+
+fn entry_info(precision: Precision, entry: Entry<EntryData>) -> EntryInfo {
+    let id = entry.id();
+    let data = entry.consume_data();
+
+    EntryInfo::new(precision.from_millis(id.timestamp().millis()), id.id(), data.tag(), data.consume_data())
+}
+
 impl Action for Message {
     fn process(self, conn: &mut Connection, frontend: &mut Core<EntryData>) -> ActionResult {
         if !self.is_command() {
@@ -94,9 +404,128 @@ impl Action for Message {
         match self {
             Message::Ping => Ok(Message::Pong),
             Message::SetWatchMode(msg) => msg.process(conn, frontend),
+            Message::SetPrecision(msg) => msg.process(conn, frontend),
             Message::AddEntry(msg) => msg.process(conn, frontend),
+            Message::AddEphemeralEntry(msg) => msg.process(conn, frontend),
+            Message::UpsertEntry(msg) => msg.process(conn, frontend),
+            Message::AddRecurringEntry(msg) => msg.process(conn, frontend),
+            Message::AddCronEntry(msg) => msg.process(conn, frontend),
             Message::RemoveEntry(msg) => msg.process(conn, frontend),
+            Message::AddEntries(msg) => msg.process(conn, frontend),
+            Message::RemoveEntries(msg) => msg.process(conn, frontend),
+            Message::RescheduleEntry(msg) => msg.process(conn, frontend),
+            Message::GetEntry(msg) => msg.process(conn, frontend),
+            Message::ListEntries(msg) => msg.process(conn, frontend),
+            Message::AckExpired(msg) => msg.process(conn, frontend),
+            Message::GetEntryByKey(msg) => msg.process(conn, frontend),
+            Message::RemoveEntryByKey(msg) => msg.process(conn, frontend),
+            Message::CountEntries => Ok(Message::EntryCount(EntryCount::new(frontend.count()? as u64))),
+            Message::Snapshot => {
+                frontend.snapshot()?;
+                Ok(Message::Ok)
+            }
             _ => Err(ActionError::Unimplemented)
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::TcpListener;
+    use mio::tcp::TcpStream;
+    use libradium::{BTreeStorage, Listener, ManualClock};
+    use radium_protocol::messages::AddEntry;
+    use super::*;
+    use super::super::owner::Owners;
+
+    struct NoopListener;
+
+    impl Listener<EntryData> for NoopListener {
+        fn on_expired(&self, _: Vec<Entry<EntryData>>, _: Timestamp) {}
+    }
+
+    /// Returns a connection in millisecond precision and the listener that keeps it open
+    fn connect(owners: &Owners) -> (Connection, TcpListener) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let sock = TcpStream::connect(&listener.local_addr().unwrap()).unwrap();
+        let mut conn = Connection::new(sock, None, owners.owner());
+
+        conn.set_precision(Precision::Milliseconds);
+
+        (conn, listener)
+    }
+
+    fn upsert(conn: &mut Connection, frontend: &mut Core<EntryData>, key: &[u8], on_conflict: ConflictPolicy) -> EntryId {
+        let msg = UpsertEntry::new(key.to_vec(), on_conflict, AddEntry::new(1000, 7, vec![])).with_ephemeral(true);
+
+        match msg.process(conn, frontend) {
+            Ok(Message::EntryAdded(added)) => EntryId::new(Timestamp::from_millis(added.timestamp()), added.id()),
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_upsert_ephemeral_shards() {
+        let mut frontend = Core::builder()
+            .shards(4, |_| BTreeStorage::new())
+            .clock(ManualClock::new(Timestamp::from_millis(0)))
+            .spawn(NoopListener);
+        let owners = Owners::new();
+        let (mut owner, _owner_listener) = connect(&owners);
+        let (mut other, _other_listener) = connect(&owners);
+
+        // Most keys belong to another shard than the generated id, so the entries are added with a new id
+        let mut added: Vec<EntryId> = (0..8u8)
+            .map(|key| upsert(&mut owner, &mut frontend, &[key], ConflictPolicy::Replace))
+            .collect();
+        let mut owned = owner.take_owned();
+
+        added.sort();
+        owned.sort();
+
+        assert_eq!(added, owned);
+
+        for &id in &added {
+            assert!(frontend.get_entry(id).unwrap().is_some());
+            owner.own(id);
+        }
+
+        // The kept entry remains owned by the connection that added it
+        let kept = upsert(&mut other, &mut frontend, &[0], ConflictPolicy::KeepExisting);
+
+        assert!(added.contains(&kept));
+        assert!(other.take_owned().is_empty());
+
+        // The replacing entry is owned by the connection that replaced the other one
+        let replaced = upsert(&mut other, &mut frontend, &[0], ConflictPolicy::Replace);
+
+        assert_eq!(vec![replaced], other.take_owned());
+        assert!(frontend.get_entry(kept).unwrap().is_none());
+        assert_eq!(8, frontend.count().unwrap());
+    }
+
+    #[test]
+    fn test_reschedule_ephemeral() {
+        let mut frontend = Core::builder()
+            .clock(ManualClock::new(Timestamp::from_millis(0)))
+            .spawn(NoopListener);
+        let owners = Owners::new();
+        let (mut owner, _owner_listener) = connect(&owners);
+        let (mut other, _other_listener) = connect(&owners);
+
+        let id = match AddEphemeralEntry::new(AddEntry::new(1000, 7, vec![])).process(&mut owner, &mut frontend) {
+            Ok(Message::EntryAdded(added)) => EntryId::new(Timestamp::from_millis(added.timestamp()), added.id()),
+            result => panic!("unexpected result {:?}", result),
+        };
+
+        // The entry remains owned by the connection that added it, although another connection rescheduled it
+        let new_id = match RescheduleEntry::new(1000, id.id(), 5000).process(&mut other, &mut frontend) {
+            Ok(Message::EntryAdded(added)) => EntryId::new(Timestamp::from_millis(added.timestamp()), added.id()),
+            result => panic!("unexpected result {:?}", result),
+        };
+
+        assert!(other.take_owned().is_empty());
+        assert_eq!(vec![new_id], owner.take_owned());
+        assert!(frontend.get_entry(new_id).unwrap().is_some());
+    }
+}
@@ -4,16 +4,23 @@ use mio::{Evented, Poll, Token, Ready, PollOpt};
 use mio::tcp::TcpStream;
 use slab::{Slab, IterMut};
 use std::collections::VecDeque;
-use radium_protocol::{WatchMode, ReaderController, Message, MessageReader, ReaderStatus, WriteValueExt};
+use libradium::EntryId;
+use radium_protocol::{WatchMode, Precision, ReaderController, Message, MessageReader, ReaderStatus, WriteValueExt};
 use radium_protocol::errors::WriteError;
+use super::delivery::Deliveries;
+use super::owner::Owner;
 pub use self::AddConnResult::{Added, Rejected};
 
 #[derive(Debug)]
 pub struct Connection {
     sock: TcpStream,
     watch_mode: WatchMode,
+    precision: Precision,
     reader: ReaderController<Message, MessageReader>,
     write_queue: VecDeque<Message>,
+    deliveries: Option<Deliveries>,
+    /// Owns the ephemeral entries that are removed when the connection is closed
+    owner: Owner,
 }
 
 pub enum AddConnResult<'a> {
@@ -26,12 +33,16 @@ pub struct Connections {
 }
 
 impl Connection {
-    pub fn new(sock: TcpStream) -> Self {
+    /// Acknowledgements sent over the connection are reported to `deliveries`, if the server redelivers entries
+    pub fn new(sock: TcpStream, deliveries: Option<Deliveries>, owner: Owner) -> Self {
         Connection {
             sock,
             watch_mode: WatchMode::None,
+            precision: Precision::default(),
             reader: ReaderController::new(Message::reader()),
             write_queue: VecDeque::new(),
+            deliveries,
+            owner,
         }
     }
 
@@ -39,8 +50,38 @@ impl Connection {
         self.watch_mode = mode;
     }
 
-    pub fn watch_mode(&self) -> WatchMode {
-        self.watch_mode
+    pub fn watch_mode(&self) -> &WatchMode {
+        &self.watch_mode
+    }
+
+    pub fn deliveries(&self) -> Option<&Deliveries> {
+        self.deliveries.as_ref()
+    }
+
+    pub fn own(&mut self, id: EntryId) {
+        self.owner.own(id);
+    }
+
+    /// Forgets the owner of an entry that has been removed, whichever connection owned it
+    pub fn disown(&mut self, id: EntryId) {
+        self.owner.owners().disown(id);
+    }
+
+    /// Keeps the owner of an entry that has been rescheduled, whichever connection owns it
+    pub fn reschedule_owned(&mut self, id: EntryId, new_id: EntryId) {
+        self.owner.owners().reschedule(id, new_id);
+    }
+
+    pub fn take_owned(&mut self) -> Vec<EntryId> {
+        self.owner.take_owned()
+    }
+
+    pub fn set_precision(&mut self, precision: Precision) {
+        self.precision = precision;
+    }
+
+    pub fn precision(&self) -> Precision {
+        self.precision
     }
 
     pub fn close(&self) -> io::Result<()> {
@@ -76,6 +117,23 @@ impl Connection {
 
         Ok(())
     }
+
+    /// Writes as many queued messages as possible without blocking.
+    /// Returns `true` once there is nothing left to write, also if the connection failed.
+    pub fn flush_writes(&mut self) -> bool {
+        while let Some(msg) = self.write_queue.pop_front() {
+            match self.sock.write_value(&msg) {
+                Ok(()) => {}
+                Err(WriteError::IoError(ref err)) if err.kind() == io::ErrorKind::WouldBlock => {
+                    self.write_queue.push_front(msg);
+                    return false;
+                }
+                Err(..) => return true,
+            }
+        }
+
+        true
+    }
 }
 
 impl Evented for Connection {
@@ -0,0 +1,244 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound::Included;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use libradium::Timestamp;
+use radium_protocol::Precision;
+use super::entry::Entry;
+
+/// Sets when expired entries that were not acknowledged are delivered again
+#[derive(Debug, Copy, Clone)]
+pub struct DeliveryPolicy {
+    /// Time after which an unacknowledged entry is delivered again
+    pub timeout: Duration,
+    /// Number of deliveries after which an entry is moved to the dead letter tag
+    pub max_attempts: u32,
+    pub dead_letter_tag: u64,
+}
+
+#[derive(Debug)]
+struct Delivery {
+    entry: Entry,
+    fired_at: Timestamp,
+    attempts: u32,
+    deadline: Instant,
+}
+
+#[derive(Debug)]
+struct Inner {
+    policy: DeliveryPolicy,
+    /// Keyed by the sequence value and the timestamp of the `EntryId`. A recurring entry keeps its sequence value,
+    /// so each of its occurrences is a separate delivery.
+    pending: BTreeMap<Key, Delivery>,
+    deadlines: BTreeSet<(Instant, Key)>,
+}
+
+type Key = (u64, Timestamp);
+
+/// The expired entries that have been sent to the watchers but were not acknowledged yet.
+///
+/// Entries are delivered again until a watcher acknowledges them. After `max_attempts` deliveries
+/// an entry is moved to the dead letter tag, where it gets another `max_attempts` deliveries before it is dropped.
+/// Pending deliveries are only kept in memory.
+///
+/// All clones share the same set of pending deliveries.
+#[derive(Clone, Debug)]
+pub struct Deliveries {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Deliveries {
+    pub fn new(policy: DeliveryPolicy) -> Self {
+        let inner = Inner {
+            policy,
+            pending: BTreeMap::new(),
+            deadlines: BTreeSet::new(),
+        };
+
+        Deliveries { inner: Arc::new(Mutex::new(inner)) }
+    }
+
+    /// Holds the entries, which are about to be delivered for the first time, until they are acknowledged
+    pub fn track(&self, entries: &[Entry], fired_at: Timestamp) {
+        let mut inner = self.inner.lock().unwrap();
+        let deadline = Instant::now() + inner.policy.timeout;
+
+        for entry in entries {
+            let key = (entry.id().id(), entry.id().timestamp());
+            let delivery = Delivery { entry: entry.clone(), fired_at, attempts: 1, deadline };
+
+            if let Some(previous) = inner.pending.insert(key, delivery) {
+                inner.deadlines.remove(&(previous.deadline, key));
+            }
+
+            inner.deadlines.insert((deadline, key));
+        }
+    }
+
+    /// Returns `false` if the entry was not awaiting an acknowledgement.
+    ///
+    /// The `timestamp` is given in the `precision` of the acknowledging connection
+    /// and has to match the timestamp the entry was delivered with.
+    /// If several occurrences of an entry expired within the same second, a timestamp in seconds acknowledges the earliest.
+    pub fn ack(&self, id: u64, timestamp: i64, precision: Precision) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+
+        let first = match precision.to_millis(timestamp) {
+            Some(first) => first,
+            None => return false,
+        };
+
+        // A timestamp in seconds covers every millisecond of that second
+        let last = precision.to_millis(1).map_or(first, |unit| first.saturating_add(unit - 1));
+        let range = (Included((id, Timestamp::from_millis(first))), Included((id, Timestamp::from_millis(last))));
+
+        let (key, deadline) = match inner.pending.range(range).next() {
+            Some((&key, delivery)) => (key, delivery.deadline),
+            None => return false,
+        };
+
+        inner.pending.remove(&key);
+        inner.deadlines.remove(&(deadline, key));
+
+        true
+    }
+
+    /// Returns the time until the next delivery is due or `None` if no entry awaits an acknowledgement
+    pub fn next_timeout(&self, now: Instant) -> Option<Duration> {
+        let inner = self.inner.lock().unwrap();
+
+        inner.deadlines
+            .iter()
+            .next()
+            .map(|&(deadline, _)| if deadline > now { deadline - now } else { Duration::from_secs(0) })
+    }
+
+    /// Returns the entries that are due to be delivered again together with the time they expired at
+    pub fn due(&self, now: Instant) -> Vec<(Entry, Timestamp)> {
+        let mut inner = self.inner.lock().unwrap();
+        let policy = inner.policy;
+        let mut due = Vec::new();
+
+        while let Some(&(deadline, key)) = inner.deadlines.iter().next() {
+            if deadline > now {
+                break;
+            }
+
+            inner.deadlines.remove(&(deadline, key));
+
+            let mut delivery = match inner.pending.remove(&key) {
+                Some(delivery) => delivery,
+                None => continue,
+            };
+
+            if delivery.attempts >= policy.max_attempts {
+                if delivery.entry.data().tag() == policy.dead_letter_tag {
+                    warn!("Dropping entry {} after {} unacknowledged deliveries", key.0, delivery.attempts);
+                    continue;
+                }
+
+                delivery.entry.data_mut().set_tag(policy.dead_letter_tag);
+                delivery.attempts = 0;
+            }
+
+            delivery.attempts += 1;
+            delivery.deadline = now + policy.timeout;
+
+            due.push((delivery.entry.clone(), delivery.fired_at));
+
+            inner.deadlines.insert((delivery.deadline, key));
+            inner.pending.insert(key, delivery);
+        }
+
+        due
+    }
+
+    /// Returns the number of entries awaiting an acknowledgement
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().pending.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use libradium::EntryId;
+    use super::*;
+    use super::super::entry::EntryData;
+
+    fn deliveries(max_attempts: u32) -> Deliveries {
+        Deliveries::new(DeliveryPolicy { timeout: Duration::from_secs(10), max_attempts, dead_letter_tag: 99 })
+    }
+
+    fn entry(millis: i64, id: u64) -> Entry {
+        Entry::new(EntryId::new(Timestamp::from_millis(millis), id), EntryData::new(1, vec![]))
+    }
+
+    fn tags(due: Vec<(Entry, Timestamp)>) -> Vec<(u64, u64)> {
+        due.iter().map(|&(ref entry, _)| (entry.id().id(), entry.data().tag())).collect()
+    }
+
+    #[test]
+    fn test_ack() {
+        let deliveries = deliveries(3);
+
+        deliveries.track(&[entry(1500, 1), entry(2000, 2)], Timestamp::from_millis(2000));
+
+        assert!(!deliveries.ack(1, 2, Precision::Seconds));
+        assert!(!deliveries.ack(3, 1, Precision::Seconds));
+        assert!(deliveries.ack(1, 1, Precision::Seconds));
+        assert!(!deliveries.ack(1, 1, Precision::Seconds));
+        assert!(deliveries.ack(2, 2000, Precision::Milliseconds));
+        assert_eq!(0, deliveries.len());
+        assert_eq!(None, deliveries.next_timeout(Instant::now()));
+    }
+
+    #[test]
+    fn test_ack_occurrences() {
+        let deliveries = deliveries(3);
+        let now = Instant::now();
+
+        // Two occurrences of a recurring entry, which keeps its sequence value when it re-arms
+        deliveries.track(&[entry(1000, 1)], Timestamp::from_millis(1000));
+        deliveries.track(&[entry(2000, 1)], Timestamp::from_millis(2000));
+
+        assert_eq!(2, deliveries.len());
+        assert!(deliveries.ack(1, 2, Precision::Seconds));
+        assert_eq!(vec![(1, 1)], tags(deliveries.due(now + Duration::from_secs(11))));
+        assert!(deliveries.ack(1, 1000, Precision::Milliseconds));
+        assert_eq!(0, deliveries.len());
+    }
+
+    #[test]
+    fn test_redelivery() {
+        let deliveries = deliveries(3);
+        let now = Instant::now();
+
+        deliveries.track(&[entry(1000, 1), entry(1000, 2)], Timestamp::from_millis(1000));
+        deliveries.ack(2, 1000, Precision::Milliseconds);
+
+        assert!(deliveries.due(now).is_empty());
+        assert!(deliveries.next_timeout(now).is_some());
+
+        let due = deliveries.due(now + Duration::from_secs(11));
+
+        assert_eq!(vec![(1, 1)], tags(due.clone()));
+        assert_eq!(Timestamp::from_millis(1000), due[0].1);
+        assert!(deliveries.due(now + Duration::from_secs(15)).is_empty());
+        assert_eq!(1, deliveries.len());
+    }
+
+    #[test]
+    fn test_dead_letter() {
+        let deliveries = deliveries(2);
+        let now = Instant::now();
+        let after = |secs| now + Duration::from_secs(secs);
+
+        deliveries.track(&[entry(1000, 1)], Timestamp::from_millis(1000));
+
+        assert_eq!(vec![(1, 1)], tags(deliveries.due(after(11))));
+        assert_eq!(vec![(1, 99)], tags(deliveries.due(after(30))));
+        assert_eq!(vec![(1, 99)], tags(deliveries.due(after(50))));
+        assert!(deliveries.due(after(70)).is_empty());
+        assert_eq!(0, deliveries.len());
+    }
+}
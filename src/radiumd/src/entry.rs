@@ -1,4 +1,7 @@
+use std::io;
+use byteorder::{ReadBytesExt, WriteBytesExt, NetworkEndian};
 use libradium;
+use libradium::Payload;
 
 #[derive(Clone, Debug)]
 pub struct EntryData {
@@ -17,7 +20,32 @@ impl EntryData {
         self.tag
     }
 
+    pub fn set_tag(&mut self, tag: u64) {
+        self.tag = tag;
+    }
+
+    /// Returns the size of the data in bytes
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+
     pub fn consume_data(self) -> Vec<u8> {
         self.data
     }
 }
+
+/// tag: u64 | data: (remaining bytes)
+impl Payload for EntryData {
+    fn write_payload<W: io::Write>(&self, target: &mut W) -> io::Result<()> {
+        target.write_u64::<NetworkEndian>(self.tag)?;
+        target.write_all(&self.data)
+    }
+
+    fn read_payload<R: io::Read>(source: &mut R) -> io::Result<Self> {
+        let tag = source.read_u64::<NetworkEndian>()?;
+        let mut data = Vec::new();
+        source.read_to_end(&mut data)?;
+
+        Ok(EntryData::new(tag, data))
+    }
+}
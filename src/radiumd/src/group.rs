@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use mio::Token;
+use super::entry::Entry;
+
+/// A connection that joined a group, identified by the id of its worker and its token
+pub type Member = (usize, Token);
+
+/// Identifies a group by its name and the tag it is limited to, if any
+type GroupKey = (String, Option<u64>);
+
+#[derive(Debug, Default)]
+struct Group {
+    members: Vec<Member>,
+    next: usize,
+}
+
+/// The groups that connections joined with `WatchMode::Group` or `WatchMode::TaggedGroup`, across all workers of the pool.
+///
+/// All clones share the same groups.
+#[derive(Clone, Debug, Default)]
+pub struct Groups {
+    inner: Arc<Mutex<HashMap<GroupKey, Group>>>,
+}
+
+impl Groups {
+    pub fn new() -> Self {
+        Groups::default()
+    }
+
+    pub fn join(&self, name: &str, tag: Option<u64>, member: Member) {
+        let mut groups = self.inner.lock().unwrap();
+
+        groups.entry((name.to_string(), tag)).or_default().members.push(member);
+    }
+
+    pub fn leave(&self, name: &str, tag: Option<u64>, member: Member) {
+        let mut groups = self.inner.lock().unwrap();
+        let key = (name.to_string(), tag);
+
+        let empty = match groups.get_mut(&key) {
+            Some(group) => {
+                if let Some(index) = group.members.iter().position(|&other| other == member) {
+                    group.members.remove(index);
+
+                    // Keeps the turn with the member that was next, rather than skipping it
+                    if index < group.next {
+                        group.next -= 1;
+                    }
+
+                    if group.next >= group.members.len() {
+                        group.next = 0;
+                    }
+                }
+
+                group.members.is_empty()
+            }
+            None => false,
+        };
+
+        if empty {
+            groups.remove(&key);
+        }
+    }
+
+    /// Assigns each entry to one member of every group for its tag, taking turns between the members of a group
+    pub fn assign(&self, entries: &[Entry]) -> HashMap<Member, Vec<Entry>> {
+        let mut groups = self.inner.lock().unwrap();
+        let mut assigned: HashMap<Member, Vec<Entry>> = HashMap::new();
+
+        for (&(_, tag), group) in groups.iter_mut() {
+            let matching = entries.iter().filter(|entry| tag.map_or(true, |tag| entry.data().tag() == tag));
+
+            for entry in matching {
+                let member = group.members[group.next % group.members.len()];
+                group.next = (group.next + 1) % group.members.len();
+
+                assigned.entry(member).or_default().push(entry.clone());
+            }
+        }
+
+        assigned
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use libradium::{EntryId, Timestamp};
+    use super::*;
+    use super::super::entry::EntryData;
+
+    fn entry(id: u64, tag: u64) -> Entry {
+        Entry::new(EntryId::new(Timestamp::from_millis(1000), id), EntryData::new(tag, vec![]))
+    }
+
+    fn assigned(groups: &Groups, entries: &[Entry], member: Member) -> Vec<u64> {
+        groups.assign(entries)
+            .remove(&member)
+            .unwrap_or_default()
+            .iter()
+            .map(|entry| entry.id().id())
+            .collect()
+    }
+
+    #[test]
+    fn test_assign() {
+        let groups = Groups::new();
+        let entries = vec![entry(1, 5), entry(2, 6), entry(3, 5), entry(4, 5)];
+
+        groups.join("jobs", None, (0, Token(1)));
+        groups.join("jobs", None, (1, Token(1)));
+        groups.join("jobs", Some(5), (0, Token(2)));
+
+        let mut assigned = groups.assign(&entries);
+        let ids = |entries: Vec<Entry>| entries.iter().map(|entry| entry.id().id()).collect::<Vec<_>>();
+
+        assert_eq!(vec![1, 3], ids(assigned.remove(&(0, Token(1))).unwrap()));
+        assert_eq!(vec![2, 4], ids(assigned.remove(&(1, Token(1))).unwrap()));
+        assert_eq!(vec![1, 3, 4], ids(assigned.remove(&(0, Token(2))).unwrap()));
+        assert!(assigned.is_empty());
+    }
+
+    #[test]
+    fn test_leave() {
+        let groups = Groups::new();
+        let entries = vec![entry(1, 5), entry(2, 5)];
+
+        groups.join("jobs", Some(5), (0, Token(1)));
+        groups.join("jobs", Some(5), (0, Token(2)));
+        groups.join("jobs", Some(5), (1, Token(1)));
+
+        assert_eq!(vec![1], assigned(&groups, &entries, (0, Token(1))));
+
+        // The member that left had its turn already, so the next one keeps its turn
+        groups.leave("jobs", Some(5), (0, Token(2)));
+
+        assert_eq!(vec![2], assigned(&groups, &entries, (0, Token(1))));
+
+        groups.leave("jobs", Some(5), (1, Token(1)));
+
+        assert_eq!(vec![1, 2], assigned(&groups, &entries, (0, Token(1))));
+
+        groups.leave("jobs", Some(5), (0, Token(1)));
+
+        assert!(groups.assign(&entries).is_empty());
+    }
+}
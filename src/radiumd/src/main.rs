@@ -1,3 +1,4 @@
+extern crate byteorder;
 extern crate libradium;
 extern crate getopts;
 #[macro_use]
@@ -5,37 +6,196 @@ extern crate log;
 extern crate mio;
 extern crate mio_channel;
 extern crate radium_protocol;
+extern crate signal_hook;
 extern crate slab;
 
 #[macro_use]
 mod macros;
 mod actions;
 mod connection;
+mod delivery;
+mod group;
+mod owner;
 mod server;
 mod logger;
 mod pool;
 mod entry;
 mod worker;
 
-use getopts::Options;
-use libradium::{Core, Listener};
+use getopts::{Options, Matches};
+use libradium::{Core, Listener, Storage, BTreeStorage, TimingWheel, PersistentStorage, SyncPolicy, SnapshotPolicy};
+use libradium::{Capacity, OverflowPolicy, ShutdownPolicy, Timestamp};
 use logger::Logger;
 use mio_channel::{channel, Sender};
 use mio::tcp::TcpListener;
 use pool::Pool;
 use std::env;
+use std::fmt;
+use std::process;
+use std::time::Duration;
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 
 use self::server::Server;
 use self::entry::{Entry, EntryData};
+use self::delivery::{Deliveries, DeliveryPolicy};
 
 struct EntryListener {
-    sender: Sender<Vec<Entry>>
+    sender: Sender<(Vec<Entry>, Timestamp)>
 }
 
 impl Listener<EntryData> for EntryListener {
-    fn on_expired(&self, entry: Vec<Entry>) {
-        self.sender.send(entry).unwrap();
+    fn on_expired(&self, entries: Vec<Entry>, fired_at: Timestamp) {
+        // The server stops receiving when it shuts down, after which the entries can't be delivered anymore
+        if self.sender.send((entries, fired_at)).is_err() {
+            warn!("Unable to deliver expired entries after the server has stopped");
+        }
+    }
+}
+
+/// Prints the error and exits
+fn exit_with_error<E: fmt::Display>(err: E) -> ! {
+    eprintln!("{}", err);
+    process::exit(1);
+}
+
+fn parse_sync_policy(value: &str) -> Option<SyncPolicy> {
+    match value {
+        "always" => Some(SyncPolicy::Always),
+        "never" => Some(SyncPolicy::Never),
+        _ => value.parse().ok().map(|millis| SyncPolicy::Interval(Duration::from_millis(millis))),
+    }
+}
+
+fn parse_snapshot_policy(matches: &Matches) -> SnapshotPolicy {
+    let interval = matches.opt_str("snapshot-interval").map(|val| match val.parse() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => exit_with_error(format!("Invalid snapshot interval {:?}", val)),
+    });
+
+    let max_journal_len = matches.opt_str("snapshot-threshold").map(|val| match val.parse() {
+        Ok(bytes) => bytes,
+        Err(_) => exit_with_error(format!("Invalid snapshot threshold {:?}", val)),
+    });
+
+    SnapshotPolicy { interval, max_journal_len }
+}
+
+/// Returns the capacity given on the command line or `None` if the storage is unlimited
+fn parse_capacity(matches: &Matches) -> Option<Capacity<EntryData>> {
+    let mut capacity = Capacity::new(EntryData::size);
+    let mut limited = false;
+
+    if let Some(val) = matches.opt_str("max-entries") {
+        match val.parse() {
+            Ok(max) => capacity = capacity.max_entries(max),
+            Err(_) => exit_with_error(format!("Invalid entry limit {:?}", val)),
+        }
+
+        limited = true;
+    }
+
+    if let Some(val) = matches.opt_str("max-bytes") {
+        match val.parse() {
+            Ok(max) => capacity = capacity.max_bytes(max),
+            Err(_) => exit_with_error(format!("Invalid byte limit {:?}", val)),
+        }
+
+        limited = true;
+    }
+
+    match matches.opt_str("when-full") {
+        None => {}
+        Some(ref val) if val == "reject" => capacity = capacity.policy(OverflowPolicy::Reject),
+        Some(ref val) if val == "expire" => capacity = capacity.policy(OverflowPolicy::ExpireNext),
+        Some(val) => exit_with_error(format!("Unknown policy {:?}", val)),
+    }
+
+    if limited { Some(capacity) } else { None }
+}
+
+/// Entries are persisted by default if there is a data directory and dropped otherwise
+fn parse_shutdown_policy(matches: &Matches) -> ShutdownPolicy {
+    match matches.opt_str("shutdown") {
+        None if matches.opt_present("d") => ShutdownPolicy::Persist,
+        None => ShutdownPolicy::Return,
+        Some(ref val) if val == "persist" => ShutdownPolicy::Persist,
+        Some(ref val) if val == "expire" => ShutdownPolicy::ExpireAll,
+        Some(ref val) if val == "drop" => ShutdownPolicy::Return,
+        Some(val) => exit_with_error(format!("Unknown shutdown policy {:?}", val)),
+    }
+}
+
+/// Returns the number of shards given on the command line, defaulting to a single shard
+fn parse_shards(matches: &Matches) -> usize {
+    match matches.opt_str("shards") {
+        None => 1,
+        Some(val) => match val.parse() {
+            Ok(shards) if shards > 0 => shards,
+            _ => exit_with_error(format!("Invalid number of shards {:?}", val)),
+        },
+    }
+}
+
+/// Returns the policy for redelivering unacknowledged entries or `None` if watchers don't have to acknowledge entries
+fn parse_delivery_policy(matches: &Matches) -> Option<DeliveryPolicy> {
+    let timeout = match matches.opt_str("ack-timeout") {
+        None => return None,
+        Some(val) => match val.parse() {
+            Ok(millis) if millis > 0 => Duration::from_millis(millis),
+            _ => exit_with_error(format!("Invalid ack timeout {:?}", val)),
+        },
+    };
+
+    let max_attempts = match matches.opt_str("max-attempts") {
+        None => 5,
+        Some(val) => match val.parse() {
+            Ok(attempts) if attempts > 0 => attempts,
+            _ => exit_with_error(format!("Invalid number of attempts {:?}", val)),
+        },
+    };
+
+    let dead_letter_tag = match matches.opt_str("dead-letter-tag") {
+        None => u64::max_value(),
+        Some(val) => match val.parse() {
+            Ok(tag) => tag,
+            Err(_) => exit_with_error(format!("Invalid dead letter tag {:?}", val)),
+        },
+    };
+
+    Some(DeliveryPolicy { timeout, max_attempts, dead_letter_tag })
+}
+
+/// Opens the storage of one of `shards` shards.
+/// Each shard of a sharded server persists its entries in its own subdirectory of the data dir.
+fn open_storage(matches: &Matches, shards: usize, shard: usize) -> Box<Storage<EntryData>> {
+    let storage: Box<Storage<EntryData>> = match matches.opt_str("S") {
+        None => Box::new(BTreeStorage::new()),
+        Some(ref val) if val == "btree" => Box::new(BTreeStorage::new()),
+        Some(ref val) if val == "wheel" => Box::new(TimingWheel::new()),
+        Some(val) => exit_with_error(format!("Unknown storage backend {:?}", val)),
+    };
+
+    let dir = match matches.opt_str("d") {
+        Some(ref dir) if shards > 1 => Path::new(dir).join(format!("shard-{}", shard)),
+        Some(dir) => PathBuf::from(dir),
+        None => return storage,
+    };
+
+    let policy = match matches.opt_str("fsync") {
+        None => SyncPolicy::Always,
+        Some(val) => match parse_sync_policy(&val) {
+            Some(policy) => policy,
+            None => exit_with_error(format!("Invalid fsync policy {:?}", val)),
+        },
+    };
+
+    match PersistentStorage::open(&dir, policy, storage) {
+        Ok(mut storage) => {
+            storage.set_snapshot_policy(parse_snapshot_policy(matches));
+            Box::new(storage)
+        }
+        Err(err) => exit_with_error(format!("Unable to open data directory {:?}: {}", dir, err)),
     }
 }
 
@@ -47,6 +207,21 @@ fn main() {
 
     opts.optopt("H", "host", "sets the host to listen on", "HOST");
     opts.optopt("P", "port", "set port to listen on", "PORT");
+    opts.optopt("S", "storage", "sets the storage backend (btree, wheel)", "STORAGE");
+    opts.optopt("d", "data-dir", "persists entries in the given directory", "DIR");
+    opts.optopt("", "shards", "partitions the entries across COUNT worker threads, which must not change for a data dir", "COUNT");
+    opts.optopt("", "fsync", "sets when the journal is synced to disk (always, never or an interval in ms)", "POLICY");
+    opts.optopt("", "snapshot-interval", "writes a snapshot and compacts the journal every SECS seconds", "SECS");
+    opts.optopt("", "snapshot-threshold", "writes a snapshot once the journal exceeds BYTES bytes", "BYTES");
+    opts.optopt("", "max-entries", "limits the number of pending entries", "COUNT");
+    opts.optopt("", "max-bytes", "limits the total size of the data of all pending entries", "BYTES");
+    opts.optopt("", "when-full", "sets how new entries are handled at the limit (reject, expire)", "POLICY");
+    opts.optopt("", "queue-size", "limits the number of pending commands, clients get a retryable error beyond it", "COUNT");
+    opts.optopt("", "ack-timeout", "delivers expired entries again if no watcher acknowledges them within MS ms", "MS");
+    opts.optopt("", "max-attempts", "moves entries to the dead letter tag after COUNT unacknowledged deliveries (default 5)", "COUNT");
+    opts.optopt("", "dead-letter-tag", "sets the tag of entries that were never acknowledged (default 18446744073709551615)", "TAG");
+    opts.optflag("", "notify-disconnected", "notifies watchers when ephemeral entries are removed because their connection was closed");
+    opts.optopt("", "shutdown", "sets what happens to pending entries on shutdown (persist, expire, drop)", "POLICY");
     opts.optflag("h", "help", "print this help menu");
 
     let matches = opts.parse(&args[1..]).unwrap();
@@ -70,13 +245,30 @@ fn main() {
     // TODO: cli flags --host, --port, --verbose
 
     let (sender, receiver) = channel();
-    let core = Core::spawn(EntryListener { sender });
+    let listener = EntryListener { sender };
+
+    let shards = parse_shards(&matches);
+    let mut builder = Core::builder().shards(shards, |shard| open_storage(&matches, shards, shard));
+
+    if let Some(val) = matches.opt_str("queue-size") {
+        match val.parse() {
+            Ok(queue_len) => builder = builder.queue_len(queue_len),
+            Err(_) => exit_with_error(format!("Invalid queue size {:?}", val)),
+        }
+    }
+
+    let core = builder.spawn(listener);
+
+    if let Some(capacity) = parse_capacity(&matches) {
+        core.set_capacity(capacity).unwrap();
+    }
 
     Logger::init().unwrap();
 
     // TODO: use cores instead of hardcoded value
-    let pool = Pool::build(core, 4);
-    let mut server: Server = Server::new(tcp, receiver, pool).unwrap();
+    let pool = Pool::build(core.clone(), 4, matches.opt_present("notify-disconnected"));
+    let deliveries = parse_delivery_policy(&matches).map(Deliveries::new);
+    let mut server: Server = Server::new(tcp, receiver, pool, core, parse_shutdown_policy(&matches), deliveries).unwrap();
 
     server.run().unwrap();
 }
\ No newline at end of file
@@ -0,0 +1,137 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use libradium::EntryId;
+
+#[derive(Debug, Default)]
+struct Inner {
+    next_owner: u64,
+    /// The owner and the current id of each ephemeral entry, keyed by the sequence value of the `EntryId`,
+    /// which doesn't change when the entry is rescheduled
+    entries: HashMap<u64, (u64, EntryId)>,
+    /// The sequence values of the entries of each owner
+    owned: HashMap<u64, HashSet<u64>>,
+}
+
+/// The owners of the ephemeral entries, which are removed when the connection that added them is closed.
+///
+/// Ownership follows the entry when it is rescheduled, regardless of the connection that reschedules it.
+///
+/// All clones share the same owners.
+#[derive(Clone, Debug, Default)]
+pub struct Owners {
+    inner: Arc<Mutex<Inner>>,
+}
+
+/// A single owner, usually a connection
+#[derive(Debug)]
+pub struct Owner {
+    id: u64,
+    owners: Owners,
+}
+
+impl Owners {
+    pub fn new() -> Self {
+        Owners::default()
+    }
+
+    pub fn owner(&self) -> Owner {
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.next_owner;
+
+        inner.next_owner += 1;
+
+        Owner { id, owners: self.clone() }
+    }
+
+    /// Forgets the owner of an entry that has been removed or has expired
+    pub fn disown(&self, id: EntryId) {
+        let mut inner = self.inner.lock().unwrap();
+
+        if let Some((owner, _)) = inner.entries.remove(&id.id()) {
+            let empty = match inner.owned.get_mut(&owner) {
+                Some(owned) => {
+                    owned.remove(&id.id());
+                    owned.is_empty()
+                }
+                None => false,
+            };
+
+            if empty {
+                inner.owned.remove(&owner);
+            }
+        }
+    }
+
+    /// Moves the ownership of an entry to the id it received when it was rescheduled
+    pub fn reschedule(&self, id: EntryId, new_id: EntryId) {
+        let mut inner = self.inner.lock().unwrap();
+
+        if let Some(entry) = inner.entries.get_mut(&id.id()) {
+            entry.1 = new_id;
+        }
+    }
+}
+
+impl Owner {
+    pub fn own(&self, id: EntryId) {
+        let mut inner = self.owners.inner.lock().unwrap();
+
+        inner.entries.insert(id.id(), (self.id, id));
+        inner.owned.entry(self.id).or_default().insert(id.id());
+    }
+
+    /// Returns the current ids of the entries of the owner, which doesn't own them afterwards
+    pub fn take_owned(&self) -> Vec<EntryId> {
+        let mut inner = self.owners.inner.lock().unwrap();
+        let owned = inner.owned.remove(&self.id).unwrap_or_default();
+
+        owned
+            .into_iter()
+            .filter_map(|seq| inner.entries.remove(&seq))
+            .map(|(_, id)| id)
+            .collect()
+    }
+
+    pub fn owners(&self) -> &Owners {
+        &self.owners
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use libradium::Timestamp;
+    use super::*;
+
+    fn id(millis: i64, seq: u64) -> EntryId {
+        EntryId::new(Timestamp::from_millis(millis), seq)
+    }
+
+    #[test]
+    fn test_take_owned() {
+        let owners = Owners::new();
+        let first = owners.owner();
+        let second = owners.owner();
+
+        first.own(id(1000, 1));
+        first.own(id(1000, 2));
+        second.own(id(1000, 3));
+        owners.disown(id(1000, 2));
+
+        assert_eq!(vec![id(1000, 1)], first.take_owned());
+        assert!(first.take_owned().is_empty());
+        assert_eq!(vec![id(1000, 3)], second.take_owned());
+    }
+
+    #[test]
+    fn test_reschedule() {
+        let owners = Owners::new();
+        let owner = owners.owner();
+
+        owner.own(id(1000, 1));
+
+        // Rescheduled through another owner, e.g. by another connection
+        owners.owner().owners().reschedule(id(1000, 1), id(5000, 1));
+
+        assert_eq!(vec![id(5000, 1)], owner.take_owned());
+    }
+}
@@ -1,42 +1,65 @@
 use std::io;
+use std::sync::mpsc;
 use std::thread;
-use libradium::Core;
-use mio_channel::{channel, Sender, SendError};
+use libradium::{Core, Timestamp};
+use mio_channel::{channel, Receiver, Sender, SendError};
 use mio::{Poll, Ready, PollOpt};
 use mio::unix::UnixReady;
 use super::connection::Connection;
 use super::entry::{Entry, EntryData};
+use super::group::Groups;
+use super::owner::{Owners, Owner};
 use super::worker::{Worker, WorkerMessage, MESSAGE_TOKEN};
 
-pub fn spawn_worker(id: usize, frontend: Core<EntryData>) -> io::Result<Sender<WorkerMessage>> {
-    let (sender, receiver) = channel::<WorkerMessage>();
-
+pub fn spawn_worker(
+    id: usize,
+    receiver: Receiver<WorkerMessage>,
+    frontend: Core<EntryData>,
+    groups: Groups,
+    peers: Vec<Sender<WorkerMessage>>,
+) -> io::Result<thread::JoinHandle<()>> {
     let poll = Poll::new()?;
     poll.register(&receiver, MESSAGE_TOKEN, Ready::readable() | UnixReady::hup(), PollOpt::edge())?;
 
-    let mut worker = Worker::new(id, poll, receiver, frontend);
+    let mut worker = Worker::new(id, poll, receiver, frontend, groups, peers);
 
-    thread::spawn(move || {
+    let join_handle = thread::spawn(move || {
         worker.run();
     });
 
-    Ok(sender)
+    Ok(join_handle)
 }
 
 pub struct Pool {
     next_worker: usize,
     num_workers: usize,
     workers: Vec<Sender<WorkerMessage>>,
+    join_handles: Vec<thread::JoinHandle<()>>,
+    groups: Groups,
+    owners: Owners,
 }
 
 impl Pool {
-    pub fn build(frontend: Core<EntryData>, num_workers: usize) -> Pool {
+    /// Watchers are notified about ephemeral entries that were removed because their connection was closed
+    /// if `notify_disconnected` is set
+    pub fn build(frontend: Core<EntryData>, num_workers: usize, notify_disconnected: bool) -> Pool {
+        let groups = Groups::new();
+
+        let (workers, receivers): (Vec<_>, Vec<_>) = (0..num_workers)
+            .map(|_| channel::<WorkerMessage>())
+            .unzip();
+
+        // Any worker might have to notify the watchers of all other workers
+        let peers = if notify_disconnected { workers.clone() } else { Vec::new() };
+
         // TODO: don't unwrap here
-        let workers = (0..num_workers)
-            .map(|i| spawn_worker(i, frontend.clone()).unwrap())
+        let join_handles = receivers
+            .into_iter()
+            .enumerate()
+            .map(|(i, receiver)| spawn_worker(i, receiver, frontend.clone(), groups.clone(), peers.clone()).unwrap())
             .collect();
 
-        Pool { workers, num_workers, next_worker: 0 }
+        Pool { workers, join_handles, num_workers, next_worker: 0, groups, owners: Owners::new() }
     }
 
     pub fn register(&mut self, conn: Connection) -> Result<(), SendError<WorkerMessage>> {
@@ -50,15 +73,58 @@ impl Pool {
         Ok(())
     }
 
-    pub fn push_expired(&self, entry: Vec<Entry>) -> Result<(), SendError<WorkerMessage>> {
+    /// Returns a new owner for the ephemeral entries of a connection
+    pub fn owner(&self) -> Owner {
+        self.owners.owner()
+    }
+
+    pub fn push_expired(&self, entry: Vec<Entry>, fired_at: Timestamp) -> Result<(), SendError<WorkerMessage>> {
+        // The owner of an ephemeral entry doesn't have to remove it anymore
+        for expired in &entry {
+            self.owners.disown(expired.id());
+        }
+
         for worker in &self.workers {
             // TODO: we probably shouldn't clone the entry for every thread
-            worker.send(WorkerMessage::Push(entry.clone()))?;
+            worker.send(WorkerMessage::Push(entry.clone(), fired_at))?;
+        }
+
+        // Members of a group can be spread across all workers,
+        // so the entries are assigned here instead of by the workers
+        for ((worker, token), entries) in self.groups.assign(&entry) {
+            self.workers[worker].send(WorkerMessage::PushTo(token, entries, fired_at))?;
         }
 
         Ok(())
     }
 
+    /// Removes the ephemeral entries of all connections, which have to be gone before the core shuts down
+    pub fn release_owned(&self) {
+        let (reply, done) = mpsc::channel();
+
+        for worker in &self.workers {
+            let _ = worker.send(WorkerMessage::ReleaseOwned(reply.clone()));
+        }
+
+        drop(reply);
+
+        // Ends early if a worker has stopped and dropped its reply sender
+        while done.recv().is_ok() {}
+    }
+
+    /// Stops all workers and waits until they have written their pending messages
+    pub fn shutdown(&mut self) {
+        for worker in &self.workers {
+            let _ = worker.send(WorkerMessage::Shutdown);
+        }
+
+        for join_handle in self.join_handles.drain(..) {
+            let _ = join_handle.join();
+        }
+
+        self.workers.clear();
+    }
+
     fn next_worker(&mut self) {
         self.next_worker += 1;
 
@@ -1,29 +1,49 @@
 use super::connection::Connection;
 use std::io;
+use std::time::Instant;
 use mio::{Token, Events, Poll, PollOpt, Ready};
 use mio::tcp::TcpListener;
 use mio_channel::Receiver;
+use libradium::{Core, ShutdownPolicy, Timestamp};
+use signal_hook::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
 
+use super::delivery::Deliveries;
 use super::pool::Pool;
-use super::entry::Entry;
+use super::entry::{Entry, EntryData};
 
 pub const RECEIVER: Token = Token(10_000_001);
 pub const SERVER: Token = Token(10_000_000);
+pub const SIGNALS: Token = Token(10_000_002);
 
 pub struct Server {
     events: Events,
     poll: Poll,
     tcp: TcpListener,
-    receiver: Receiver<Vec<Entry>>,
+    receiver: Receiver<(Vec<Entry>, Timestamp)>,
     pool: Pool,
+    signals: Signals,
+    frontend: Core<EntryData>,
+    shutdown_policy: ShutdownPolicy,
+    deliveries: Option<Deliveries>,
+    stopping: bool,
 }
 
 impl Server {
-    pub fn new(tcp: TcpListener, receiver: Receiver<Vec<Entry>>, pool: Pool) -> io::Result<Self> {
+    pub fn new(
+        tcp: TcpListener,
+        receiver: Receiver<(Vec<Entry>, Timestamp)>,
+        pool: Pool,
+        frontend: Core<EntryData>,
+        shutdown_policy: ShutdownPolicy,
+        deliveries: Option<Deliveries>,
+    ) -> io::Result<Self> {
         let poll = Poll::new()?;
+        let signals = Signals::new([SIGINT, SIGTERM])?;
 
         poll.register(&tcp, SERVER, Ready::readable(), PollOpt::edge())?;
         poll.register(&receiver, RECEIVER, Ready::readable(), PollOpt::edge())?;
+        poll.register(&signals, SIGNALS, Ready::readable(), PollOpt::edge())?;
 
         Ok(Server {
             events: Events::with_capacity(1024),
@@ -31,33 +51,102 @@ impl Server {
             tcp,
             receiver,
             pool,
+            signals,
+            frontend,
+            shutdown_policy,
+            deliveries,
+            stopping: false,
         })
     }
 
+    /// Runs until the process receives SIGINT or SIGTERM
     pub fn run(&mut self) -> io::Result<()> {
-        loop {
+        while !self.stopping {
             self.poll()?;
         }
+
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> io::Result<()> {
+        info!("Shutting down");
+
+        self.poll.deregister(&self.tcp)?;
+
+        // Ephemeral entries must not outlive their connections, even if the pending entries are persisted
+        self.pool.release_owned();
+
+        match self.frontend.shutdown(self.shutdown_policy) {
+            Ok(ref entries) if !entries.is_empty() => warn!("Dropped {} pending entries", entries.len()),
+            Ok(..) => {}
+            Err(err) => error!("Unable to shut down the core: {}", err),
+        }
+
+        // Entries that expired before or during the shutdown of the core
+        // are sent to the clients before their connections are closed
+        while let Ok((entries, fired_at)) = self.receiver.try_recv() {
+            // TODO: proper error handling
+            self.pool.push_expired(entries, fired_at).unwrap();
+        }
+
+        if let Some(ref deliveries) = self.deliveries {
+            let pending = deliveries.len();
+
+            if pending > 0 {
+                warn!("Dropped {} unacknowledged entries", pending);
+            }
+        }
+
+        self.pool.shutdown();
+
+        Ok(())
     }
 
     fn poll(&mut self) -> io::Result<()> {
-        self.poll.poll(&mut self.events, None)?;
+        let timeout = self.deliveries.as_ref().and_then(|deliveries| deliveries.next_timeout(Instant::now()));
+
+        self.poll.poll(&mut self.events, timeout)?;
 
         for i in 0..self.events.len() {
             let event = self.events.get(i).unwrap();
             self.handle_event(event.token());
         }
 
+        self.redeliver();
+
         Ok(())
     }
 
+    /// Sends the entries that were not acknowledged in time to the watchers again
+    fn redeliver(&mut self) {
+        let due = match self.deliveries {
+            Some(ref deliveries) => deliveries.due(Instant::now()),
+            None => return,
+        };
+
+        for (entry, fired_at) in due {
+            // TODO: proper error handling
+            self.pool.push_expired(vec![entry], fired_at).unwrap();
+        }
+    }
+
     fn handle_event(&mut self, token: Token) {
         // TODO: proper error handling
         match token {
             SERVER => self.accept(),
-            RECEIVER => self.pool.push_expired(
-                self.receiver.try_recv().unwrap()
-            ).unwrap(),
+            RECEIVER => {
+                // The receiver is edge-triggered and the shards of the core send independently
+                while let Ok((entries, fired_at)) = self.receiver.try_recv() {
+                    if let Some(ref deliveries) = self.deliveries {
+                        deliveries.track(&entries, fired_at);
+                    }
+
+                    self.pool.push_expired(entries, fired_at).unwrap();
+                }
+            }
+            SIGNALS if self.signals.pending().next().is_some() => {
+                self.stopping = true;
+            }
             _ => {
                 // TODO
             }
@@ -77,6 +166,8 @@ impl Server {
         };
 
         // TODO: proper error handling
-        self.pool.register(Connection::new(stream)).unwrap();
+        let owner = self.pool.owner();
+
+        self.pool.register(Connection::new(stream, self.deliveries.clone(), owner)).unwrap();
     }
 }
\ No newline at end of file
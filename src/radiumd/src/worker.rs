@@ -1,26 +1,41 @@
 use std::io;
 use std::error::Error;
 use std::fmt;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
 
-use libradium::Core;
-use mio_channel::Receiver;
+use libradium::{Core, EntryId, Timestamp};
+use mio_channel::{Receiver, Sender};
 use mio::{Poll, Token, Ready, PollOpt, Events, Event};
 use mio::unix::UnixReady;
 use radium_protocol::{Message, WriteValueExt, ErrorCode};
 use radium_protocol::errors::{ReadError, WriteError};
-use radium_protocol::messages::{EntryExpired, ErrorMessage};
+use radium_protocol::messages::{EntryExpired, EntryInfo, ErrorMessage, OwnerDisconnected};
 
 use super::actions::Action;
 use super::connection::{Connection, Connections, Added, Rejected};
 use super::entry::{Entry, EntryData};
+use super::group::Groups;
 
 pub const MESSAGE_TOKEN: Token = Token(10_000_000);
 pub const DEFAULT_WORKER_CONNECTIONS: usize = 128;
 
+/// Time a worker waits for its connections to become writable when shutting down
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Debug)]
 pub enum WorkerMessage {
     Connection(Connection),
-    Push(Vec<Entry>),
+    /// Sends the entries that the core expired at the given time to the watching connections
+    Push(Vec<Entry>, Timestamp),
+    /// Sends the entries that were assigned to a member of a group to its connection
+    PushTo(Token, Vec<Entry>, Timestamp),
+    /// Notifies the watching connections that the owner of the ephemeral entries has disconnected
+    PushDisconnected(Vec<Entry>),
+    /// Removes the ephemeral entries of all connections and replies once they are removed
+    ReleaseOwned(mpsc::Sender<()>),
+    /// Writes all pending messages, closes all connections and stops the worker
+    Shutdown,
 }
 
 #[derive(Debug)]
@@ -38,6 +53,10 @@ pub struct Worker {
     poll: Poll,
     receiver: Receiver<WorkerMessage>,
     frontend: Core<EntryData>,
+    groups: Groups,
+    /// The workers that are notified when ephemeral entries are removed, empty if watchers aren't notified
+    peers: Vec<Sender<WorkerMessage>>,
+    stopping: bool,
 }
 
 impl fmt::Display for WorkerError {
@@ -83,7 +102,7 @@ impl From<WriteError> for WorkerError {
 }
 
 impl Worker {
-    pub fn new(id: usize, poll: Poll, receiver: Receiver<WorkerMessage>, frontend: Core<EntryData>) -> Self {
+    pub fn new(id: usize, poll: Poll, receiver: Receiver<WorkerMessage>, frontend: Core<EntryData>, groups: Groups, peers: Vec<Sender<WorkerMessage>>) -> Self {
         let connections = env_var!("RADIUM_WORKER_CONNECTIONS", DEFAULT_WORKER_CONNECTIONS);
 
         Worker {
@@ -92,13 +111,16 @@ impl Worker {
             poll,
             receiver,
             frontend,
+            groups,
+            peers,
+            stopping: false,
         }
     }
 
     pub fn run(&mut self) {
         let mut events = Events::with_capacity(1024);
 
-        loop {
+        while !self.stopping {
             self.poll.poll(&mut events, None).unwrap();
 
             for i in 0..events.len() {
@@ -107,6 +129,33 @@ impl Worker {
                 self.handle_event(event);
             }
         }
+
+        self.flush();
+    }
+
+    /// Writes the queued messages of all connections and closes them,
+    /// giving up on connections that don't become writable in time
+    fn flush(&mut self) {
+        let deadline = Instant::now() + FLUSH_TIMEOUT;
+        let mut events = Events::with_capacity(1024);
+
+        loop {
+            let pending = self.connections
+                .iter_mut()
+                .map(|conn| conn.flush_writes())
+                .filter(|flushed| !flushed)
+                .count();
+
+            if pending == 0 || Instant::now() >= deadline {
+                break;
+            }
+
+            let _ = self.poll.poll(&mut events, Some(Duration::from_millis(10)));
+        }
+
+        for conn in self.connections.iter_mut() {
+            let _ = conn.close();
+        }
     }
 
     fn handle_event(&mut self, event: Event) {
@@ -115,12 +164,22 @@ impl Worker {
         let unix_ready = UnixReady::from(ready);
 
         if token == MESSAGE_TOKEN {
-            let msg = self.receiver.try_recv().unwrap();
-
-            return match msg {
-                WorkerMessage::Connection(conn) => { self.accept(conn) }
-                WorkerMessage::Push(entries) => { self.push(entries) }
+            // The receiver is edge triggered, so all messages that arrived since the last event are read
+            while let Ok(msg) = self.receiver.try_recv() {
+                match msg {
+                    WorkerMessage::Connection(conn) => { self.accept(conn) }
+                    WorkerMessage::Push(entries, fired_at) => { self.push(entries, fired_at) }
+                    WorkerMessage::PushTo(token, entries, fired_at) => { self.push_to(token, entries, fired_at) }
+                    WorkerMessage::PushDisconnected(entries) => { self.push_disconnected(entries) }
+                    WorkerMessage::ReleaseOwned(reply) => {
+                        self.release_owned();
+                        let _ = reply.send(());
+                    }
+                    WorkerMessage::Shutdown => { self.stopping = true }
+                }
             }
+
+            return;
         }
 
         if unix_ready.is_hup() || unix_ready.is_error() {
@@ -159,12 +218,23 @@ impl Worker {
             debug!("worker {}, conn {} | {:?}", self.id, token.0, msg);
 
             let msg_type = msg.message_type();
+            let watch_mode = conn.watch_mode().clone();
 
             let resp: Message = match msg.process(conn, &mut self.frontend) {
                 Ok(resp) => { resp }
                 Err(err) => { err.into() }
             };
 
+            if conn.watch_mode() != &watch_mode {
+                if let Some((name, tag)) = watch_mode.group() {
+                    self.groups.leave(name, tag, (self.id, token));
+                }
+
+                if let Some((name, tag)) = conn.watch_mode().group() {
+                    self.groups.join(name, tag, (self.id, token));
+                }
+            }
+
             debug!("worker {}, conn {} | {:?} -> {:?}", self.id, token.0, msg_type, resp.message_type());
 
             conn.write_message(resp)?;
@@ -188,24 +258,93 @@ impl Worker {
         };
     }
 
-    fn push(&mut self, entries: Vec<Entry>) {
+    fn push(&mut self, entries: Vec<Entry>, fired_at: Timestamp) {
         for entry in entries {
             let id = entry.id();
+            let occurrence = entry.occurrence();
             let tag = entry.data().tag();
-            let inner = EntryExpired::new(id.timestamp(), id.id(), tag, entry.consume_data().consume_data());
-            let msg = Message::EntryExpired(inner);
+            let data = entry.consume_data().consume_data();
 
             let conns = self.connections
                 .iter_mut()
                 .filter(|conn| conn.watch_mode().matches_tag(tag));
 
             for conn in conns {
-                // TODO: I don't want to clone messages but it's easier than a ref inside Connection
-                let _ = conn.write_message(msg.clone());
+                // TODO: I don't want to clone the data but it's easier than a ref inside Connection
+                write_expired(conn, id, occurrence, tag, data.clone(), fired_at);
             }
         }
     }
 
+    fn push_to(&mut self, token: Token, entries: Vec<Entry>, fired_at: Timestamp) {
+        // The entries are lost if the connection was closed after they had been assigned to it
+        let conn = match self.connections.get_conn_mut(token) {
+            Some(conn) => conn,
+            None => return,
+        };
+
+        for entry in entries {
+            let id = entry.id();
+            let occurrence = entry.occurrence();
+            let tag = entry.data().tag();
+
+            write_expired(conn, id, occurrence, tag, entry.consume_data().consume_data(), fired_at);
+        }
+    }
+
+    fn push_disconnected(&mut self, entries: Vec<Entry>) {
+        for entry in entries {
+            let id = entry.id();
+            let data = entry.consume_data();
+            let tag = data.tag();
+            let data = data.consume_data();
+
+            // Every member of a group is notified, as the entry could have been assigned to any of them
+            let conns = self.connections
+                .iter_mut()
+                .filter(|conn| conn.watch_mode().receives_tag(tag));
+
+            for conn in conns {
+                let timestamp = conn.precision().from_millis(id.timestamp().millis());
+                let msg = OwnerDisconnected::new(EntryInfo::new(timestamp, id.id(), tag, data.clone()));
+
+                let _ = conn.write_message(Message::OwnerDisconnected(msg));
+            }
+        }
+    }
+
+    fn release_owned(&mut self) {
+        let owned = self.connections
+            .iter_mut()
+            .flat_map(|conn| conn.take_owned())
+            .collect();
+
+        self.remove_owned(owned);
+    }
+
+    /// Removes the ephemeral entries, which are owned by closed connections
+    fn remove_owned(&mut self, owned: Vec<EntryId>) {
+        if owned.is_empty() {
+            return;
+        }
+
+        let removed: Vec<Entry> = match self.frontend.remove_entries(owned) {
+            Ok(removed) => removed.into_iter().flatten().collect(),
+            Err(err) => {
+                error!("worker {} | Unable to remove ephemeral entries: {}", self.id, err);
+                return;
+            }
+        };
+
+        if removed.is_empty() {
+            return;
+        }
+
+        for peer in &self.peers {
+            let _ = peer.send(WorkerMessage::PushDisconnected(removed.clone()));
+        }
+    }
+
     fn disconnect(&mut self, token: Token, code: Option<ErrorCode>) -> WorkerResult<()> {
         let conn = self.connections.remove_conn(token);
 
@@ -213,6 +352,13 @@ impl Worker {
             Some(mut conn) => {
                 self.poll.deregister(&conn)?;
 
+                if let Some((name, tag)) = conn.watch_mode().group() {
+                    self.groups.leave(name, tag, (self.id, token));
+                }
+
+                let owned = conn.take_owned();
+                self.remove_owned(owned);
+
                 // We're intentionally ignoring the result here
                 // don't need the guarantee that the error code has come through
                 if let Some(code) = code {
@@ -226,4 +372,14 @@ impl Worker {
             None => { Ok(()) }
         }
     }
-}
\ No newline at end of file
+}
+
+fn write_expired(conn: &mut Connection, id: EntryId, occurrence: u32, tag: u64, data: Vec<u8>, fired_at: Timestamp) {
+    // The timestamp has to be converted for every connection, as each one might use a different precision
+    let precision = conn.precision();
+    let timestamp = precision.from_millis(id.timestamp().millis());
+    let msg = EntryExpired::new(timestamp, id.id(), occurrence, tag, data)
+        .with_fired_at(precision.from_millis(fired_at.millis()));
+
+    let _ = conn.write_message(Message::EntryExpired(msg));
+}